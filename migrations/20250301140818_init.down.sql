@@ -0,0 +1,3 @@
+DROP TABLE stories;
+
+DROP TABLE users;
\ No newline at end of file
@@ -0,0 +1 @@
+-- The site user is kept: everything posted from a browser belongs to it.
\ No newline at end of file
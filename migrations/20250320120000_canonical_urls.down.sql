@@ -0,0 +1,3 @@
+DROP INDEX stories_canonical_url_idx;
+
+ALTER TABLE stories DROP COLUMN canonical_url;
\ No newline at end of file
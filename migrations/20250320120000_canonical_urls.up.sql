@@ -0,0 +1,3 @@
+ALTER TABLE stories ADD COLUMN canonical_url TEXT;
+
+CREATE INDEX stories_canonical_url_idx ON stories (canonical_url, created_at DESC);
\ No newline at end of file
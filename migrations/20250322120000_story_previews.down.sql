@@ -0,0 +1 @@
+DROP TABLE story_previews;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE story_previews (
+  story_id INTEGER PRIMARY KEY REFERENCES stories(id),
+  title TEXT,
+  description TEXT,
+  image_url TEXT,
+  site_name TEXT,
+  fetched_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE votes;
+
+ALTER TABLE users DROP COLUMN created_at;
\ No newline at end of file
@@ -0,0 +1,16 @@
+ALTER TABLE users ADD COLUMN created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+CREATE TABLE votes (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  story_id INTEGER REFERENCES stories(id),
+  comment_id INTEGER REFERENCES comments(id),
+  value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  CHECK ((story_id IS NULL) <> (comment_id IS NULL))
+);
+
+CREATE UNIQUE INDEX votes_user_story_idx ON votes (user_id, story_id) WHERE story_id IS NOT NULL;
+CREATE UNIQUE INDEX votes_user_comment_idx ON votes (user_id, comment_id) WHERE comment_id IS NOT NULL;
+CREATE INDEX votes_story_idx ON votes (story_id);
+CREATE INDEX votes_comment_idx ON votes (comment_id);
\ No newline at end of file
@@ -0,0 +1,11 @@
+ALTER TABLE stories
+  DROP COLUMN comment_count,
+  DROP COLUMN score,
+  DROP COLUMN text_html;
+
+DROP INDEX users_display_name_idx;
+
+ALTER TABLE users
+  DROP COLUMN banned_at,
+  DROP COLUMN password_hash,
+  DROP COLUMN is_admin;
\ No newline at end of file
@@ -0,0 +1,19 @@
+ALTER TABLE users
+  ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN password_hash TEXT,
+  ADD COLUMN banned_at TIMESTAMP WITH TIME ZONE;
+
+-- Names become unique; later holders of a taken name get their id appended.
+UPDATE users u SET display_name = LEFT(u.display_name, 240) || '-' || u.id
+WHERE EXISTS (SELECT 1 FROM users o WHERE o.display_name = u.display_name AND o.id < u.id);
+
+CREATE UNIQUE INDEX users_display_name_idx ON users (display_name);
+
+ALTER TABLE stories
+  ADD COLUMN text_html TEXT,
+  ADD COLUMN score INTEGER NOT NULL DEFAULT 0,
+  ADD COLUMN comment_count INTEGER NOT NULL DEFAULT 0;
+
+UPDATE stories s SET
+  score = (SELECT COALESCE(SUM(v.value), 0) FROM votes v WHERE v.story_id = s.id),
+  comment_count = (SELECT COUNT(*) FROM comments c WHERE c.story_id = s.id);
\ No newline at end of file
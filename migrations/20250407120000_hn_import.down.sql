@@ -0,0 +1,3 @@
+DROP TABLE hn_items;
+
+DROP TABLE hn_users;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- Maps Hacker News ids onto local rows so repeated imports skip what exists.
+CREATE TABLE hn_users (
+  name TEXT PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE hn_items (
+  hn_id BIGINT PRIMARY KEY,
+  story_id INTEGER NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+  comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE
+);
\ No newline at end of file
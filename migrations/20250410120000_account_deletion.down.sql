@@ -0,0 +1,5 @@
+DELETE FROM users WHERE display_name = '[deleted]'
+  AND NOT EXISTS (SELECT 1 FROM stories WHERE author_id = users.id)
+  AND NOT EXISTS (SELECT 1 FROM comments WHERE author_id = users.id);
+
+DROP TABLE audit_log;
\ No newline at end of file
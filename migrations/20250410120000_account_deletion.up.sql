@@ -0,0 +1,16 @@
+-- Who did what to whom; actor_id is NULL for operators and the system.
+CREATE TABLE audit_log (
+  id BIGSERIAL PRIMARY KEY,
+  actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  action TEXT NOT NULL,
+  subject_id INTEGER,
+  details JSONB NOT NULL DEFAULT '{}',
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX audit_log_subject_idx ON audit_log (subject_id, created_at DESC);
+
+-- Deleted accounts' stories and comments are reassigned here.
+INSERT INTO users (display_name, banned_at, created_at)
+VALUES ('[deleted]', NOW(), NOW())
+ON CONFLICT (display_name) DO NOTHING;
\ No newline at end of file
@@ -0,0 +1,15 @@
+-- Replies and mentions waiting for a user; one per recipient and comment.
+CREATE TABLE notifications (
+  id BIGSERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  kind TEXT NOT NULL CHECK (kind IN ('story_reply', 'comment_reply', 'mention')),
+  actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  story_id INTEGER NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+  comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  read_at TIMESTAMP WITH TIME ZONE,
+  UNIQUE NULLS NOT DISTINCT (user_id, story_id, comment_id)
+);
+
+CREATE INDEX notifications_user_idx ON notifications (user_id, created_at DESC);
+CREATE INDEX notifications_unread_idx ON notifications (user_id) WHERE read_at IS NULL;
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP VIEW comment_mentions;
+DROP TYPE mention;
+DROP TABLE mentions;
\ No newline at end of file
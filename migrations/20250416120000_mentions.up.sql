@@ -0,0 +1,23 @@
+-- Resolved when a story or comment is written, so renaming a user can't point
+-- an existing mention at someone else.
+CREATE TABLE mentions (
+  id BIGSERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  story_id INTEGER NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+  comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  UNIQUE NULLS NOT DISTINCT (story_id, comment_id, name)
+);
+
+CREATE INDEX mentions_user_idx ON mentions (user_id);
+CREATE INDEX mentions_comment_idx ON mentions (comment_id) WHERE comment_id IS NOT NULL;
+
+-- A mention as written, and the current name of the user it refers to.
+CREATE TYPE mention AS (name TEXT, user_name TEXT);
+
+CREATE VIEW comment_mentions AS
+SELECT m.comment_id, ARRAY_AGG(ROW(m.name, u.display_name)::mention ORDER BY m.id) AS mentions
+FROM mentions m
+JOIN users u ON u.id = m.user_id
+WHERE m.comment_id IS NOT NULL
+GROUP BY m.comment_id;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN favorites_public;
+DROP TABLE bookmarks;
\ No newline at end of file
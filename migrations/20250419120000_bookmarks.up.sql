@@ -0,0 +1,15 @@
+-- Stories and comments a user saved, with an optional private note. A
+-- comment bookmark also records its story.
+CREATE TABLE bookmarks (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  story_id INTEGER NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+  comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+  note TEXT,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  UNIQUE NULLS NOT DISTINCT (user_id, story_id, comment_id)
+);
+
+CREATE INDEX bookmarks_user_idx ON bookmarks (user_id, created_at DESC);
+
+ALTER TABLE users ADD COLUMN favorites_public BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
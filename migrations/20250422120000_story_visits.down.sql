@@ -0,0 +1 @@
+DROP TABLE story_visits;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- When each reader last opened a story, and the stories they hid from the
+-- front page.
+CREATE TABLE story_visits (
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  story_id INTEGER NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+  seen_at TIMESTAMP WITH TIME ZONE,
+  hidden_at TIMESTAMP WITH TIME ZONE,
+  PRIMARY KEY (user_id, story_id)
+);
+
+CREATE INDEX story_visits_hidden_idx ON story_visits (user_id) WHERE hidden_at IS NOT NULL;
\ No newline at end of file
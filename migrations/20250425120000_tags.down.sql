@@ -0,0 +1,4 @@
+DROP VIEW story_tag_names;
+DROP TABLE tag_preferences;
+DROP TABLE story_tags;
+DROP TABLE tags;
\ No newline at end of file
@@ -0,0 +1,38 @@
+-- The curated topic vocabulary. Submitters pick from it; moderators edit it
+-- and retag stories with `news-admin tag`.
+CREATE TABLE tags (
+  id SERIAL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  description TEXT,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO tags (name, description) VALUES
+  ('type-theory', 'Types, proofs and programs'),
+  ('category-theory', 'Objects, arrows and the laws between them'),
+  ('haskell', 'The Haskell language and its ecosystem'),
+  ('rust', 'The Rust language and its ecosystem'),
+  ('ask', 'Questions for everyone'),
+  ('show', 'Things people made');
+
+CREATE TABLE story_tags (
+  story_id INTEGER NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+  tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  PRIMARY KEY (story_id, tag_id)
+);
+
+CREATE INDEX story_tags_tag_idx ON story_tags (tag_id, story_id);
+
+-- Tags each reader follows, or mutes to keep them off their front page.
+CREATE TABLE tag_preferences (
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  muted BOOLEAN NOT NULL,
+  PRIMARY KEY (user_id, tag_id)
+);
+
+CREATE VIEW story_tag_names AS
+SELECT st.story_id, ARRAY_AGG(t.name ORDER BY t.name) AS tags
+FROM story_tags st
+JOIN tags t ON t.id = st.tag_id
+GROUP BY st.story_id;
\ No newline at end of file
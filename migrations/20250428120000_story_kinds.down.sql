@@ -0,0 +1,3 @@
+DROP INDEX stories_kind_idx;
+ALTER TABLE stories DROP COLUMN kind;
+DROP TYPE story_kind;
\ No newline at end of file
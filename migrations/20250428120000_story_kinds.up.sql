@@ -0,0 +1,10 @@
+-- What a story is. Links and job postings point elsewhere; asks and polls are
+-- discussions started here; shows present the submitter's own work.
+CREATE TYPE story_kind AS ENUM ('link', 'ask', 'show', 'poll', 'job');
+
+ALTER TABLE stories ADD COLUMN kind story_kind NOT NULL DEFAULT 'link';
+
+UPDATE stories SET kind = 'show' WHERE url IS NOT NULL AND title ~* '^show [^:]*:';
+UPDATE stories SET kind = 'ask' WHERE url IS NULL;
+
+CREATE INDEX stories_kind_idx ON stories (kind, created_at DESC);
\ No newline at end of file
@@ -0,0 +1,25 @@
+-- Poll stories: their options in order, and each reader's ballot, one row per
+-- chosen option.
+CREATE TABLE polls (
+  story_id INTEGER PRIMARY KEY REFERENCES stories(id) ON DELETE CASCADE,
+  multiple BOOLEAN NOT NULL DEFAULT FALSE,
+  closes_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE TABLE poll_options (
+  id SERIAL PRIMARY KEY,
+  story_id INTEGER NOT NULL REFERENCES polls(story_id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  text TEXT NOT NULL,
+  UNIQUE (story_id, position)
+);
+
+CREATE TABLE ballots (
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  option_id INTEGER NOT NULL REFERENCES poll_options(id) ON DELETE CASCADE,
+  story_id INTEGER NOT NULL REFERENCES polls(story_id) ON DELETE CASCADE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (user_id, option_id)
+);
+
+CREATE INDEX ballots_story_idx ON ballots (story_id, user_id);
\ No newline at end of file
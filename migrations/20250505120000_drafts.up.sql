@@ -0,0 +1,16 @@
+-- Unposted stories and comments, saved as their authors write them. A
+-- story draft has no story; a comment draft names its story and, for a
+-- reply, the comment it answers. Each editor keeps one draft per user.
+CREATE TABLE drafts (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  story_id INTEGER REFERENCES stories(id) ON DELETE CASCADE,
+  parent_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+  title TEXT,
+  url TEXT,
+  text TEXT NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  UNIQUE NULLS NOT DISTINCT (user_id, story_id, parent_id)
+);
+
+CREATE INDEX drafts_user_idx ON drafts (user_id, updated_at DESC);
\ No newline at end of file
@@ -0,0 +1 @@
+-- Which asks were polls or jobs isn't kept, so there is nothing to undo.
\ No newline at end of file
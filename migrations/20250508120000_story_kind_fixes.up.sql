@@ -0,0 +1,5 @@
+-- Hacker News polls were imported without their options and text-only jobs
+-- without a link; both read as asks, which need neither.
+UPDATE stories SET kind = 'ask'
+WHERE (kind = 'poll' AND NOT EXISTS (SELECT 1 FROM polls WHERE polls.story_id = stories.id))
+  OR (kind = 'job' AND url IS NULL);
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- Browser sign-ins. Only a hash of the cookie's secret is kept, as for API
+-- tokens; a session ends when it expires or its user signs out.
+CREATE TABLE sessions (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX sessions_user_idx ON sessions (user_id);
\ No newline at end of file
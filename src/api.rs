@@ -1,206 +1,709 @@
-use crate::{
-    constants::PAGE_SIZE,
-    model::{Comment, CommentCreateArgs, LambdaError, Story, StoryCreateArgs, StoryListItem},
+use crate::model::{
+    ApiToken, ApiTokenCreateArgs, ApiTokenCreated, Bookmark, BookmarkArgs, Comment, CommentCreateArgs, Draft,
+    DraftArgs, Favorites, LinkPreview, Notification, Poll, Profile, Story, StoryCreateArgs, StoryFilter, StoryKind,
+    StoryListItem, StoryVisit, Tag, TagPreference, TagPreferences, TextPreview, VoteTally, Webhook,
+    WebhookCreateArgs, WebhookCreated, WebhookDelivery,
 };
 use leptos::prelude::*;
-use validator::Validate;
 
+/// The front page. Browsers send the stories and tags they keep off it;
+/// token holders' are kept on the server.
 #[server]
-pub async fn story_list(page: Option<i64>) -> Result<Vec<StoryListItem>, ServerFnError> {
-    use crate::server::pool;
-    use sqlx::query_as;
+pub async fn story_list(page: Option<i64>, filter: Option<StoryFilter>) -> Result<Vec<StoryListItem>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo, story_cache, story_filter},
+    };
+
+    let repo = repo()?;
+    let bearer = bearer().await?;
+    bearer.principal().require(TokenScope::Read)?;
+    let filter = story_filter(&repo, &bearer, filter).await?;
+
+    Ok(story_cache().story_page(&*repo, page.unwrap_or(0), &filter).await?)
+}
 
-    let pool = pool()?;
+/// A listing of one kind of stories, e.g. `/ask`.
+#[server]
+pub async fn story_kind_list(
+    kind: StoryKind,
+    page: Option<i64>,
+    filter: Option<StoryFilter>,
+) -> Result<Vec<StoryListItem>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo, story_filter},
+    };
+
+    let repo = repo()?;
+    let bearer = bearer().await?;
+    bearer.principal().require(TokenScope::Read)?;
+    let filter = story_filter(&repo, &bearer, filter).await?;
+
+    Ok(repo.story_kind_page(kind, page.unwrap_or(0), &filter).await?)
+}
 
-    let offset: i64 = page.unwrap_or(0) * PAGE_SIZE;
+#[server]
+pub async fn story_create(story: StoryCreateArgs) -> Result<Story, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo, story_cache, throttle},
+    };
+
+    let repo = repo()?;
+    let bearer = bearer().await?;
+    let author_id = bearer.principal().require(TokenScope::Submit)?;
+    throttle().await?;
+
+    let story = repo.story_insert(story, author_id).await?;
+    story_cache().invalidate().await;
+    if let Some(user_id) = bearer.owner() {
+        repo.draft_discard(user_id, None, None).await?;
+    }
 
-    let stories = query_as!(
-        StoryListItem,
-        r#"
-            SELECT 
-                s.id, 
-                s.title, 
-                s.text, 
-                s.url, 
-                s.created_at, 
-                u.display_name as author_name,
-                0 as rating,
-                (SELECT COUNT(*)::integer FROM comments c WHERE c.story_id = s.id) as comment_count
-            FROM 
-                stories s
-            JOIN 
-                users u ON s.author_id = u.id
-            ORDER BY 
-                s.created_at DESC
-            LIMIT $1 
-            OFFSET $2
-        "#,
-        PAGE_SIZE,
-        offset,
-    )
-    .fetch_all(&pool)
-    .await?;
+    Ok(story)
+}
 
-    Ok(stories)
+#[server]
+pub async fn fetch_link_preview(url: String) -> Result<LinkPreview, ServerFnError> {
+    use crate::{
+        model::{LambdaError, TokenScope},
+        server::{authorize, preview_fetcher, throttle},
+    };
+
+    // Each preview is an outbound request, so it costs like a write.
+    authorize(TokenScope::Submit).await?;
+    throttle().await?;
+    let preview = preview_fetcher()
+        .fetch(&url)
+        .await
+        .map_err(|err| LambdaError::InvalidData(err.to_string()))?;
+
+    Ok(preview)
 }
 
+/// Renders an editor's text the way posting it would, mentions included.
 #[server]
-pub async fn story_create(story: StoryCreateArgs) -> Result<Story, ServerFnError> {
-    use crate::server::pool;
-    use chrono::Local;
-    use sqlx::query_as;
+pub async fn preview_text(text: String) -> Result<TextPreview, ServerFnError> {
+    use crate::{
+        features::ui::markdown::render_markdown,
+        model::TokenScope,
+        server::{authorize, mentions, pool},
+    };
 
     let pool = pool()?;
-    let timestamp = Local::now();
+    authorize(TokenScope::Read).await?;
 
-    story.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+    let mentions: Vec<_> = mentions::resolve(&pool, &text)
+        .await?
+        .into_iter()
+        .map(|resolved| resolved.mention)
+        .collect();
 
-    let result = query_as!(Story, r#"INSERT INTO stories (title, text, url, author_id, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING *"#, story.title, story.text, story.url, 1, timestamp.into())
-        .fetch_one(&pool)
-        .await?;
+    Ok(TextPreview {
+        html: render_markdown(&text, &mentions),
+        mentions,
+    })
+}
 
-    Ok(result)
+#[server]
+pub async fn story_find_duplicate(url: String) -> Result<Option<Story>, ServerFnError> {
+    use crate::{
+        features::canonical::canonicalize_url,
+        model::TokenScope,
+        server::{authorize, repo},
+    };
+
+    let repo = repo()?;
+    authorize(TokenScope::Read).await?;
+
+    let Some(canonical_url) = canonicalize_url(&url) else {
+        return Ok(None);
+    };
+
+    Ok(repo.story_duplicate(&canonical_url).await?)
 }
 
 #[server]
 pub async fn story_get(id: i32) -> Result<Story, ServerFnError> {
-    use crate::server::pool;
-    use sqlx::query_as;
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo},
+    };
 
-    let pool = pool()?;
+    let repo = repo()?;
+    authorize(TokenScope::Read).await?;
 
-    let story = query_as!(Story, r#"SELECT * FROM stories WHERE id = $1"#, id)
-        .fetch_one(&pool)
-        .await?;
+    Ok(repo.story_by_id(id).await?)
+}
 
-    Ok(story)
+#[server]
+pub async fn story_votes(id: i32) -> Result<VoteTally, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo},
+    };
+
+    let repo = repo()?;
+    authorize(TokenScope::Read).await?;
+
+    Ok(repo.vote_tally(Some(id), None).await?)
+}
+
+#[server]
+pub async fn poll_get(story_id: i32) -> Result<Poll, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = authorize(TokenScope::Read).await?;
+
+    Ok(repo.poll(story_id, Some(user_id)).await?)
 }
 
+/// Casts the reader's ballot, replacing their last; no options withdraws it.
 #[server]
-pub async fn get_story_page_count() -> Result<i64, ServerFnError> {
-    use crate::server::pool;
-    use sqlx::query;
+pub async fn poll_vote(story_id: i32, #[server(default)] option_ids: Vec<i32>) -> Result<Poll, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo, throttle},
+    };
+
+    let repo = repo()?;
+    let user_id = authorize(TokenScope::Vote).await?;
+    throttle().await?;
+
+    Ok(repo.poll_vote(story_id, user_id, &option_ids).await?)
+}
+
+/// Marks the story as seen by a token holder, returning when they had last
+/// seen it. Browsers keep their visits locally.
+#[server]
+pub async fn story_mark_seen(id: i32) -> Result<StoryVisit, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(repo.story_visit(user_id, id).await?)
+}
+
+#[server]
+pub async fn story_visits(story_ids: Vec<i32>) -> Result<Vec<StoryVisit>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Read)?;
+
+    Ok(repo.story_visits(user_id, &story_ids).await?)
+}
+
+/// The comments on each story since a browser last opened it, at the
+/// matching `seen_at`. A signed-in user's own comments aren't new to them.
+#[server]
+pub async fn story_new_comments(
+    #[server(default)] story_ids: Vec<i32>,
+    #[server(default)] seen_at: Vec<chrono::DateTime<chrono::FixedOffset>>,
+) -> Result<Vec<StoryVisit>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, pool, visits},
+    };
 
     let pool = pool()?;
+    let bearer = bearer().await?;
+    bearer.principal().require(TokenScope::Read)?;
 
-    let count = query!("SELECT COUNT(*) FROM stories")
-        .fetch_one(&pool)
-        .await?
-        .count;
+    Ok(visits::new_comments(&pool, &story_ids, &seen_at, bearer.session).await?)
+}
 
-    let pages = count.map(|count| count / PAGE_SIZE + 1).unwrap_or(0);
+/// Hides a story from a token holder's listings, or brings it back. Browsers
+/// keep their hidden stories locally.
+#[server]
+pub async fn story_hide(story_id: i32, hidden: bool) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(repo.story_hide(user_id, story_id, hidden).await?)
+}
 
-    Ok(pages)
+#[server]
+pub async fn get_story_page_count(filter: Option<StoryFilter>) -> Result<i64, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo, story_filter},
+    };
+
+    let repo = repo()?;
+    let bearer = bearer().await?;
+    bearer.principal().require(TokenScope::Read)?;
+    let filter = story_filter(&repo, &bearer, filter).await?;
+
+    Ok(repo.story_page_count(&filter).await?)
 }
 
 #[server]
 pub async fn comment_create(comment: CommentCreateArgs) -> Result<(), ServerFnError> {
-    use crate::server::pool;
-    use chrono::Local;
-    use sqlx::query;
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo, throttle},
+    };
+
+    let repo = repo()?;
+    let bearer = bearer().await?;
+    let author_id = bearer.principal().require(TokenScope::Comment)?;
+    throttle().await?;
+
+    let (story_id, parent_id) = (comment.story_id, comment.parent_id);
+    repo.comment_insert(comment, author_id).await?;
+    if let Some(user_id) = bearer.owner() {
+        repo.draft_discard(user_id, Some(story_id), parent_id).await?;
+    }
+
+    Ok(())
+}
+
+#[server]
+pub async fn comment_list(story_id: i32) -> Result<Vec<Comment>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo},
+    };
+
+    let repo = repo()?;
+    authorize(TokenScope::Read).await?;
+
+    Ok(repo.comments_for_story(story_id).await?)
+}
+
+/// Signs a named user in to this browser with their password; from then on
+/// the browser acts as them.
+#[server]
+pub async fn session_create(name: String, password: String) -> Result<(), ServerFnError> {
+    use crate::server::{auth, pool, set_session_cookie, throttle};
 
     let pool = pool()?;
-    let timestamp = Local::now();
+    // Every attempt counts, so passwords can't be guessed quickly.
+    throttle().await?;
+    let user_id = auth::password_login(&pool, &name, &password).await?;
+    let secret = auth::session_create(&pool, user_id).await?;
+    set_session_cookie(Some(&secret));
 
-    query!(
-        r#"INSERT INTO comments (story_id, parent_id, text, author_id, created_at) VALUES ($1, $2, $3, $4, $5)"#,
-        comment.story_id,
-        comment.parent_id,
-        comment.text,
-        1,
-        timestamp.into()
-    )
-    .execute(&pool)
-    .await?;
+    Ok(())
+}
+
+#[server]
+pub async fn session_delete() -> Result<(), ServerFnError> {
+    use crate::server::{auth, pool, set_session_cookie};
+
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+    if let Some(secret) = auth::session_secret(&headers) {
+        auth::session_delete(&pool()?, secret).await?;
+    }
+    set_session_cookie(None);
 
     Ok(())
 }
 
+/// The name of the user signed in to this browser, if any.
 #[server]
-pub async fn comment_list(story_id: i32) -> Result<Vec<Comment>, ServerFnError> {
-    use crate::server::pool;
-    use sqlx::query_as;
+pub async fn session_get() -> Result<Option<String>, ServerFnError> {
+    use crate::server::{auth, pool};
+
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+    let Some(secret) = auth::session_secret(&headers) else {
+        return Ok(None);
+    };
+
+    Ok(auth::session_user(&pool()?, secret).await.ok().map(|user| user.username))
+}
+
+#[server]
+pub async fn api_token_list() -> Result<Vec<ApiToken>, ServerFnError> {
+    use crate::server::{auth::token_list, pool, session_user};
+
+    let pool = pool()?;
+    let user_id = session_user().await?;
+
+    Ok(token_list(&pool, user_id).await?)
+}
+
+#[server]
+pub async fn api_token_create(token: ApiTokenCreateArgs) -> Result<ApiTokenCreated, ServerFnError> {
+    use crate::server::{auth::token_create, pool, session_user, throttle};
 
     let pool = pool()?;
+    let user_id = session_user().await?;
+    throttle().await?;
 
-    let comments = query_as!(
-        Comment,
-        r#"
-            SELECT 
-                c.id,
-                c.text,
-                c.parent_id,
-                c.story_id,
-                c.created_at,
-                u.display_name as author_name
-            FROM comments c
-            JOIN 
-                users u ON c.author_id = u.id
-            WHERE story_id = $1
-            ORDER BY created_at DESC"#,
-        story_id
-    )
-    .fetch_all(&pool)
-    .await?;
+    Ok(token_create(&pool, user_id, token).await?)
+}
+
+#[server]
+pub async fn api_token_revoke(id: i32) -> Result<(), ServerFnError> {
+    use crate::server::{auth::token_revoke, pool, session_user};
 
-    Ok(comments)
+    let pool = pool()?;
+    let user_id = session_user().await?;
+
+    Ok(token_revoke(&pool, user_id, id).await?)
+}
+
+#[server]
+pub async fn webhook_list() -> Result<Vec<Webhook>, ServerFnError> {
+    use crate::server::{pool, session_user, webhooks};
+
+    let pool = pool()?;
+    let user_id = session_user().await?;
+
+    Ok(webhooks::webhook_list(&pool, user_id).await?)
+}
+
+#[server]
+pub async fn webhook_create(webhook: WebhookCreateArgs) -> Result<WebhookCreated, ServerFnError> {
+    use crate::server::{pool, session_user, throttle, webhook_dispatcher, webhooks};
+
+    let pool = pool()?;
+    let user_id = session_user().await?;
+    throttle().await?;
+    let allow_private = webhook_dispatcher().allow_private;
+
+    Ok(webhooks::webhook_create(&pool, user_id, webhook, allow_private).await?)
+}
+
+#[server]
+pub async fn webhook_delete(id: i32) -> Result<(), ServerFnError> {
+    use crate::server::{pool, session_user, webhooks};
+
+    let pool = pool()?;
+    let user_id = session_user().await?;
+
+    Ok(webhooks::webhook_delete(&pool, user_id, id).await?)
+}
+
+/// Deletes the account of the pasted token, which needs the `account` scope;
+/// browsers carry no credentials of their own. `content` is a deletion mode,
+/// `anonymize` or `remove`.
+#[server]
+pub async fn account_delete(token: String, content: String) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{account, auth::token_principal, pool, story_cache, throttle},
+    };
+
+    let pool = pool()?;
+    let user_id = token_principal(&pool, &token).await?.require(TokenScope::Account)?;
+    throttle().await?;
+
+    account::account_delete(&pool, user_id, content.parse()?, Some(user_id)).await?;
+    story_cache().invalidate().await;
+
+    Ok(())
+}
+
+#[server]
+pub async fn webhook_deliveries(webhook_id: i32) -> Result<Vec<WebhookDelivery>, ServerFnError> {
+    use crate::server::{pool, session_user, webhooks};
+
+    let pool = pool()?;
+    let user_id = session_user().await?;
+
+    Ok(webhooks::delivery_list(&pool, user_id, webhook_id).await?)
 }
 
 #[server]
 pub async fn comment_with_parents(comment_id: i32) -> Result<Vec<Comment>, ServerFnError> {
-    use crate::server::{pool, row_to_comment};
-    use chrono::{DateTime, FixedOffset, Utc};
-    use sqlx::{postgres::PgRow, query, Row};
-
-    let pool = pool()?;
-
-    let rows = query(
-        r#"
-        WITH RECURSIVE comment_hierarchy AS (
-            -- Base case: the starting comment
-            SELECT 
-                c.id,
-                c.text,
-                c.parent_id,
-                c.story_id,
-                c.created_at,
-                u.display_name as author_name
-            FROM 
-                comments c
-            JOIN 
-                users u ON c.author_id = u.id
-            WHERE 
-                c.id = $1
-            
-            UNION ALL
-            
-            -- Recursive case: parent comments
-            SELECT 
-                c.id,
-                c.text,
-                c.parent_id,
-                c.story_id,
-                c.created_at,
-                u.display_name as author_name
-            FROM 
-                comments c
-            JOIN 
-                users u ON c.author_id = u.id
-            JOIN 
-                comment_hierarchy ch ON c.id = ch.parent_id
-        )
-        SELECT * FROM comment_hierarchy
-        ORDER BY created_at ASC -- Order from oldest to newest
-        "#,
-    )
-    .bind(comment_id)
-    .fetch_all(&pool)
-    .await?;
-
-    let comments = rows
-        .into_iter()
-        .map(row_to_comment)
-        .collect();
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo},
+    };
+
+    let repo = repo()?;
+    authorize(TokenScope::Read).await?;
+
+    Ok(repo.comment_with_parents(comment_id).await?)
+}
+
+/// The inbox of a signed-in user or token holder. Anonymous browsers all act
+/// as the site user, so they have none.
+#[server]
+pub async fn notification_list() -> Result<Vec<Notification>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, notifications, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.member(TokenScope::Read)?;
+
+    Ok(notifications::notification_list(&pool, user_id).await?)
+}
+
+#[server]
+pub async fn notification_unread_count() -> Result<i64, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, notifications, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.member(TokenScope::Read)?;
+
+    Ok(notifications::unread_count(&pool, user_id).await?)
+}
+
+#[server]
+pub async fn notification_mark_read(id: i64) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, notifications, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.member(TokenScope::Write)?;
+
+    Ok(notifications::mark_read(&pool, user_id, id).await?)
+}
+
+#[server]
+pub async fn notification_mark_all_read() -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, notifications, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.member(TokenScope::Write)?;
+    notifications::mark_all_read(&pool, user_id).await?;
+
+    Ok(())
+}
+/// Saves a bookmark for a token holder. Browsers keep theirs locally, as
+/// filled in by [`bookmark_target`].
+#[server]
+pub async fn bookmark_set(bookmark: BookmarkArgs) -> Result<Bookmark, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, bookmarks, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(bookmarks::bookmark_set(&pool, user_id, bookmark).await?)
+}
+
+#[server]
+pub async fn bookmark_remove(story_id: i32, comment_id: Option<i32>) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, bookmarks, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(bookmarks::bookmark_remove(&pool, user_id, story_id, comment_id).await?)
+}
+
+#[server]
+pub async fn bookmark_keys() -> Result<Vec<(i32, Option<i32>)>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, bookmarks, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.require(TokenScope::Read)?;
+
+    Ok(bookmarks::bookmark_keys(&pool, user_id).await?)
+}
+
+/// A new bookmark of a story, or one of its comments, for a browser to keep.
+#[server]
+pub async fn bookmark_target(story_id: i32, comment_id: Option<i32>) -> Result<Bookmark, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{authorize, bookmarks, pool},
+    };
+
+    let pool = pool()?;
+    authorize(TokenScope::Read).await?;
+
+    Ok(bookmarks::bookmark_target(&pool, story_id, comment_id).await?)
+}
+
+/// `name`'s collection, or without a name the token holder's own. Only a
+/// token's holder sees their notes and private collection.
+#[server]
+pub async fn favorites_get(name: Option<String>) -> Result<Favorites, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, bookmarks, pool},
+    };
 
-    Ok(comments)
-}
\ No newline at end of file
+    let pool = pool()?;
+    let bearer = bearer().await?;
+    bearer.principal().require(TokenScope::Read)?;
+
+    Ok(bookmarks::favorites(&pool, bearer.owner(), name.as_deref()).await?)
+}
+
+#[server]
+pub async fn favorites_set_public(public: bool) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, bookmarks, pool},
+    };
+
+    let pool = pool()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(bookmarks::favorites_set_public(&pool, user_id, public).await?)
+}
+
+#[server]
+pub async fn tag_list() -> Result<Vec<Tag>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo},
+    };
+
+    let repo = repo()?;
+    authorize(TokenScope::Read).await?;
+
+    Ok(repo.tag_list().await?)
+}
+
+#[server]
+pub async fn tag_stories(name: String, page: Option<i64>) -> Result<Vec<StoryListItem>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{authorize, repo},
+    };
+
+    let repo = repo()?;
+    authorize(TokenScope::Read).await?;
+
+    Ok(repo.tag_page(&name, page.unwrap_or(0)).await?)
+}
+
+/// A token holder's followed and muted tags. Browsers keep theirs locally.
+#[server]
+pub async fn tag_preferences_get() -> Result<TagPreferences, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Read)?;
+
+    Ok(repo.tag_preferences(user_id).await?)
+}
+
+#[server]
+pub async fn tag_prefer(name: String, preference: Option<TagPreference>) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(repo.tag_prefer(user_id, &name, preference).await?)
+}
+
+/// Retags a story; only moderators may.
+#[server]
+pub async fn story_tags_set(story_id: i32, tags: Vec<String>) -> Result<Vec<String>, ServerFnError> {
+    use crate::{
+        model::{TokenScope, WebhookEvent},
+        server::{audit, moderator, pool, repo, story_cache, webhooks},
+    };
+
+    let repo = repo()?;
+    let moderator_id = moderator(TokenScope::Submit).await?;
+
+    let tags = repo.story_tags_set(story_id, &tags).await?;
+    story_cache().invalidate().await;
+    let pool = pool()?;
+    let details = serde_json::json!({ "tags": tags });
+    if let Err(err) = audit::record(&pool, Some(moderator_id), audit::STORY_TAGGED, Some(story_id), details).await {
+        leptos::logging::error!("Failed to audit retagging story {story_id}: {err}");
+    }
+    let data = serde_json::json!({ "action": audit::STORY_TAGGED, "story_id": story_id, "tags": tags });
+    webhooks::emit(&pool, WebhookEvent::ModerationAction, &data).await;
+
+    Ok(tags)
+}
+
+/// The reader's saved draft for an editor: a new story without a story, or
+/// a comment on one, replying to `parent_id` when set. Drafts are kept on the
+/// server only for token holders; browsers keep theirs in local storage.
+#[server]
+pub async fn draft_get(story_id: Option<i32>, parent_id: Option<i32>) -> Result<Option<Draft>, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Read)?;
+
+    Ok(repo.drafts(user_id, Some((story_id, parent_id))).await?.pop())
+}
+
+#[server]
+pub async fn draft_save(draft: DraftArgs) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(repo.draft_save(user_id, draft).await?)
+}
+
+#[server]
+pub async fn draft_discard(story_id: Option<i32>, parent_id: Option<i32>) -> Result<(), ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, repo},
+    };
+
+    let repo = repo()?;
+    let user_id = bearer().await?.require(TokenScope::Write)?;
+
+    Ok(repo.draft_discard(user_id, story_id, parent_id).await?)
+}
+
+#[server]
+pub async fn profile_get(name: Option<String>) -> Result<Profile, ServerFnError> {
+    use crate::{
+        model::TokenScope,
+        server::{bearer, drafts, pool},
+    };
+
+    let pool = pool()?;
+    let bearer = bearer().await?;
+    let viewer_id = bearer.principal().require(TokenScope::Read)?;
+
+    Ok(drafts::profile(&pool, viewer_id, bearer.owner().is_some(), name.as_deref()).await?)
+}
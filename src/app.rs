@@ -1,25 +1,18 @@
-use std::cmp::min;
-
 use crate::{
-    api::*, constants::{LAMBDA, LAMBDA_FUNCTION, LOADING, NEW, PROFILE, STORY, TITLE_ERROR}, features::{chrono::{provide_now, RelativeTime}, ui::markdown::*, utils::pluralize}, model::{Comment, Story, StoryGetArgs, StoryListItem}
+    api::*, constants::{ACCOUNT, BOOKMARKS_KEY, DELETE, DRAFT_KEY_PREFIX, FAVORITES, HIDDEN_STORIES_KEY, INBOX, LAMBDA, LOADING, LOGIN, NEW, POLL_MAX_OPTIONS, PROFILE, STORY, STORY_VISITS_KEY, TAG, TITLE_DUPLICATE, TITLE_EMPTY, TITLE_ERROR, TITLE_SUCCESS, TAG_PREFERENCES_KEY, TOKENS, HOOKS}, features::{chrono::{provide_now, RelativeTime}, live::use_live, mention::mention_spans, poll::percent, site::{page_title, provide_site, use_site}, ui::{editor::{apply_format, Format}, markdown::*}, utils::pluralize}, model::{ApiTokenCreated, Bookmark, Comment, Draft, DraftArgs, Favorites, LiveEvent, Mention, Notification, NotificationKind, Poll, PollOption, Profile, Story, StoryFilter, StoryGetArgs, StoryKind, StoryListItem, StoryVisit, Tag, TagPreference, TagPreferences, TextPreview, TokenScope, VoteTally, WebhookCreated, WebhookEvent}
 };
 use chrono::{DateTime, FixedOffset, Local};
-use chrono_humanize::HumanTime;
-use comrak::{
-    format_html, format_html_with_plugins, markdown_to_html_with_plugins, nodes::NodeValue,
-    parse_document, plugins::syntect::SyntectAdapterBuilder, Arena, ExtensionOptions, Options,
-    Plugins,
-};
-use leptos::{either::Either, logging::log, prelude::*};
-use leptos_meta::{provide_meta_context, MetaTags, Stylesheet, Title};
+use codee::string::JsonSerdeCodec;
+use leptos::{either::Either, prelude::*};
+use leptos_meta::{provide_meta_context, Link, MetaTags, Stylesheet, Title};
 use leptos_router::{
     components::{Route, Router, Routes, A},
-    hooks::{use_navigate, use_params},
+    hooks::{use_location, use_navigate, use_params, use_params_map},
     ParamSegment, SsrMode, StaticSegment,
 };
-use leptos_use::{use_document_visibility, use_interval_fn};
+use leptos_use::storage::use_local_storage;
+use std::collections::BTreeMap;
 use url::Url;
-use web_sys::VisibilityState;
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
     // 👻
@@ -45,6 +38,12 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
 pub fn App() -> impl IntoView {
     provide_meta_context();
     provide_now();
+    provide_site();
+    let site = use_site();
+    provide_context(SessionActions::new());
+    provide_context(InboxActions::default());
+    provide_context(BookmarkActions::new());
+    provide_context(ReaderPreferences::new());
 
     view! {
         <Stylesheet id="leptos" href="/pkg/news.css" />
@@ -55,7 +54,7 @@ pub fn App() -> impl IntoView {
                         <span class="lambda-home".to_string()>
                             <A href="/">
                                 <span class="lambda-icon".to_string()>{LAMBDA}</span>
-                                <span class="lambda-text".to_string()>{LAMBDA_FUNCTION}</span>
+                                <span class="lambda-text".to_string()>{site.name}</span>
                             </A>
                         </span>
                     </li>
@@ -65,11 +64,76 @@ pub fn App() -> impl IntoView {
                     <li>
                         <A href=format!("/{STORY}/{NEW}",)>Bind</A>
                     </li>
+                    {StoryKind::ALL
+                        .into_iter()
+                        .filter(|kind| *kind != StoryKind::Link)
+                        .map(|kind| {
+                            view! {
+                                <li>
+                                    <A href=format!("/{}", kind.as_str())>{kind.label()}</A>
+                                </li>
+                            }
+                        })
+                        .collect_view()}
+                    <li>
+                        <A href=format!("/{TOKENS}")>Tokens</A>
+                    </li>
+                    <li>
+                        <A href=format!("/{HOOKS}")>Hooks</A>
+                    </li>
+                    <li>
+                        <A href=format!("/{ACCOUNT}")>Account</A>
+                    </li>
+                    <li>
+                        <A href=format!("/{TAG}")>Tags</A>
+                    </li>
+                    <li>
+                        <A href=format!("/{FAVORITES}")>Favorites</A>
+                    </li>
+                    <li>
+                        <A href=format!("/{PROFILE}")>Profile</A>
+                    </li>
+                    <li>
+                        <InboxLink />
+                    </li>
+                    <li>
+                        <SessionLink />
+                    </li>
                 </ul>
             </header>
             <Routes fallback=NotFound>
-                <Route path=StaticSegment("") view=StoryList />
+                <Route path=StaticSegment("") view=|| view! { <StoryList /> } />
+                <Route
+                    path=StaticSegment(StoryKind::Ask.as_str())
+                    view=|| view! { <StoryList kind=StoryKind::Ask /> }
+                />
+                <Route
+                    path=StaticSegment(StoryKind::Show.as_str())
+                    view=|| view! { <StoryList kind=StoryKind::Show /> }
+                />
+                <Route
+                    path=StaticSegment(StoryKind::Poll.as_str())
+                    view=|| view! { <StoryList kind=StoryKind::Poll /> }
+                />
+                <Route
+                    path=StaticSegment(StoryKind::Job.as_str())
+                    view=|| view! { <StoryList kind=StoryKind::Job /> }
+                />
                 <Route path=(StaticSegment(STORY), StaticSegment(NEW)) view=StoryCreate />
+                <Route path=StaticSegment(TOKENS) view=ApiTokens />
+                <Route path=StaticSegment(HOOKS) view=Webhooks />
+                <Route path=StaticSegment(ACCOUNT) view=AccountPage />
+                <Route path=StaticSegment(LOGIN) view=LoginPage />
+                <Route path=StaticSegment(INBOX) view=Inbox />
+                <Route path=StaticSegment(FAVORITES) view=FavoritesPage />
+                <Route path=StaticSegment(PROFILE) view=ProfilePage />
+                <Route path=(StaticSegment(PROFILE), ParamSegment("name")) view=ProfilePage />
+                <Route path=StaticSegment(TAG) view=TagIndex />
+                <Route path=(StaticSegment(TAG), ParamSegment("name")) view=TagPage />
+                <Route
+                    path=(StaticSegment(PROFILE), ParamSegment("name"), StaticSegment(FAVORITES))
+                    view=FavoritesPage
+                />
                 <Route
                     path=(StaticSegment(STORY), ParamSegment("id"))
                     view=StoryDetail
@@ -80,23 +144,61 @@ pub fn App() -> impl IntoView {
     }
 }
 
+/// The front page, or with `kind` that kind's own listing.
 #[component]
-fn StoryList() -> impl IntoView {
-    let (page, set_page) = signal(0 as i64);
-    let stories_resource = Resource::new(move || page.get(), |page| story_list(Some(page)));
+fn StoryList(#[prop(optional)] kind: Option<StoryKind>) -> impl IntoView {
+    let (page, _set_page) = signal(0_i64);
+    let preferences: ReaderPreferences = expect_context();
+    let stories_resource = Resource::new(
+        move || (page.get(), preferences.filter()),
+        move |(page, filter)| async move {
+            match kind {
+                Some(kind) => story_kind_list(kind, Some(page), Some(filter)).await,
+                None => story_list(Some(page), Some(filter)).await,
+            }
+        },
+    );
     let stories = move || {
         stories_resource
             .get()
             .map(|n| n.unwrap_or_default())
             .unwrap_or_default()
     };
+    // The browser keeps when it last opened each story, so what's new is
+    // only asked for once hydrated.
+    let (visits, set_visits) = signal(Vec::<StoryVisit>::new());
+    Effect::new(move |_| {
+        let (story_ids, seen_at): (Vec<_>, Vec<_>) = preferences.seen.with(|seen| {
+            stories().into_iter().filter_map(|story| Some((story.id, *seen.get(&story.id)?))).unzip()
+        });
+        if story_ids.is_empty() {
+            set_visits(vec![]);
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            set_visits(story_new_comments(story_ids, seen_at).await.unwrap_or_default());
+        });
+    });
+    let new_comments = move |story_id: i32| {
+        visits
+            .with(|visits| visits.iter().find(|visit| visit.story_id == story_id).map(|visit| visit.new_comment_ids.len()))
+            .filter(|count| *count > 0)
+    };
+    let live = use_live(None);
+    Effect::new(move |_| {
+        let fresh = matches!(live.get(), Some(LiveEvent::StoryCreated { .. } | LiveEvent::Lagged));
+        if fresh && page.get_untracked() == 0 {
+            stories_resource.refetch();
+        }
+    });
     view! {
-        <Title text=format!("{} :: {}", "Root Binding", LAMBDA_FUNCTION) />
+        <Title text=page_title(kind.map_or("Root Binding", |kind| kind.label())) />
         <Transition fallback=|| view! { <p>{LOADING}</p> }>
             <ol class="binding".to_string() start=0>
                 <For each=stories key=|story| story.id let:story>
                     <li>
                         <div>
+                            <KindBadge kind=story.kind />
                             <StoryLink story_id=story.id title=story.title url=story.url />
                         </div>
                         <div class="meta".to_string()>
@@ -106,7 +208,25 @@ fn StoryList() -> impl IntoView {
                                 story.id,
                             )>{story.comment_count}" "{pluralize(story.comment_count.unwrap_or_default(), "effect", "effects")}</A>
                             <span>owned by <UserLink user_name=story.author_name /></span>
+                            {move || {
+                                new_comments(story.id)
+                                    .map(|count| {
+                                        view! {
+                                            <span class="new".to_string()>
+                                                {count}" new "{pluralize(count as i32, "effect", "effects")}
+                                            </span>
+                                        }
+                                    })
+                            }}
                             <RelativeTime from=story.created_at />
+                            <TagLinks tags=story.tags />
+                            <BookmarkToggle story_id=story.id />
+                            <button
+                                class="hide".to_string()
+                                on:click=move |_| preferences.hide(story.id, true)
+                            >
+                                "Hide"
+                            </button>
                         </div>
                     </li>
                 </For>
@@ -131,8 +251,24 @@ fn StoryDetail() -> impl IntoView {
             _ => vec![],
         }
     });
+    let tally = Resource::new(id, |id| async move {
+        match id {
+            Ok(id) => story_votes(id).await,
+            _ => Err(ServerFnError::ServerError("Story not found.".into())),
+        }
+    });
+    // When the browser had last opened the story, recorded once hydrated.
+    let preferences: ReaderPreferences = expect_context();
+    let SessionActions { user_name, .. } = expect_context();
+    let (seen_at, set_seen_at) = signal(None);
+    Effect::new(move |_| {
+        if let Ok(id) = id() {
+            set_seen_at(preferences.visit(id));
+        }
+    });
 
     view! {
+        {move || id().ok().map(|story_id| view! { <StoryLive story_id comments tally /> })}
         <Transition fallback=|| view! { <p>{LOADING}</p> }>
             {move || {
                 Suspend::new(async move {
@@ -140,15 +276,29 @@ fn StoryDetail() -> impl IntoView {
                     comments.await;
                     view! {
                         {match story {
-                            Ok(Story { title, text, id, url, .. }) => {
+                            Ok(Story { title, text, id, url, text_html, kind, tags, .. }) => {
                                 Either::Left(
                                     view! {
-                                        <Title text=format!("{} :: {}", title, LAMBDA_FUNCTION) />
+                                        <Title text=page_title(&title) />
                                         <main>
                                             <h4>
+                                                <KindBadge kind />
                                                 <StoryLink story_id=id title=title url=url />
                                             </h4>
-                                            <Markdown text=text.unwrap_or_default() />
+                                            <div class="meta".to_string()>
+                                                {move || {
+                                                    tally
+                                                        .get()
+                                                        .and_then(Result::ok)
+                                                        .map(|VoteTally { score, .. }| {
+                                                            format!("{score} {}", pluralize(score, "point", "points"))
+                                                        })
+                                                }}
+                                                <TagLinks tags />
+                                                <HideToggle story_id=id />
+                                            </div>
+                                            <Markdown text=text.unwrap_or_default() html=text_html />
+                                            {(kind == StoryKind::Poll).then(|| view! { <PollView story_id=id /> })}
                                         </main>
                                         <CommentCreate
                                             story_id=id
@@ -170,7 +320,12 @@ fn StoryDetail() -> impl IntoView {
                                         .get()
                                         .clone()
                                         .and_then(|comment| {
-                                            view! { <CommentDetail comment=comment.clone() /> }.into()
+                                            let (created_at, author_name) = (comment.created_at, comment.author_name.clone());
+                                            let new = Signal::derive(move || {
+                                                let own = user_name.get().flatten().is_some_and(|name| name == author_name);
+                                                seen_at.get().is_some_and(|seen_at| created_at > seen_at) && !own
+                                            });
+                                            view! { <CommentDetail comment=comment.clone() new /> }.into()
                                         })
                                 }
                             />
@@ -182,19 +337,86 @@ fn StoryDetail() -> impl IntoView {
     }
 }
 
+/// Applies live updates for a story to its loaded comments and score. Keyed
+/// on the story, so moving to another one resubscribes.
+#[component]
+fn StoryLive(
+    story_id: i32,
+    comments: Resource<Vec<Comment>>,
+    tally: Resource<Result<VoteTally, ServerFnError>>,
+) -> impl IntoView {
+    let live = use_live(Some(story_id));
+    Effect::new(move |_| match live.get() {
+        Some(LiveEvent::CommentCreated { comment }) => comments.update(|comments| {
+            if let Some(comments) = comments.as_mut().filter(|comments| comments.iter().all(|c| c.id != comment.id)) {
+                comments.insert(0, comment);
+            }
+        }),
+        Some(LiveEvent::ScoreChanged { tally: latest, .. }) => tally.set(Some(Ok(latest))),
+        Some(LiveEvent::Lagged) => {
+            comments.refetch();
+            tally.refetch();
+        }
+        _ => {}
+    });
+}
+
 #[component]
 fn StoryCreate() -> impl IntoView {
     let navigate = use_navigate();
 
     let submit = ServerAction::<StoryCreate>::new();
     let value = submit.value();
+    let (url, set_url) = signal(String::new());
+    let duplicate = Resource::new(move || url.get(), |url| async move {
+        if url.is_empty() {
+            return None;
+        }
+        story_find_duplicate(url).await.ok().flatten()
+    });
+    let preview = Resource::new(move || url.get(), |url| async move {
+        if url.is_empty() {
+            return None;
+        }
+        fetch_link_preview(url).await.ok()
+    });
+    let tags = Resource::new(|| (), |_| tag_list());
+    let (poll, set_poll) = signal(false);
+    let title_element: NodeRef<leptos::html::Input> = NodeRef::new();
+    let url_element: NodeRef<leptos::html::Input> = NodeRef::new();
+    let text_element: NodeRef<leptos::html::Textarea> = NodeRef::new();
+    let (save_draft, clear_draft) = use_draft(None, None, move |draft| {
+        if let (Some(title), Some(url), Some(text)) = (title_element.get(), url_element.get(), text_element.get()) {
+            title.set_value(draft.title.as_deref().unwrap_or_default());
+            url.set_value(draft.url.as_deref().unwrap_or_default());
+            text.set_value(&draft.text);
+            set_url(draft.url.unwrap_or_default());
+        }
+    });
+    let draft = move || {
+        let value = |input: Option<web_sys::HtmlInputElement>| input.map(|input| input.value());
+        DraftArgs {
+            title: value(title_element.get()),
+            url: value(url_element.get()),
+            text: text_element.get().map(|text| text.value()).unwrap_or_default(),
+            ..DraftArgs::default()
+        }
+    };
+    Effect::new(move |_| {
+        let title = preview.get().flatten().and_then(|preview| preview.title);
+        if let (Some(title), Some(input)) = (title, title_element.get()) {
+            if input.value().is_empty() {
+                input.set_value(&title);
+            }
+        }
+    });
     Effect::watch(
         move || submit.value().get(),
-        move |story, _, _| match story {
-            Some(Ok(story)) => {
+        move |story, _, _| {
+            if let Some(Ok(story)) = story {
+                clear_draft();
                 navigate(format!("/{STORY}/{}", story.id).as_str(), Default::default());
             }
-            _ => {}
         },
         false,
     );
@@ -225,25 +447,232 @@ fn StoryCreate() -> impl IntoView {
         _ => None,
     };
 
+    let existing = move || {
+        duplicate.get().flatten().map(|Story { id, title, .. }| view! {
+            <article class="duplicate">
+                <h4>{TITLE_DUPLICATE}</h4>
+                <p>
+                    "This link is already bound as "
+                    <A href=format!("/{STORY}/{id}")>{title}</A>
+                    ". Applying will join the existing discussion."
+                </p>
+            </article>
+        })
+    };
+
     view! {
-        <Title text=format!("{} :: {}", "New", LAMBDA_FUNCTION) />
+        <Title text=page_title("New") />
         <main>
-            <ActionForm action=submit>
+            <ActionForm action=submit on:input=move |_| save_draft(draft())>
                 {success} {error} <h1>Bind New Value</h1> <label>
-                    <span>Title</span>
-                    <input type="text" name="story[title]" />
-                </label> <label>
-                    <span>Text</span>
-                    <textarea name="story[text]"></textarea>
+                    <span>Kind</span>
+                    <select
+                        name="story[kind]"
+                        on:change=move |ev| set_poll(event_target_value(&ev) == StoryKind::Poll.as_str())
+                    >
+                        {StoryKind::ALL
+                            .into_iter()
+                            .map(|kind| view! { <option value=kind.as_str()>{kind.label()}</option> })
+                            .collect_view()}
+                    </select>
                 </label> <label>
+                    <span>Title</span>
+                    <input type="text" name="story[title]" node_ref=title_element />
+                </label> <Editor label="Text" name="story[text]" markdown=true textarea=text_element /> <label>
                     <span>URL</span>
-                    <input type="text" name="story[url]" />
-                </label> <button type="submit">"Apply"</button>
+                    <input
+                        type="text"
+                        name="story[url]"
+                        node_ref=url_element
+                        on:change=move |ev| set_url(event_target_value(&ev))
+                    />
+                </label> <Transition fallback=|| ()>{existing}</Transition>
+                <Show when=poll>
+                    <fieldset class="poll".to_string()>
+                        <legend>"Poll"</legend>
+                        {(0..POLL_MAX_OPTIONS)
+                            .map(|index| {
+                                view! {
+                                    <input
+                                        type="text"
+                                        name=format!("story[poll][options][{index}]")
+                                        placeholder=format!("Option {}", index + 1)
+                                    />
+                                }
+                            })
+                            .collect_view()}
+                        <label class="choice".to_string()>
+                            <input type="checkbox" name="story[poll][multiple]" value="true" />
+                            "Allow several choices"
+                        </label>
+                        <label>
+                            <span>"Closes (UTC, optional)"</span>
+                            <input type="datetime-local" name="story[poll][closes_at]" />
+                        </label>
+                    </fieldset>
+                </Show>
+                <fieldset class="tags".to_string()>
+                    <legend>"Tags"</legend>
+                    <Transition fallback=|| ()>
+                        {move || {
+                            tags.get()
+                                .map(|tags| {
+                                    tags.unwrap_or_default()
+                                        .into_iter()
+                                        .enumerate()
+                                        .map(|(index, Tag { name, description, .. })| {
+                                            view! {
+                                                <label title=description>
+                                                    <input
+                                                        type="checkbox"
+                                                        name=format!("story[tags][{index}]")
+                                                        value=name.clone()
+                                                    />
+                                                    {name}
+                                                </label>
+                                            }
+                                        })
+                                        .collect_view()
+                                })
+                        }}
+                    </Transition>
+                </fieldset>
+                <button type="submit">"Apply"</button>
             </ActionForm>
         </main>
     }
 }
 
+/// A poll's results as bars, with a ballot while it is open. Radio buttons
+/// share one field, checkboxes take one each, so either posts a list.
+#[component]
+fn PollView(story_id: i32) -> impl IntoView {
+    let vote = ServerAction::<PollVote>::new();
+    let poll = Resource::new(move || vote.version().get(), move |_| poll_get(story_id));
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                poll.get()
+                    .and_then(Result::ok)
+                    .map(|poll| {
+                        let Poll { multiple, closes_at, closed, options, voters, ballot, .. } = poll;
+                        let voted = !ballot.is_empty();
+                        let rows = options
+                            .into_iter()
+                            .enumerate()
+                            .map(|(index, PollOption { id, text, votes })| {
+                                let share = percent(votes, voters);
+                                let field = format!("option_ids[{}]", if multiple { index } else { 0 });
+                                let kind = if multiple { "checkbox" } else { "radio" };
+                                view! {
+                                    <li class:chosen=ballot.contains(&id)>
+                                        <label>
+                                            {(!closed)
+                                                .then(|| {
+                                                    view! {
+                                                        <input
+                                                            type=kind
+                                                            name=field
+                                                            value=id
+                                                            checked=ballot.contains(&id)
+                                                        />
+                                                    }
+                                                })}
+                                            <span>{text}</span>
+                                        </label>
+                                        <svg
+                                            class="bar"
+                                            viewBox="0 0 100 1"
+                                            preserveAspectRatio="none"
+                                            role="img"
+                                            aria-label=format!("{share}%")
+                                        >
+                                            <rect width=share height="1" />
+                                        </svg>
+                                        <span class="votes".to_string()>
+                                            {votes}" "{pluralize(votes as i32, "vote", "votes")}" · "{share}"%"
+                                        </span>
+                                    </li>
+                                }
+                            })
+                            .collect_view();
+                        view! {
+                            <ActionForm action=vote attr:class="poll">
+                                <input type="hidden" name="story_id" value=story_id />
+                                <ol>{rows}</ol>
+                                <div class="meta".to_string()>
+                                    <span>{voters}" "{pluralize(voters as i32, "voter", "voters")}</span>
+                                    {match (closed, closes_at) {
+                                        (true, _) => Some(Either::Left(view! { <span>"closed"</span> })),
+                                        (false, Some(closes_at)) => {
+                                            Some(
+                                                Either::Right(
+                                                    view! {
+                                                        <span>"closes " <RelativeTime from=closes_at /></span>
+                                                    },
+                                                ),
+                                            )
+                                        }
+                                        (false, None) => None,
+                                    }}
+                                    {(!closed)
+                                        .then(|| {
+                                            view! {
+                                                <button type="submit">
+                                                    {if voted { "Change vote" } else { "Vote" }}
+                                                </button>
+                                            }
+                                        })}
+                                    {(!closed && voted)
+                                        .then(|| {
+                                            view! {
+                                                <button
+                                                    type="button"
+                                                    on:click=move |_| {
+                                                        vote.dispatch(PollVote { story_id, option_ids: vec![] });
+                                                    }
+                                                >
+                                                    "Withdraw"
+                                                </button>
+                                            }
+                                        })}
+                                </div>
+                            </ActionForm>
+                            {move || {
+                                vote.value()
+                                    .get()
+                                    .and_then(Result::err)
+                                    .map(|err| {
+                                        view! {
+                                            <p class="error".to_string()>
+                                                {match err {
+                                                    ServerFnError::ServerError(err) => err,
+                                                    _ => "The ballot was not counted.".to_string(),
+                                                }}
+                                            </p>
+                                        }
+                                    })
+                            }}
+                        }
+                    })
+            }}
+        </Transition>
+    }
+}
+
+/// Links to the listing of the story's kind; links need no badge.
+#[component]
+fn KindBadge(kind: StoryKind) -> impl IntoView {
+    (kind != StoryKind::Link).then(|| {
+        view! {
+            <span class="kind".to_string()>
+                <A href=format!("/{}", kind.as_str())>{kind.label()}</A>
+            </span>
+        }
+    })
+}
+
 #[component]
 fn StoryLink(story_id: i32, title: String, url: Option<String>) -> impl IntoView {
     let story_url = format!("/{STORY}/{story_id}",);
@@ -272,7 +701,7 @@ fn UserLink(user_name: String) -> impl IntoView {
 #[component]
 pub fn NotFound() -> impl IntoView {
     view! {
-        <Title text=format!("{} :: {}", "Not found", LAMBDA_FUNCTION) />
+        <Title text=page_title("Not found") />
         <main class="error".to_string()>
             <header>404</header>
             <p>The requested function is undefined</p>
@@ -284,26 +713,34 @@ pub fn NotFound() -> impl IntoView {
 fn CommentCreate(
     #[prop(optional)] parent_id: Option<i32>,
     story_id: i32,
-    on_submit: impl Fn() -> () + 'static,
+    on_submit: impl Fn() + 'static,
 ) -> impl IntoView {
-    let navigate = use_navigate();
     let submit = ServerAction::<CommentCreate>::new();
     let input_element: NodeRef<leptos::html::Textarea> = NodeRef::new();
-    create_effect(move |_| {
+    let (save_draft, clear_draft) = use_draft(Some(story_id), parent_id, move |draft| {
+        if let Some(input) = input_element.get() {
+            input.set_value(&draft.text);
+        }
+    });
+    Effect::new(move |_| {
         if submit.value().with(|res| res.as_ref().is_some_and(|res| res.is_ok())) {
             submit.clear();
+            clear_draft();
             if let Some(input) = input_element.get() {
                 input.set_value("");
             }
             on_submit();
         }
     });
+    let draft = move || DraftArgs {
+        story_id: Some(story_id),
+        parent_id,
+        text: input_element.get().map(|input| input.value()).unwrap_or_default(),
+        ..DraftArgs::default()
+    };
     view! {
-        <ActionForm action=submit>
-            <label>
-                <span>Text</span>
-                <textarea name="comment[text]" placeholder="Compose yourself" node_ref=input_element></textarea>
-            </label>
+        <ActionForm action=submit on:input=move |_| save_draft(draft())>
+            <Editor label="Text" name="comment[text]" placeholder="Compose yourself" textarea=input_element />
             <input type="hidden" name="comment[story_id]" value=story_id />
             <input type="hidden" name="comment[parent_id]" value=parent_id />
             <button type="submit">Apply</button>
@@ -311,14 +748,1312 @@ fn CommentCreate(
     }
 }
 
+/// Comment text with each resolved mention linked to its user's profile.
+#[component]
+fn CommentText(text: String, mentions: Vec<Mention>) -> impl IntoView {
+    let mut parts = Vec::new();
+    let mut rest = 0;
+    for span in mention_spans(&text) {
+        let name = &text[span.start + 1..span.end];
+        let Some(mention) = mentions.iter().find(|mention| mention.name == name) else {
+            continue;
+        };
+        parts.push(Either::Left(text[rest..span.start].to_string()));
+        parts.push(Either::Right(view! { "@" <UserLink user_name=mention.user_name.clone() /> }));
+        rest = span.end;
+    }
+    parts.push(Either::Left(text[rest..].to_string()));
+
+    view! { <div>{parts}</div> }
+}
+
+/// Where the browser keeps an editor's draft: a new story's, or a comment's
+/// on `story_id`, replying to `parent_id` when set.
+fn draft_key(story_id: Option<i32>, parent_id: Option<i32>) -> String {
+    match story_id {
+        Some(story_id) => format!("{DRAFT_KEY_PREFIX}{story_id}-{}", parent_id.unwrap_or_default()),
+        None => format!("{DRAFT_KEY_PREFIX}story"),
+    }
+}
+
+/// Keeps an editor's draft in the browser as its author writes. Returns a
+/// function to save the draft and one to clear it once posted. When the
+/// editor opens, a draft left there goes to `restore`.
+fn use_draft(
+    story_id: Option<i32>,
+    parent_id: Option<i32>,
+    restore: impl Fn(Draft) + 'static,
+) -> (impl Fn(DraftArgs) + Clone + 'static, impl Fn() + Clone + 'static) {
+    let (local, set_local, remove_local) =
+        use_local_storage::<Option<Draft>, JsonSerdeCodec>(draft_key(story_id, parent_id));
+
+    Effect::new(move |_| {
+        if let Some(draft) = local.get_untracked() {
+            restore(draft);
+        }
+    });
+
+    let save = {
+        let remove_local = remove_local.clone();
+        move |draft: DraftArgs| {
+            if draft.is_blank() {
+                return remove_local();
+            }
+            let DraftArgs { title, url, text, .. } = draft;
+            set_local.set(Some(Draft {
+                story_id,
+                story_title: None,
+                parent_id,
+                title,
+                url,
+                text,
+                updated_at: chrono::Local::now().fixed_offset(),
+            }));
+        }
+    };
+    let clear = move || remove_local();
+    (save, clear)
+}
+
+/// The drafts this browser keeps, most recently saved first, by key.
+fn local_drafts() -> Vec<(String, Draft)> {
+    use codee::Decoder;
+
+    let Ok(Some(storage)) = window().local_storage() else {
+        return Vec::new();
+    };
+    let mut drafts: Vec<_> = (0..storage.length().unwrap_or_default())
+        .filter_map(|index| storage.key(index).ok().flatten())
+        .filter(|key| key.starts_with(DRAFT_KEY_PREFIX))
+        .filter_map(|key| {
+            let value = storage.get_item(&key).ok().flatten()?;
+            let draft = <JsonSerdeCodec as Decoder<Option<Draft>>>::decode(&value).ok().flatten()?;
+            Some((key, draft))
+        })
+        .collect();
+    drafts.sort_by(|(_, a), (_, b)| b.updated_at.cmp(&a.updated_at));
+    drafts
+}
+
+/// A textarea with Write and Preview tabs. The preview is rendered on the
+/// server the way the text will read once posted; `markdown` text, which
+/// renders as Markdown, also gets a formatting toolbar.
+#[component]
+fn Editor(
+    label: &'static str,
+    name: &'static str,
+    #[prop(optional)] placeholder: &'static str,
+    #[prop(optional)] markdown: bool,
+    #[prop(optional)] textarea: NodeRef<leptos::html::Textarea>,
+) -> impl IntoView {
+    let (previewing, set_previewing) = signal(false);
+    let (text, set_text) = signal(String::new());
+    let preview = Resource::new(
+        move || previewing.get().then(|| text.get()),
+        |text| async move {
+            match text {
+                Some(text) if !text.trim().is_empty() => Some(preview_text(text).await),
+                _ => None,
+            }
+        },
+    );
+
+    let show_preview = move |_| {
+        if let Some(textarea) = textarea.get() {
+            set_text(textarea.value());
+        }
+        set_previewing(true);
+    };
+    let format = move |format: Format| {
+        let Some(textarea) = textarea.get() else {
+            return;
+        };
+        let start = textarea.selection_start().ok().flatten().unwrap_or(0);
+        let end = textarea.selection_end().ok().flatten().unwrap_or(start);
+        let (value, start, end) = apply_format(&textarea.value(), start, end, format);
+        textarea.set_value(&value);
+        // Setting the value fires no `input`, which the form listens to for
+        // saving the draft.
+        let init = web_sys::EventInit::new();
+        init.set_bubbles(true);
+        if let Ok(input) = web_sys::Event::new_with_event_init_dict("input", &init) {
+            let _ = textarea.dispatch_event(&input);
+        }
+        let _ = textarea.focus();
+        let _ = textarea.set_selection_range(start, end);
+    };
+
+    let rendered = move || {
+        preview.get().map(|preview| match preview {
+            None => view! { <p class="empty">"Nothing to preview."</p> }.into_any(),
+            Some(Ok(TextPreview { html, mentions })) => match markdown {
+                true => view! { <Markdown text=text.get_untracked() html=Some(html) /> }.into_any(),
+                false => view! { <CommentText text=text.get_untracked() mentions /> }.into_any(),
+            },
+            Some(Err(_)) => view! { <p class="empty">"The preview is unavailable."</p> }.into_any(),
+        })
+    };
+
+    view! {
+        <div class="editor".to_string() class:previewing=previewing>
+            <div class="tabs" role="tablist">
+                <span>{label}</span>
+                <button
+                    type="button"
+                    role="tab"
+                    aria-selected=move || (!previewing.get()).to_string()
+                    on:click=move |_| set_previewing(false)
+                >
+                    "Write"
+                </button>
+                <button
+                    type="button"
+                    role="tab"
+                    aria-selected=move || previewing.get().to_string()
+                    on:click=show_preview
+                >
+                    "Preview"
+                </button>
+                <Show when=move || markdown && !previewing.get()>
+                    <span class="toolbar">
+                        {Format::ALL
+                            .into_iter()
+                            .map(|each| {
+                                view! {
+                                    <button type="button" title=each.title() on:click=move |_| format(each)>
+                                        {each.label()}
+                                    </button>
+                                }
+                            })
+                            .collect_view()}
+                    </span>
+                </Show>
+            </div>
+            <textarea
+                name=name
+                aria-label=label
+                placeholder=placeholder
+                node_ref=textarea
+                hidden=previewing
+            ></textarea>
+            <Show when=previewing>
+                <div class="preview" role="tabpanel">
+                    <Transition fallback=|| view! { <p class="empty">{LOADING}</p> }>{rendered}</Transition>
+                </div>
+            </Show>
+        </div>
+    }
+}
+
 #[component]
-fn CommentDetail(comment: Comment) -> impl IntoView {
+fn CommentDetail(comment: Comment, #[prop(into)] new: Signal<bool>) -> impl IntoView {
     view! {
-        <li>
-            <div>{comment.text}</div>
+        <li class:new=new>
+            <CommentText text=comment.text mentions=comment.mentions />
             <div class="meta".to_string()>
                 <span>by <UserLink user_name=comment.author_name /></span>
                 <RelativeTime from=comment.created_at />
+                <BookmarkToggle story_id=comment.story_id comment_id=Some(comment.id) />
+            </div>
+        </li>
+    }
+}
+
+
+/// Hides the story from the reader's front page, or brings it back.
+#[component]
+fn HideToggle(story_id: i32) -> impl IntoView {
+    let preferences: ReaderPreferences = expect_context();
+    let hidden = move || preferences.hidden.with(|hidden| hidden.contains(&story_id));
+    view! {
+        <button class="hide".to_string() on:click=move |_| preferences.hide(story_id, !hidden())>
+            {move || if hidden() { "Unhide" } else { "Hide" }}
+        </button>
+    }
+}
+
+/// The stories the reader hid or last opened, and the tags they follow or
+/// mute. Browsers all act as the site user, so these stay in the browser and
+/// listings are asked to leave hidden stories out.
+#[derive(Clone, Copy)]
+struct ReaderPreferences {
+    hidden: Signal<Vec<i32>>,
+    set_hidden: WriteSignal<Vec<i32>>,
+    tags: Signal<TagPreferences>,
+    set_tags: WriteSignal<TagPreferences>,
+    seen: Signal<BTreeMap<i32, DateTime<FixedOffset>>>,
+    set_seen: WriteSignal<BTreeMap<i32, DateTime<FixedOffset>>>,
+}
+
+impl ReaderPreferences {
+    fn new() -> Self {
+        let (hidden, set_hidden, _) = use_local_storage::<Vec<i32>, JsonSerdeCodec>(HIDDEN_STORIES_KEY);
+        let (tags, set_tags, _) = use_local_storage::<TagPreferences, JsonSerdeCodec>(TAG_PREFERENCES_KEY);
+        let (seen, set_seen, _) =
+            use_local_storage::<BTreeMap<i32, DateTime<FixedOffset>>, JsonSerdeCodec>(STORY_VISITS_KEY);
+        ReaderPreferences { hidden, set_hidden, tags, set_tags, seen, set_seen }
+    }
+
+    /// Records that the reader opened the story, returning when they last had.
+    fn visit(&self, story_id: i32) -> Option<DateTime<FixedOffset>> {
+        let seen_at = self.seen.with_untracked(|seen| seen.get(&story_id).copied());
+        self.set_seen.update(|seen| {
+            seen.insert(story_id, Local::now().fixed_offset());
+        });
+        seen_at
+    }
+
+    fn filter(&self) -> StoryFilter {
+        StoryFilter {
+            hidden: self.hidden.get(),
+            muted: self.tags.with(|tags| tags.muted.clone()),
+        }
+    }
+
+    fn hide(&self, story_id: i32, hidden: bool) {
+        self.set_hidden.update(|stories| {
+            stories.retain(|&id| id != story_id);
+            if hidden {
+                stories.push(story_id);
+            }
+        });
+    }
+
+    fn prefer(&self, name: String, preference: Option<TagPreference>) {
+        self.set_tags.update(|TagPreferences { followed, muted }| {
+            followed.retain(|tag| *tag != name);
+            muted.retain(|tag| *tag != name);
+            match preference {
+                Some(TagPreference::Follow) => followed.push(name),
+                Some(TagPreference::Mute) => muted.push(name),
+                None => {}
+            }
+        });
+    }
+}
+
+#[component]
+fn TagLinks(tags: Vec<String>) -> impl IntoView {
+    let preferences: ReaderPreferences = expect_context();
+
+    view! {
+        <span class="tags".to_string()>
+            {tags
+                .into_iter()
+                .map(|tag| {
+                    let href = format!("/{TAG}/{tag}");
+                    let name = tag.clone();
+                    let followed = move || preferences.tags.with(|tags| tags.followed.contains(&name));
+                    view! {
+                        <span class="tag".to_string() class:followed=followed>
+                            <A href=href>{tag}</A>
+                        </span>
+                    }
+                })
+                .collect_view()}
+        </span>
+    }
+}
+
+/// Follow and mute buttons for a tag; pressing the active one undoes it.
+#[component]
+fn TagPreferenceToggle(name: String) -> impl IntoView {
+    let preferences: ReaderPreferences = expect_context();
+    let name = StoredValue::new(name);
+    let current = move || {
+        preferences.tags.with(|tags| {
+            name.with_value(|name| {
+                if tags.followed.contains(name) {
+                    Some(TagPreference::Follow)
+                } else if tags.muted.contains(name) {
+                    Some(TagPreference::Mute)
+                } else {
+                    None
+                }
+            })
+        })
+    };
+    let toggle = move |preference: TagPreference| {
+        let preference = (current() != Some(preference)).then_some(preference);
+        preferences.prefer(name.get_value(), preference);
+    };
+
+    view! {
+        <button
+            class="tag-preference".to_string()
+            class:active=move || current() == Some(TagPreference::Follow)
+            on:click=move |_| toggle(TagPreference::Follow)
+        >
+            {move || if current() == Some(TagPreference::Follow) { "Following" } else { "Follow" }}
+        </button>
+        <button
+            class="tag-preference".to_string()
+            class:active=move || current() == Some(TagPreference::Mute)
+            on:click=move |_| toggle(TagPreference::Mute)
+        >
+            {move || if current() == Some(TagPreference::Mute) { "Muted" } else { "Mute" }}
+        </button>
+    }
+}
+
+#[component]
+fn TagIndex() -> impl IntoView {
+    let tags = Resource::new(|| (), |_| tag_list());
+
+    view! {
+        <Title text=page_title("Tags") />
+        <main>
+            <h1>"Tags"</h1>
+            <Transition fallback=|| view! { <p>{LOADING}</p> }>
+                <ul class="tag-index".to_string()>
+                    {move || {
+                        tags.get()
+                            .map(|tags| {
+                                tags.unwrap_or_default()
+                                    .into_iter()
+                                    .map(|Tag { name, description, story_count }| {
+                                        let href = format!("/{TAG}/{name}");
+                                        let label = name.clone();
+                                        view! {
+                                            <li>
+                                                <A href=href>{label}</A>
+                                                " "
+                                                <span>{description}</span>
+                                                <div class="meta".to_string()>
+                                                    <span>
+                                                        {story_count}" "
+                                                        {pluralize(story_count as i32, "binding", "bindings")}
+                                                    </span>
+                                                    <TagPreferenceToggle name />
+                                                </div>
+                                            </li>
+                                        }
+                                    })
+                                    .collect_view()
+                            })
+                    }}
+                </ul>
+            </Transition>
+        </main>
+    }
+}
+
+/// The stories filed under a tag at `/tag/{name}`, with its feed.
+#[component]
+fn TagPage() -> impl IntoView {
+    let params = use_params_map();
+    let name = move || params.with(|params| params.get("name")).unwrap_or_default();
+    let stories = Resource::new(name, |name| async move {
+        let stories = tag_stories(name.clone(), None).await;
+        (name, stories)
+    });
+
+    view! {
+        <Transition fallback=|| view! { <p>{LOADING}</p> }>
+            {move || {
+                stories
+                    .get()
+                    .map(|(name, stories)| match stories {
+                        Ok(stories) => Either::Left(view! { <TagStories name stories /> }),
+                        Err(_) => Either::Right(NotFound),
+                    })
+            }}
+        </Transition>
+    }
+}
+
+#[component]
+fn TagStories(name: String, stories: Vec<StoryListItem>) -> impl IntoView {
+    let feed = format!("/{TAG}/{name}/feed.atom");
+
+    view! {
+        <Title text=page_title(&name) />
+        <Link rel="alternate" type_="application/atom+xml" href=feed.clone() />
+        <main>
+            <h1>{name.clone()}</h1>
+            <div class="meta".to_string()>
+                <a href=feed rel="external">"Atom feed"</a>
+                <TagPreferenceToggle name />
+            </div>
+            {if stories.is_empty() {
+                Either::Left(view! { <p>{TITLE_EMPTY}</p> })
+            } else {
+                Either::Right(view! {
+                    <ol class="binding".to_string() start=0>
+                        {stories
+                            .into_iter()
+                            .map(|story| {
+                                view! {
+                                    <li>
+                                        <div>
+                                            <StoryLink story_id=story.id title=story.title url=story.url />
+                                        </div>
+                                        <div class="meta".to_string()>
+                                            <A href=format!("/{STORY}/{}", story.id)>
+                                                {story.comment_count}" "
+                                                {pluralize(story.comment_count.unwrap_or_default(), "effect", "effects")}
+                                            </A>
+                                            <span>owned by <UserLink user_name=story.author_name /></span>
+                                            <RelativeTime from=story.created_at />
+                                            <TagLinks tags=story.tags />
+                                            <BookmarkToggle story_id=story.id />
+                                        </div>
+                                    </li>
+                                }
+                            })
+                            .collect_view()}
+                    </ol>
+                })
+            }}
+        </main>
+    }
+}
+
+#[component]
+fn ApiTokens() -> impl IntoView {
+    let create = ServerAction::<ApiTokenCreate>::new();
+    let revoke = ServerAction::<ApiTokenRevoke>::new();
+    let tokens = Resource::new(
+        move || (create.version().get(), revoke.version().get()),
+        |_| api_token_list(),
+    );
+
+    let result = move || match create.value().get() {
+        Some(Ok(ApiTokenCreated { token, secret })) => Some(view! {
+            <article class="success">
+                <h4>{TITLE_SUCCESS}</h4>
+                <p>"Copy the token for " {token.name} " now, it will not be shown again."</p>
+                <code>{secret}</code>
+            </article>
+        }.into_any()),
+        Some(Err(e)) => Some(view! {
+            <article class="error">
+                <h4>{TITLE_ERROR}</h4>
+                <p>
+                    {match e {
+                        ServerFnError::ServerError(e) => e.to_string(),
+                        _ => "An error occurred.".to_string(),
+                    }}
+                </p>
+            </article>
+        }.into_any()),
+        None => None,
+    };
+
+    view! {
+        <Title text=page_title("Tokens") />
+        <main>
+            <SignedIn purpose="manage API tokens">
+                <ActionForm action=create>
+                    {result} <h1>API Tokens</h1> <label>
+                        <span>Name</span>
+                        <input type="text" name="token[name]" />
+                    </label> <fieldset>
+                        {TokenScope::ALL
+                            .map(|scope| {
+                                view! {
+                                    <label>
+                                        <input
+                                            type="checkbox"
+                                            name=format!("token[{}]", scope.as_str())
+                                            value="true"
+                                        />
+                                        <span>{scope.as_str()}</span>
+                                    </label>
+                                }
+                            })
+                            .collect_view()}
+                    </fieldset> <button type="submit">"Apply"</button>
+                </ActionForm>
+                <Transition fallback=|| view! { <p>{LOADING}</p> }>
+                    <ul class="tokens".to_string()>
+                        {move || {
+                            tokens
+                                .get()
+                                .map(|tokens| {
+                                    tokens
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(|token| {
+                                            let scopes = token
+                                                .scopes
+                                                .iter()
+                                                .map(|scope| scope.as_str())
+                                                .collect::<Vec<_>>()
+                                                .join(", ");
+                                            view! {
+                                                <li>
+                                                    <div>{token.name}</div>
+                                                    <div class="meta".to_string()>
+                                                        <span>{scopes}</span>
+                                                        <span>
+                                                            "created " <RelativeTime from=token.created_at />
+                                                        </span>
+                                                        {token
+                                                            .last_used_at
+                                                            .map(|used| {
+                                                                view! {
+                                                                    <span>"used " <RelativeTime from=used /></span>
+                                                                }
+                                                            })}
+                                                        <ActionForm action=revoke>
+                                                            <input type="hidden" name="id" value=token.id />
+                                                            <button type="submit">{DELETE}</button>
+                                                        </ActionForm>
+                                                    </div>
+                                                </li>
+                                            }
+                                        })
+                                        .collect_view()
+                                })
+                        }}
+                    </ul>
+                </Transition>
+            </SignedIn>
+        </main>
+    }
+}
+
+#[component]
+fn Webhooks() -> impl IntoView {
+    let create = ServerAction::<WebhookCreate>::new();
+    let delete = ServerAction::<WebhookDelete>::new();
+    let webhooks = Resource::new(
+        move || (create.version().get(), delete.version().get()),
+        |_| webhook_list(),
+    );
+
+    let result = move || match create.value().get() {
+        Some(Ok(WebhookCreated { webhook, secret })) => Some(view! {
+            <article class="success">
+                <h4>{TITLE_SUCCESS}</h4>
+                <p>"Deliveries to " {webhook.url} " are signed with this secret, it will not be shown again."</p>
+                <code>{secret}</code>
+            </article>
+        }.into_any()),
+        Some(Err(e)) => Some(view! {
+            <article class="error">
+                <h4>{TITLE_ERROR}</h4>
+                <p>
+                    {match e {
+                        ServerFnError::ServerError(e) => e.to_string(),
+                        _ => "An error occurred.".to_string(),
+                    }}
+                </p>
+            </article>
+        }.into_any()),
+        None => None,
+    };
+
+    view! {
+        <Title text=page_title("Hooks") />
+        <main>
+            <SignedIn purpose="manage webhooks">
+                <ActionForm action=create>
+                    {result} <h1>Webhooks</h1> <label>
+                        <span>URL</span>
+                        <input type="text" name="webhook[url]" />
+                    </label> <fieldset>
+                        {WebhookEvent::ALL
+                            .map(|event| {
+                                view! {
+                                    <label>
+                                        <input
+                                            type="checkbox"
+                                            name=format!("webhook[{}]", event.as_str().replace('.', "_"))
+                                            value="true"
+                                        />
+                                        <span>{event.as_str()}</span>
+                                    </label>
+                                }
+                            })
+                            .collect_view()}
+                    </fieldset> <button type="submit">"Apply"</button>
+                </ActionForm>
+                <Transition fallback=|| view! { <p>{LOADING}</p> }>
+                    <ul class="webhooks".to_string()>
+                        {move || {
+                            webhooks
+                                .get()
+                                .map(|webhooks| {
+                                    webhooks
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(|webhook| {
+                                            let events = webhook
+                                                .events
+                                                .iter()
+                                                .map(|event| event.as_str())
+                                                .collect::<Vec<_>>()
+                                                .join(", ");
+                                            view! {
+                                                <li>
+                                                    <div>{webhook.url}</div>
+                                                    <div class="meta".to_string()>
+                                                        <span>{events}</span>
+                                                        <RelativeTime from=webhook.created_at />
+                                                        <ActionForm action=delete>
+                                                            <input type="hidden" name="id" value=webhook.id />
+                                                            <button type="submit">{DELETE}</button>
+                                                        </ActionForm>
+                                                    </div>
+                                                    <WebhookDeliveries webhook_id=webhook.id />
+                                                </li>
+                                            }
+                                        })
+                                        .collect_view()
+                                })
+                        }}
+                    </ul>
+                </Transition>
+            </SignedIn>
+        </main>
+    }
+}
+
+#[component]
+fn WebhookDeliveries(webhook_id: i32) -> impl IntoView {
+    let deliveries = Resource::new(|| (), move |_| webhook_deliveries(webhook_id));
+
+    view! {
+        <details>
+            <summary>Deliveries</summary>
+            <Transition fallback=|| view! { <p>{LOADING}</p> }>
+                <table>
+                    <tbody>
+                        {move || {
+                            deliveries
+                                .get()
+                                .map(|deliveries| {
+                                    deliveries
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(|delivery| {
+                                            let status = delivery.status.clone();
+                                            view! {
+                                                <tr class=status>
+                                                    <td>{delivery.event}</td>
+                                                    <td>{delivery.status}</td>
+                                                    <td>{delivery.attempts}</td>
+                                                    <td>{delivery.last_status_code}</td>
+                                                    <td>{delivery.last_error}</td>
+                                                    <td>
+                                                        <RelativeTime from=delivery.created_at />
+                                                    </td>
+                                                </tr>
+                                            }
+                                        })
+                                        .collect_view()
+                                })
+                        }}
+                    </tbody>
+                </table>
+            </Transition>
+        </details>
+    }
+}
+
+/// Shared so the header badge refreshes when the inbox marks anything read.
+#[derive(Clone, Copy)]
+struct InboxActions {
+    mark_read: ServerAction<NotificationMarkRead>,
+    mark_all_read: ServerAction<NotificationMarkAllRead>,
+}
+
+impl Default for InboxActions {
+    fn default() -> Self {
+        InboxActions {
+            mark_read: ServerAction::new(),
+            mark_all_read: ServerAction::new(),
+        }
+    }
+}
+
+/// Shared so the header follows the browser signing in and out.
+#[derive(Clone, Copy)]
+struct SessionActions {
+    create: ServerAction<SessionCreate>,
+    delete: ServerAction<SessionDelete>,
+    user_name: Resource<Option<String>>,
+}
+
+impl SessionActions {
+    fn new() -> Self {
+        let create = ServerAction::new();
+        let delete = ServerAction::new();
+        let user_name = Resource::new(
+            move || (create.version().get(), delete.version().get()),
+            |_| async { session_get().await.ok().flatten() },
+        );
+        SessionActions { create, delete, user_name }
+    }
+}
+
+/// The signed-in user with a way to sign out, or a link to sign in.
+#[component]
+fn SessionLink() -> impl IntoView {
+    let SessionActions { delete, user_name, .. } = expect_context();
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                user_name
+                    .get()
+                    .map(|user_name| match user_name {
+                        Some(user_name) => Either::Left(view! {
+                            <UserLink user_name />
+                            <ActionForm action=delete attr:class="session">
+                                <button type="submit">"Sign out"</button>
+                            </ActionForm>
+                        }),
+                        None => Either::Right(view! { <A href=format!("/{LOGIN}")>"Sign in"</A> }),
+                    })
+            }}
+        </Transition>
+    }
+}
+
+/// Signs a named user in to this browser with the password an admin set for
+/// them; browsers that don't sign in share the site user.
+#[component]
+fn LoginPage() -> impl IntoView {
+    let SessionActions { create, user_name, .. } = expect_context();
+    let navigate = use_navigate();
+    Effect::watch(
+        move || create.value().get(),
+        move |result, _, _| {
+            if let Some(Ok(())) = result {
+                navigate("/", Default::default());
+            }
+        },
+        false,
+    );
+    let error = move || {
+        create.value().get().and_then(Result::err).map(|_| view! {
+            <article class="error">
+                <h4>{TITLE_ERROR}</h4>
+                <p>"The name or password is not right."</p>
+            </article>
+        })
+    };
+
+    view! {
+        <Title text=page_title("Sign in") />
+        <main>
+            <ActionForm action=create>
+                {error} <h1>Sign in</h1>
+                <Transition fallback=|| ()>
+                    {move || {
+                        user_name
+                            .get()
+                            .flatten()
+                            .map(|user_name| view! { <p>"Signed in as " <UserLink user_name /> "."</p> })
+                    }}
+                </Transition> <label>
+                    <span>Name</span>
+                    <input type="text" name="name" autocomplete="username" />
+                </label> <label>
+                    <span>Password</span>
+                    <input type="password" name="password" autocomplete="current-password" />
+                </label> <button type="submit">"Sign in"</button>
+            </ActionForm>
+        </main>
+    }
+}
+
+/// Shows `children` to a signed-in browser and asks others to sign in, for
+/// pages that manage the account.
+#[component]
+fn SignedIn(purpose: &'static str, children: ChildrenFn) -> impl IntoView {
+    let SessionActions { user_name, .. } = expect_context();
+    let children = StoredValue::new(children);
+
+    view! {
+        <Transition fallback=|| view! { <p>{LOADING}</p> }>
+            {move || {
+                user_name
+                    .get()
+                    .map(|user_name| match user_name {
+                        Some(_) => Either::Left(children.with_value(|children| children())),
+                        None => Either::Right(view! {
+                            <p>
+                                <A href=format!("/{LOGIN}")>"Sign in"</A>
+                                " to "
+                                {purpose}
+                                "."
+                            </p>
+                        }),
+                    })
+            }}
+        </Transition>
+    }
+}
+
+/// Export and deletion for an account, which a browser reaches through one
+/// of its API tokens with the `account` scope.
+#[component]
+fn AccountPage() -> impl IntoView {
+    let delete = ServerAction::<AccountDelete>::new();
+    let result = move || match delete.value().get() {
+        Some(Ok(())) => Some(view! {
+            <article class="success">
+                <h4>{TITLE_SUCCESS}</h4>
+                <p>"The account is deleted and its tokens no longer work."</p>
+            </article>
+        }.into_any()),
+        Some(Err(e)) => Some(view! {
+            <article class="error">
+                <h4>{TITLE_ERROR}</h4>
+                <p>
+                    {match e {
+                        ServerFnError::ServerError(e) => e.to_string(),
+                        _ => "An error occurred.".to_string(),
+                    }}
+                </p>
+            </article>
+        }.into_any()),
+        None => None,
+    };
+
+    view! {
+        <Title text=page_title("Account") />
+        <main>
+            <h1>Account</h1>
+            <form method="post" action=format!("/{ACCOUNT}/export.json")>
+                <h2>Export</h2>
+                <p>"Download everything stored about the account of a token with the account scope."</p>
+                <label>
+                    <span>Token</span>
+                    <input type="password" name="token" autocomplete="off" />
+                </label>
+                <button type="submit">"Download"</button>
+            </form>
+            <ActionForm action=delete>
+                {result} <h2>Delete</h2>
+                <p>"Stories and comments move to a [deleted] user; choose whether their text stays."</p>
+                <label>
+                    <span>Token</span>
+                    <input type="password" name="token" autocomplete="off" />
+                </label>
+                <fieldset>
+                    <label>
+                        <input type="radio" name="content" value="anonymize" checked />
+                        <span>"Keep the text"</span>
+                    </label>
+                    <label>
+                        <input type="radio" name="content" value="remove" />
+                        <span>"Remove the text"</span>
+                    </label>
+                </fieldset>
+                <button type="submit">{DELETE}</button>
+            </ActionForm>
+        </main>
+    }
+}
+
+#[component]
+fn InboxLink() -> impl IntoView {
+    let InboxActions { mark_read, mark_all_read } = expect_context();
+    let SessionActions { create, delete, .. } = expect_context();
+    let location = use_location();
+    let unread = Resource::new(
+        move || {
+            let session = (create.version().get(), delete.version().get());
+            (location.pathname.get(), mark_read.version().get(), mark_all_read.version().get(), session)
+        },
+        |_| notification_unread_count(),
+    );
+    let badge = move || {
+        unread
+            .get()
+            .and_then(Result::ok)
+            .filter(|&count| count > 0)
+            .map(|count| view! { <span class="badge".to_string()>{count}</span> })
+    };
+
+    view! {
+        <A href=format!("/{INBOX}")>Inbox</A>
+        <Transition fallback=|| ()>{badge}</Transition>
+    }
+}
+
+#[component]
+fn Inbox() -> impl IntoView {
+    let InboxActions { mark_read, mark_all_read } = expect_context();
+    let SessionActions { create, delete, .. } = expect_context();
+    let notifications = Resource::new(
+        move || {
+            let session = (create.version().get(), delete.version().get());
+            (mark_read.version().get(), mark_all_read.version().get(), session)
+        },
+        |_| notification_list(),
+    );
+
+    view! {
+        <Title text=page_title("Inbox") />
+        <main>
+            <h1>Inbox</h1>
+            <SignedIn purpose="see replies and mentions">
+                <ActionForm action=mark_all_read>
+                    <button type="submit">"Mark all read"</button>
+                </ActionForm>
+                <Transition fallback=|| view! { <p>{LOADING}</p> }>
+                    {move || {
+                        notifications
+                            .get()
+                            .map(|notifications| {
+                                let notifications = notifications.unwrap_or_default();
+                                if notifications.is_empty() {
+                                    return Either::Left(view! { <p>{TITLE_EMPTY}</p> });
+                                }
+                                Either::Right(view! {
+                                    <ul class="inbox".to_string()>
+                                        {notifications
+                                            .into_iter()
+                                            .map(|notification| view! { <InboxItem notification /> })
+                                            .collect_view()}
+                                    </ul>
+                                })
+                            })
+                    }}
+                </Transition>
+            </SignedIn>
+        </main>
+    }
+}
+
+#[component]
+fn InboxItem(notification: Notification) -> impl IntoView {
+    let InboxActions { mark_read, .. } = expect_context();
+    let Notification { id, kind, actor_name, story_id, story_title, excerpt, created_at, read_at, .. } = notification;
+    let action = match kind {
+        NotificationKind::StoryReply => "replied to your story",
+        NotificationKind::CommentReply => "replied to your comment on",
+        NotificationKind::Mention => "mentioned you in",
+    };
+    let actor = match actor_name {
+        Some(actor_name) => Either::Left(view! { <UserLink user_name=actor_name /> }),
+        None => Either::Right("Someone"),
+    };
+    let unread = read_at.is_none();
+
+    view! {
+        <li class:unread=unread>
+            <div>
+                {actor} " " {action} " " <A href=format!("/{STORY}/{story_id}")>{story_title}</A>
+            </div>
+            {excerpt.map(|excerpt| view! { <blockquote>{excerpt}</blockquote> })}
+            <div class="meta".to_string()>
+                <RelativeTime from=created_at />
+                {unread
+                    .then(|| {
+                        view! {
+                            <ActionForm action=mark_read>
+                                <input type="hidden" name="id" value=id />
+                                <button type="submit">"Mark read"</button>
+                            </ActionForm>
+                        }
+                    })}
+            </div>
+        </li>
+    }
+}
+
+/// The bookmarks saved in this browser, shared so every toggle and the
+/// favorites page follow changes made anywhere. Browsers all act as the site
+/// user, so bookmarks and their notes stay in the browser.
+#[derive(Clone, Copy)]
+struct BookmarkActions {
+    target: ServerAction<BookmarkTarget>,
+    bookmarks: Signal<Vec<Bookmark>>,
+    set_bookmarks: WriteSignal<Vec<Bookmark>>,
+}
+
+impl BookmarkActions {
+    fn new() -> Self {
+        let target = ServerAction::<BookmarkTarget>::new();
+        let (bookmarks, set_bookmarks, _) = use_local_storage::<Vec<Bookmark>, JsonSerdeCodec>(BOOKMARKS_KEY);
+        Effect::new(move |_| {
+            if let Some(Ok(bookmark)) = target.value().get() {
+                set_bookmarks.update(|bookmarks| {
+                    bookmarks.retain(|kept| (kept.story_id, kept.comment_id) != (bookmark.story_id, bookmark.comment_id));
+                    bookmarks.insert(0, bookmark);
+                });
+            }
+        });
+        BookmarkActions { target, bookmarks, set_bookmarks }
+    }
+
+    fn saved(&self, story_id: i32, comment_id: Option<i32>) -> bool {
+        self.bookmarks.with(|bookmarks| {
+            bookmarks.iter().any(|bookmark| (bookmark.story_id, bookmark.comment_id) == (story_id, comment_id))
+        })
+    }
+
+    fn save(&self, story_id: i32, comment_id: Option<i32>) {
+        self.target.dispatch(BookmarkTarget { story_id, comment_id });
+    }
+
+    fn remove(&self, story_id: i32, comment_id: Option<i32>) {
+        self.set_bookmarks.update(|bookmarks| {
+            bookmarks.retain(|bookmark| (bookmark.story_id, bookmark.comment_id) != (story_id, comment_id));
+        });
+    }
+
+    /// Replaces a bookmark's note; blank notes are kept as none.
+    fn note(&self, story_id: i32, comment_id: Option<i32>, note: String) {
+        let note = Some(note.trim().to_string()).filter(|note| !note.is_empty());
+        self.set_bookmarks.update(|bookmarks| {
+            bookmarks
+                .iter_mut()
+                .filter(|bookmark| (bookmark.story_id, bookmark.comment_id) == (story_id, comment_id))
+                .for_each(|bookmark| bookmark.note = note.clone());
+        });
+    }
+}
+
+#[component]
+fn BookmarkToggle(story_id: i32, #[prop(optional_no_strip)] comment_id: Option<i32>) -> impl IntoView {
+    let bookmarks: BookmarkActions = expect_context();
+    let saved = move || bookmarks.saved(story_id, comment_id);
+    let toggle = move |_| {
+        if saved() {
+            bookmarks.remove(story_id, comment_id);
+        } else {
+            bookmarks.save(story_id, comment_id);
+        }
+    };
+
+    view! {
+        <button class="bookmark".to_string() class:bookmarked=saved on:click=toggle>
+            {move || if saved() { "★ Saved" } else { "☆ Save" }}
+        </button>
+    }
+}
+
+/// A user's public bookmarks at `/closure/{name}/favorites`, or the ones
+/// saved in this browser at `/favorites`.
+#[component]
+fn FavoritesPage() -> impl IntoView {
+    let params = use_params_map();
+
+    move || match params.with(|params| params.get("name")) {
+        Some(name) => Either::Left(view! { <UserFavorites name /> }),
+        None => Either::Right(view! { <LocalFavorites /> }),
+    }
+}
+
+#[component]
+fn UserFavorites(name: String) -> impl IntoView {
+    let favorites = Resource::new(move || name.clone(), |name| favorites_get(Some(name)));
+
+    view! {
+        <Transition fallback=|| view! { <p>{LOADING}</p> }>
+            {move || {
+                favorites
+                    .get()
+                    .map(|favorites| match favorites {
+                        Ok(favorites) => Either::Left(view! { <FavoritesList favorites /> }),
+                        Err(_) => Either::Right(NotFound),
+                    })
+            }}
+        </Transition>
+    }
+}
+
+#[component]
+fn FavoritesList(favorites: Favorites) -> impl IntoView {
+    let Favorites { user_name, bookmarks, .. } = favorites;
+    let page = format!("/{PROFILE}/{user_name}/{FAVORITES}");
+    let title = format!("{user_name}'s favorites");
+
+    view! {
+        <Title text=page_title(&title) />
+        <Link rel="alternate" type_="application/atom+xml" href=format!("{page}.atom") />
+        <main>
+            <h1>{title}</h1>
+            <div class="meta".to_string()>
+                <a href=format!("{page}.json") rel="external">"Export JSON"</a>
+                <a href=format!("{page}.atom") rel="external">"Atom feed"</a>
+            </div>
+            {if bookmarks.is_empty() {
+                Either::Left(view! { <p>{TITLE_EMPTY}</p> })
+            } else {
+                Either::Right(view! {
+                    <ul class="favorites".to_string()>
+                        {bookmarks
+                            .into_iter()
+                            .map(|bookmark| view! { <FavoriteItem bookmark /> })
+                            .collect_view()}
+                    </ul>
+                })
+            }}
+        </main>
+    }
+}
+
+/// The bookmarks saved in this browser, with their notes.
+#[component]
+fn LocalFavorites() -> impl IntoView {
+    let BookmarkActions { bookmarks, .. } = expect_context();
+
+    view! {
+        <Title text=page_title("Favorites") />
+        <main>
+            <h1>"Favorites"</h1>
+            <p class="meta".to_string()>"Saved in this browser."</p>
+            <Show when=move || !bookmarks.with(Vec::is_empty) fallback=|| view! { <p>{TITLE_EMPTY}</p> }>
+                <ul class="favorites".to_string()>
+                    <For
+                        each=move || bookmarks.get()
+                        key=|bookmark| (bookmark.story_id, bookmark.comment_id)
+                        children=|bookmark| view! { <FavoriteItem bookmark local=true /> }
+                    />
+                </ul>
+            </Show>
+        </main>
+    }
+}
+
+#[component]
+fn FavoriteItem(bookmark: Bookmark, #[prop(optional)] local: bool) -> impl IntoView {
+    let Bookmark { story_id, story_title, story_url, comment_id, excerpt, note, created_at, .. } = bookmark;
+    let target = match comment_id {
+        Some(_) => Either::Left(view! {
+            "Comment on " <A href=format!("/{STORY}/{story_id}")>{story_title}</A>
+        }),
+        None => Either::Right(view! { <StoryLink story_id title=story_title url=story_url /> }),
+    };
+
+    view! {
+        <li>
+            <div>{target}</div>
+            {excerpt.map(|excerpt| view! { <blockquote>{excerpt}</blockquote> })}
+            {local
+                .then(|| {
+                    let bookmarks: BookmarkActions = expect_context();
+                    view! {
+                        <textarea
+                            placeholder="Private note"
+                            on:change=move |ev| bookmarks.note(story_id, comment_id, event_target_value(&ev))
+                        >
+                            {note}
+                        </textarea>
+                    }
+                })}
+            <div class="meta".to_string()>
+                <RelativeTime from=created_at />
+                {local.then(|| view! { <BookmarkToggle story_id comment_id /> })}
+            </div>
+        </li>
+    }
+}
+
+#[component]
+fn ProfilePage() -> impl IntoView {
+    let discard = ServerAction::<DraftDiscard>::new();
+    let params = use_params_map();
+    let profile = Resource::new(
+        move || (params.with(|params| params.get("name")), discard.version().get()),
+        |(name, _)| profile_get(name),
+    );
+
+    view! {
+        <Transition fallback=|| view! { <p>{LOADING}</p> }>
+            {move || {
+                profile
+                    .get()
+                    .map(|profile| match profile {
+                        Ok(profile) => Either::Left(view! { <ProfileView profile discard /> }),
+                        Err(_) => Either::Right(NotFound),
+                    })
+            }}
+        </Transition>
+    }
+}
+
+#[component]
+fn ProfileView(profile: Profile, discard: ServerAction<DraftDiscard>) -> impl IntoView {
+    let Profile { user_name, created_at, favorites_public, owned, drafts } = profile;
+    let favorites = (favorites_public || owned).then(|| {
+        view! { <A href=format!("/{PROFILE}/{user_name}/{FAVORITES}")>"Favorites"</A> }
+    });
+    let drafts = owned.then(|| {
+        // Browsers keep their drafts to themselves, so those are only read
+        // once hydrated.
+        let local = RwSignal::new(Vec::new());
+        Effect::new(move |_| local.set(local_drafts()));
+        let kept = !drafts.is_empty();
+        let kept_drafts = drafts
+            .into_iter()
+            .map(|draft| {
+                let (story_id, parent_id) = (draft.story_id, draft.parent_id);
+                let discard = move || {
+                    discard.dispatch(DraftDiscard { story_id, parent_id });
+                };
+                view! { <DraftItem draft discard /> }
+            })
+            .collect_view();
+        view! {
+            <h2>"Drafts"</h2>
+            {move || (!kept && local.with(Vec::is_empty)).then(|| view! { <p>{TITLE_EMPTY}</p> })}
+            <ul class="drafts".to_string()>
+                {kept_drafts}
+                <For
+                    each=move || local.get()
+                    key=|(key, draft)| (key.clone(), draft.updated_at)
+                    children=move |(key, draft)| {
+                        let discard = move || {
+                            if let Ok(Some(storage)) = window().local_storage() {
+                                let _ = storage.remove_item(&key);
+                            }
+                            local.update(|drafts| drafts.retain(|(kept, _)| *kept != key));
+                        };
+                        view! { <DraftItem draft discard /> }
+                    }
+                />
+            </ul>
+        }
+    });
+
+    view! {
+        <Title text=page_title(&user_name) />
+        <main>
+            <h1>{user_name.clone()}</h1>
+            <div class="meta".to_string()>
+                <span>"Joined " <RelativeTime from=created_at /></span>
+                {favorites}
+            </div>
+            {drafts}
+        </main>
+    }
+}
+
+/// An open draft, linking to the editor that resumes it.
+#[component]
+fn DraftItem(draft: Draft, discard: impl Fn() + 'static) -> impl IntoView {
+    let Draft { story_id, story_title, parent_id, title, text, updated_at, .. } = draft;
+    let target = match story_id {
+        Some(story_id) => Either::Left(view! {
+            {if parent_id.is_some() { "Reply on " } else { "Comment on " }}
+            <A href=format!("/{STORY}/{story_id}")>{story_title.unwrap_or_else(|| format!("story {story_id}"))}</A>
+        }),
+        None => {
+            let title = title.filter(|title| !title.trim().is_empty());
+            Either::Right(view! {
+                <A href=format!("/{STORY}/{NEW}")>{title.unwrap_or_else(|| "Untitled story".into())}</A>
+            })
+        }
+    };
+    let excerpt: String = text.chars().take(200).collect();
+
+    view! {
+        <li>
+            <div>{target}</div>
+            {(!excerpt.trim().is_empty()).then(|| view! { <blockquote>{excerpt}</blockquote> })}
+            <div class="meta".to_string()>
+                <span>"Saved " <RelativeTime from=updated_at /></span>
+                <button type="button" on:click=move |_| discard()>"Discard"</button>
             </div>
         </li>
     }
@@ -0,0 +1,364 @@
+//! Operational tasks against the configured database, e.g.
+//! `news-admin user create ada --admin` or `news-admin export backup.tar.gz`.
+//! Reads the same configuration as the server.
+
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use news::{
+    model::{ApiTokenCreateArgs, TokenScope},
+    server::{
+        account::{self, DeletionMode},
+        admin, archive, audit, auth,
+        cache::StoryCache,
+        config::Config,
+        hn, tags, user_by_name,
+    },
+};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "news-admin", version, about = "Operate a Lambda Function site")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Apply or revert schema migrations.
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Manage user accounts.
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Issue and revoke users' API tokens.
+    #[command(subcommand)]
+    Token(TokenCommand),
+    /// Curate the tag vocabulary and retag stories.
+    #[command(subcommand)]
+    Tag(TagCommand),
+    /// Recompute derived data.
+    #[command(subcommand)]
+    Rebuild(RebuildCommand),
+    /// Write users, stories, comments, votes, tags, polls and the audit trail
+    /// to a `.tar.gz` archive.
+    Export {
+        /// Output file, or `-` for stdout.
+        path: PathBuf,
+    },
+    /// Restore an archive into a database without content.
+    Import {
+        /// Archive file, or `-` for stdin.
+        path: PathBuf,
+    },
+    /// Import Hacker News items and users; re-runs skip what was imported.
+    ImportHn {
+        /// JSON dump, or `-` for stdin.
+        path: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MigrateCommand {
+    /// Apply pending migrations.
+    Run,
+    /// Revert migrations newer than a version. Refuses to go past one that
+    /// fixed data it can't restore.
+    Revert {
+        #[arg(long)]
+        to: i64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum UserCommand {
+    /// Create a user, optionally with a password and admin rights.
+    Create {
+        name: String,
+        #[arg(long)]
+        admin: bool,
+        /// Read a password from stdin, prompting on a terminal.
+        #[arg(long)]
+        password: bool,
+    },
+    /// Set a user's password, read from stdin, prompting on a terminal.
+    Password { name: String },
+    /// Rename a user; their existing mentions follow the new name.
+    Rename { name: String, new_name: String },
+    /// Ban a user; their API tokens stop working.
+    Ban { name: String },
+    /// Lift a ban.
+    Unban { name: String },
+    /// Write everything stored about a user as JSON.
+    Export {
+        name: String,
+        /// Output file, or `-` for stdout.
+        path: PathBuf,
+    },
+    /// Delete a user, handing their stories and comments to the tombstone user.
+    Delete {
+        name: String,
+        /// `anonymize` keeps the text, `remove` replaces it.
+        #[arg(long)]
+        content: DeletionMode,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TokenCommand {
+    /// List a user's tokens.
+    List { user: String },
+    /// Create a token for a user and print its secret, which isn't shown
+    /// again.
+    Create {
+        user: String,
+        #[arg(long)]
+        name: String,
+        /// `read`, `submit`, `comment`, `vote`, `write` or `account`; repeat for more.
+        #[arg(long = "scope", required = true)]
+        scopes: Vec<TokenScope>,
+    },
+    /// Revoke one of a user's tokens.
+    Revoke { user: String, id: i32 },
+}
+
+#[derive(Subcommand, Debug)]
+enum TagCommand {
+    /// List tags with how many stories carry each.
+    List,
+    /// Add a tag to the vocabulary.
+    Create {
+        name: String,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Remove a tag from the vocabulary and from every story.
+    Delete { name: String },
+    /// Replace a story's tags; give none to clear them.
+    Set { story_id: i32, tags: Vec<String> },
+}
+
+#[derive(Subcommand, Debug)]
+enum RebuildCommand {
+    /// Story scores and comment counts.
+    Counts,
+    /// Canonical story links used to detect duplicates.
+    CanonicalUrls,
+    /// Mentions in stories and comments that weren't recorded.
+    Mentions,
+    /// Rendered story Markdown.
+    Markdown,
+}
+
+/// A password from stdin, never the command line, where it would end up in
+/// the shell history and the process list.
+fn read_password() -> anyhow::Result<String> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        eprint!("Password: ");
+        io::stderr().flush()?;
+    }
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line)?;
+    let password = line.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        bail!("password is empty");
+    }
+    Ok(password)
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", describe(&err));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Joins the error chain, skipping causes a wrapper already printed inline.
+fn describe(err: &anyhow::Error) -> String {
+    let mut message = err.to_string();
+    for cause in err.chain().skip(1).map(ToString::to_string) {
+        if !message.contains(&cause) {
+            message = format!("{message}: {cause}");
+        }
+    }
+    message
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let pool = config.database.connect().await.context("connecting to the database")?;
+
+    match cli.command {
+        Command::Migrate(MigrateCommand::Run) => {
+            admin::MIGRATOR.run(&pool).await?;
+            eprintln!("Migrations applied");
+        }
+        Command::Migrate(MigrateCommand::Revert { to }) => {
+            let reverted = admin::migrations_revert(&pool, to).await?;
+            eprintln!("Reverted {} migration(s)", reverted.len());
+        }
+        Command::User(UserCommand::Create { name, admin, password }) => {
+            let password = if password { Some(read_password()?) } else { None };
+            let user = admin::user_create(&pool, &name, password.as_deref(), admin).await?;
+            eprintln!("Created user {} with id {}", user.username, user.id);
+        }
+        Command::User(UserCommand::Password { name }) => {
+            let password = read_password()?;
+            admin::user_set_password(&pool, &name, &password).await?;
+            eprintln!("Password updated for {name}");
+        }
+        Command::User(UserCommand::Rename { name, new_name }) => {
+            let rendered = admin::user_rename(&pool, &name, &new_name).await?;
+            eprintln!("Renamed {name} to {new_name}; rendered {rendered} stories again");
+        }
+        Command::User(UserCommand::Ban { name }) => {
+            admin::user_set_banned(&pool, &name, true).await?;
+            eprintln!("Banned {name}");
+        }
+        Command::User(UserCommand::Unban { name }) => {
+            admin::user_set_banned(&pool, &name, false).await?;
+            eprintln!("Unbanned {name}");
+        }
+        Command::User(UserCommand::Export { name, path }) => {
+            let user = user_by_name(&pool, &name).await?;
+            let data = account::personal_data(&pool, user.id).await?;
+            let out: Box<dyn Write> = if is_stdio(&path) {
+                Box::new(io::stdout().lock())
+            } else {
+                Box::new(File::create(&path).with_context(|| format!("creating {}", path.display()))?)
+            };
+            let mut out = BufWriter::new(out);
+            serde_json::to_writer_pretty(&mut out, &data)?;
+            out.flush()?;
+            eprintln!("Exported account {name}");
+        }
+        Command::User(UserCommand::Delete { name, content }) => {
+            let user = user_by_name(&pool, &name).await?;
+            let deleted = account::account_delete(&pool, user.id, content, None).await?;
+            // Reaches a shared Valkey cache; in-process caches expire on their own.
+            StoryCache::connect(&config.cache).await?.invalidate().await;
+            eprintln!(
+                "Deleted {name}; {} stories and {} comments reassigned, {} votes removed",
+                deleted.stories, deleted.comments, deleted.votes
+            );
+        }
+        Command::Token(TokenCommand::List { user }) => {
+            let user = user_by_name(&pool, &user).await?;
+            for token in auth::token_list(&pool, user.id).await? {
+                let scopes: Vec<_> = token.scopes.iter().map(TokenScope::as_str).collect();
+                let used = token.last_used_at.map(|used| used.to_rfc3339()).unwrap_or_default();
+                println!("{}\t{}\t{}\t{}", token.id, token.name, scopes.join(","), used);
+            }
+        }
+        Command::Token(TokenCommand::Create { user, name, scopes }) => {
+            let user = user_by_name(&pool, &user).await?;
+            let args = ApiTokenCreateArgs::builder()
+                .name(name)
+                .read(scopes.contains(&TokenScope::Read))
+                .submit(scopes.contains(&TokenScope::Submit))
+                .comment(scopes.contains(&TokenScope::Comment))
+                .vote(scopes.contains(&TokenScope::Vote))
+                .write(scopes.contains(&TokenScope::Write))
+                .account(scopes.contains(&TokenScope::Account))
+                .build();
+            let created = auth::token_create(&pool, user.id, args).await?;
+            eprintln!("Created token {} for {}", created.token.id, user.username);
+            println!("{}", created.secret);
+        }
+        Command::Token(TokenCommand::Revoke { user, id }) => {
+            let user = user_by_name(&pool, &user).await?;
+            auth::token_revoke(&pool, user.id, id).await?;
+            eprintln!("Revoked token {id} of {}", user.username);
+        }
+        Command::Tag(TagCommand::List) => {
+            for tag in tags::tag_list(&pool).await? {
+                println!("{}\t{}\t{}", tag.name, tag.story_count, tag.description.unwrap_or_default());
+            }
+        }
+        Command::Tag(TagCommand::Create { name, description }) => {
+            tags::tag_create(&pool, &name, description.as_deref()).await?;
+            eprintln!("Created tag {name}");
+        }
+        Command::Tag(TagCommand::Delete { name }) => {
+            tags::tag_delete(&pool, &name).await?;
+            eprintln!("Deleted tag {name}");
+        }
+        Command::Tag(TagCommand::Set { story_id, tags }) => {
+            let tags = tags::story_tags_set(&pool, story_id, &tags).await?;
+            let details = serde_json::json!({ "tags": tags });
+            audit::record(&pool, None, audit::STORY_TAGGED, Some(story_id), details).await?;
+            eprintln!("Tagged story {story_id} with [{}]", tags.join(", "));
+        }
+        Command::Rebuild(RebuildCommand::Counts) => {
+            let changed = admin::counts_rebuild(&pool).await?;
+            eprintln!("Corrected counts on {changed} stories");
+        }
+        Command::Rebuild(RebuildCommand::CanonicalUrls) => {
+            let changed = admin::canonical_urls_rebuild(&pool, |done| eprint!("\rScanned {done} stories")).await?;
+            eprintln!("\rCorrected canonical links on {changed} stories");
+        }
+        Command::Rebuild(RebuildCommand::Mentions) => {
+            let scanned = admin::mentions_rebuild(&pool, |done| eprint!("\rScanned {done} texts")).await?;
+            eprintln!("\rScanned {scanned} texts");
+        }
+        Command::Rebuild(RebuildCommand::Markdown) => {
+            let rendered = admin::markdown_rerender(&pool, |done| eprint!("\rRendered {done} stories")).await?;
+            eprintln!("\rRendered {rendered} stories");
+        }
+        Command::Export { path } => {
+            let out: Box<dyn Write> = if is_stdio(&path) {
+                Box::new(io::stdout().lock())
+            } else {
+                Box::new(File::create(&path).with_context(|| format!("creating {}", path.display()))?)
+            };
+            let manifest = archive::export(&pool, BufWriter::new(out), |table, done| {
+                eprint!("\rExported {done} rows ({table})")
+            })
+            .await?;
+            let total: u64 = manifest.files.iter().map(|file| file.rows).sum();
+            eprintln!("\rExported {total} rows");
+        }
+        Command::Import { path } => {
+            let input: Box<dyn Read> = if is_stdio(&path) {
+                Box::new(io::stdin().lock())
+            } else {
+                let file = File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+                Box::new(BufReader::new(file))
+            };
+            let manifest = archive::import(&pool, input, |done| eprint!("\rImported {done} rows")).await?;
+            let total: u64 = manifest.files.iter().map(|file| file.rows).sum();
+            eprintln!("\rImported {total} rows from an archive of {}", manifest.created_at);
+        }
+        Command::ImportHn { path } => {
+            let input: Box<dyn Read> = if is_stdio(&path) {
+                Box::new(io::stdin().lock())
+            } else {
+                let file = File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+                Box::new(BufReader::new(file))
+            };
+            let summary = hn::import(&pool, input, |done| eprint!("\rRead {done} records")).await?;
+            eprintln!(
+                "\nImported {} users, {} stories and {} comments; {} already present, {} skipped",
+                summary.users, summary.stories, summary.comments, summary.existing, summary.skipped
+            );
+        }
+    }
+
+    pool.close().await;
+    Ok(())
+}
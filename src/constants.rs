@@ -3,12 +3,43 @@
 pub const LAMBDA: &str = "λ";
 pub const LAMBDA_FUNCTION: &str = "Lambda Function";
 pub const PAGE_SIZE: i64 = 32;
+pub const DUPLICATE_WINDOW_DAYS: i64 = 30;
+pub const PREVIEW_TIMEOUT_SECS: u64 = 5;
+pub const PREVIEW_MAX_BYTES: usize = 512 * 1024;
+pub const PREVIEW_MAX_REDIRECTS: usize = 5;
+pub const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+pub const WEBHOOK_POLL_SECS: u64 = 5;
+pub const WEBHOOK_BACKOFF_SECS: i64 = 30;
+pub const WEBHOOK_MAX_ATTEMPTS: i32 = 8;
+pub const WEBHOOK_BATCH_SIZE: i64 = 16;
+pub const INBOX_SIZE: i64 = 100;
+pub const LIVE_BUFFER: usize = 256;
+pub const LIVE_RETRY_SECS: u64 = 5;
+pub const READINESS_TIMEOUT_SECS: u64 = 2;
+pub const SESSION_DAYS: i64 = 30;
+pub const SESSION_COOKIE: &str = "lf_session";
+pub const STORY_MAX_TAGS: usize = 3;
+pub const JOB_LISTING_DAYS: i64 = 30;
+pub const POLL_MAX_OPTIONS: usize = 10;
+pub const DRAFT_KEY_PREFIX: &str = "draft-";
+pub const HIDDEN_STORIES_KEY: &str = "hidden-stories";
+pub const TAG_PREFERENCES_KEY: &str = "tag-preferences";
+pub const BOOKMARKS_KEY: &str = "bookmarks";
+pub const STORY_VISITS_KEY: &str = "story-visits";
 
 pub const STORY: &str = "bind";
 pub const COMMENT: &str = "effect";
 pub const PROFILE: &str = "closure";
+pub const TAG: &str = "tag";
 
 pub const NEW: &str = "apply";
+pub const TOKENS: &str = "tokens";
+pub const HOOKS: &str = "hooks";
+pub const INBOX: &str = "inbox";
+pub const FAVORITES: &str = "favorites";
+pub const ACCOUNT: &str = "account";
+pub const LOGIN: &str = "login";
+pub const LIVE_PATH: &str = "/live";
 
 pub const APPLY: &str = "→ Apply";
 pub const EDIT: &str = "β Reduce";
@@ -22,4 +53,5 @@ pub const LOADING_4: &str = "Verifying naturality…";
 
 pub const TITLE_ERROR: &str = "⊥ Contradiction";
 pub const TITLE_SUCCESS: &str = "□ Q.E.D.";
-pub const TITLE_EMPTY: &str = "∅ Empty Set";
\ No newline at end of file
+pub const TITLE_EMPTY: &str = "∅ Empty Set";
+pub const TITLE_DUPLICATE: &str = "≅ Isomorphism";
\ No newline at end of file
@@ -0,0 +1,38 @@
+use url::Url;
+
+const TRACKING_PREFIX: &str = "utm_";
+
+/// Reduces a submitted URL to the form used for duplicate detection: scheme,
+/// `www.`, fragments, tracking params and trailing slashes are ignored.
+pub fn canonicalize_url(url: &str) -> Option<String> {
+    let mut parsed = Url::parse(url.trim()).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let host = parsed.host_str()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    parsed.set_host(Some(&host)).ok()?;
+    parsed.set_scheme("https").ok()?;
+    parsed.set_fragment(None);
+
+    let query: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| !key.to_lowercase().starts_with(TRACKING_PREFIX))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if query.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(query);
+    }
+
+    let path = parsed.path().trim_end_matches('/').to_string();
+    parsed.set_path(&path);
+
+    let canonical = parsed.to_string();
+    Some(match canonical.strip_suffix('/') {
+        Some(stripped) if parsed.query().is_none() => stripped.to_string(),
+        _ => canonical,
+    })
+}
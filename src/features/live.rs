@@ -0,0 +1,15 @@
+use crate::{constants::LIVE_PATH, model::LiveEvent};
+use codee::string::JsonSerdeCodec;
+use leptos::prelude::*;
+use leptos_use::use_event_source;
+
+/// Subscribes to live updates for one story, or to new submissions when
+/// `story_id` is `None`. The stream closes along with the calling owner.
+pub fn use_live(story_id: Option<i32>) -> Signal<Option<LiveEvent>> {
+    let url = match story_id {
+        Some(story_id) => format!("{LIVE_PATH}?story={story_id}"),
+        None => LIVE_PATH.to_string(),
+    };
+
+    use_event_source::<LiveEvent, JsonSerdeCodec>(&url).data
+}
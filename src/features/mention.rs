@@ -0,0 +1,42 @@
+use std::ops::Range;
+
+/// Characters allowed in a mentioned name, which covers every name the site
+/// creates itself and those imported from Hacker News.
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Byte ranges of every `@mention` in `text`, each including its `@`. An `@`
+/// inside a word, as in an email address, is not a mention.
+pub fn mention_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut previous = None;
+
+    for (index, c) in text.char_indices() {
+        let starts_mention = c == '@' && !previous.is_some_and(|p: char| is_name_char(p) || p == '@');
+        previous = Some(c);
+        if !starts_mention {
+            continue;
+        }
+
+        let rest = &text[index + 1..];
+        let end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+        if end > 0 {
+            spans.push(index..index + 1 + end);
+        }
+    }
+
+    spans
+}
+
+/// The distinct names `@mentioned` in `text`, in order of appearance.
+pub fn mentioned_names(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for span in mention_spans(text) {
+        let name = &text[span.start + 1..span.end];
+        if !names.iter().any(|seen| seen == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
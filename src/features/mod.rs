@@ -1,4 +1,10 @@
+pub mod canonical;
 pub mod chrono;
+pub mod live;
+pub mod mention;
+pub mod poll;
+pub mod site;
 pub mod styled;
+pub mod tag;
 pub mod ui;
 pub mod utils;
\ No newline at end of file
@@ -0,0 +1,63 @@
+use crate::{features::utils::pluralize, model::Poll};
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use serde::{Deserialize, Deserializer};
+
+/// A poll's options as submitted, trimmed, with blank ones left out, so a
+/// form can offer more option fields than a poll uses.
+pub fn poll_options(options: &[String]) -> Vec<String> {
+    options
+        .iter()
+        .map(|option| option.trim().to_string())
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+/// `votes` as a whole percentage of `total`. Polls count against voters, so
+/// a multiple-choice poll's options can add up to more than 100%.
+pub fn percent(votes: i64, total: i64) -> i64 {
+    if total <= 0 {
+        return 0;
+    }
+    (votes * 100 + total / 2) / total
+}
+
+/// Results as plain text, one option per line, for feeds.
+pub fn poll_results_text(poll: &Poll) -> String {
+    let mut lines: Vec<String> = poll
+        .options
+        .iter()
+        .map(|option| {
+            let votes = pluralize(option.votes as i32, "vote", "votes");
+            format!("{}: {} {votes} ({}%)", option.text, option.votes, percent(option.votes, poll.voters))
+        })
+        .collect();
+    let voters = pluralize(poll.voters as i32, "voter", "voters");
+    lines.push(match poll.closed {
+        true => format!("{} {voters}, closed", poll.voters),
+        false => format!("{} {voters}", poll.voters),
+    });
+    lines.join("\n")
+}
+
+/// Reads a poll's close time as RFC 3339, or as the offset-less value of a
+/// `datetime-local` input, taken as UTC. Blank means the poll never closes.
+pub fn deserialize_closes_at<'de, D>(deserializer: D) -> Result<Option<DateTime<FixedOffset>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(value) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(closes_at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(Some(closes_at));
+    }
+    ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|closes_at| Some(closes_at.and_utc().fixed_offset()))
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid close time {value:?}")))
+}
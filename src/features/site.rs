@@ -0,0 +1,17 @@
+use crate::model::SiteSettings;
+use leptos::prelude::*;
+
+/// Provides the configured site settings, serialised on the server so that
+/// hydration sees the same values without a round trip.
+pub fn provide_site() {
+  let site = SharedValue::new(|| use_context::<SiteSettings>().unwrap_or_default()).into_inner();
+  provide_context(site);
+}
+
+pub fn use_site() -> SiteSettings {
+  use_context::<SiteSettings>().unwrap_or_default()
+}
+
+pub fn page_title(page: &str) -> String {
+  format!("{} :: {}", page, use_site().name)
+}
@@ -1,5 +1,3 @@
-use leptos::prelude::*;
-
 #[macro_export]
 macro_rules! styled {
     ($component:ident, $tag:expr, $styles:expr) => {
@@ -0,0 +1,20 @@
+/// Tag names are short lowercase slugs such as `type-theory`, so they can be
+/// used in paths as they are.
+pub fn is_tag_name(name: &str) -> bool {
+    (1..=32).contains(&name.len())
+        && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+/// The distinct tags among `names`, trimmed and sorted, as stories keep them.
+pub fn normalize_tags(names: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
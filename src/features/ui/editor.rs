@@ -0,0 +1,123 @@
+use std::ops::Range;
+
+/// The Markdown a toolbar button puts around the selection.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Bold,
+    Italic,
+    Code,
+    Link,
+    Quote,
+    List,
+    CodeBlock,
+}
+
+impl Format {
+    pub const ALL: [Format; 7] = [
+        Format::Bold,
+        Format::Italic,
+        Format::Code,
+        Format::Link,
+        Format::Quote,
+        Format::List,
+        Format::CodeBlock,
+    ];
+
+    /// The button's face, kept short enough for a narrow toolbar.
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Bold => "B",
+            Format::Italic => "I",
+            Format::Code => "<>",
+            Format::Link => "Link",
+            Format::Quote => ">",
+            Format::List => "-",
+            Format::CodeBlock => "```",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Format::Bold => "Bold",
+            Format::Italic => "Italic",
+            Format::Code => "Inline code",
+            Format::Link => "Link",
+            Format::Quote => "Quote",
+            Format::List => "List",
+            Format::CodeBlock => "Code block",
+        }
+    }
+}
+
+/// Applies `format` to the selection `start..end` of `text`, returning the
+/// new text and selection. Offsets count UTF-16 code units, as a textarea's
+/// selection does. Inline formats wrap the selection and keep it selected,
+/// so an empty one leaves the caret between the markers; a link selects its
+/// placeholder URL; quotes and lists prefix every line the selection touches.
+pub fn apply_format(text: &str, start: u32, end: u32, format: Format) -> (String, u32, u32) {
+    let (start, end) = (byte_offset(text, start.min(end)), byte_offset(text, start.max(end)));
+    let (before, selected, after) = (&text[..start], &text[start..end], &text[end..]);
+
+    let (replacement, selection) = match format {
+        Format::Bold => wrap(selected, "**", "**"),
+        Format::Italic => wrap(selected, "_", "_"),
+        Format::Code => wrap(selected, "`", "`"),
+        Format::Link => {
+            let replacement = format!("[{selected}](https://)");
+            let url = replacement.len() - "https://)".len()..replacement.len() - 1;
+            (replacement, url)
+        }
+        Format::CodeBlock => {
+            let opening = if before.is_empty() || before.ends_with('\n') { "```\n" } else { "\n```\n" };
+            wrap(selected, opening, "\n```")
+        }
+        Format::Quote | Format::List => {
+            let marker = if format == Format::Quote { "> " } else { "- " };
+            let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+            let line_end = end + after.find('\n').unwrap_or(after.len());
+            let lines = &text[line_start..line_end];
+            let replacement: String = lines
+                .split('\n')
+                .map(|line| format!("{marker}{line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let length = replacement.len();
+            return splice(&text[..line_start], &replacement, &text[line_end..], 0..length);
+        }
+    };
+
+    splice(before, &replacement, after, selection)
+}
+
+/// Puts `opening` and `closing` around `selected`, selecting what's inside.
+fn wrap(selected: &str, opening: &str, closing: &str) -> (String, Range<usize>) {
+    (
+        format!("{opening}{selected}{closing}"),
+        opening.len()..opening.len() + selected.len(),
+    )
+}
+
+/// Joins the pieces, turning `selection`, in bytes of `replacement`, into
+/// UTF-16 offsets into the result.
+fn splice(before: &str, replacement: &str, after: &str, selection: Range<usize>) -> (String, u32, u32) {
+    let offset = utf16_len(before);
+    let start = offset + utf16_len(&replacement[..selection.start]);
+    let end = offset + utf16_len(&replacement[..selection.end]);
+    (format!("{before}{replacement}{after}"), start, end)
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+/// The byte offset of a UTF-16 offset, clamped to the text.
+fn byte_offset(text: &str, utf16: u32) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= utf16 {
+            return index;
+        }
+        units += c.len_utf16() as u32;
+    }
+    text.len()
+}
@@ -1,47 +1,106 @@
+use crate::{constants::PROFILE, features::mention::mention_spans, model::Mention};
 use comrak::{
-    format_html, format_html_with_plugins, markdown_to_html_with_plugins, nodes::NodeValue,
-    parse_document, plugins::syntect::SyntectAdapterBuilder, Arena, ExtensionOptions, Options,
-    Plugins,
+    format_html_with_plugins,
+    nodes::{AstNode, NodeLink, NodeValue},
+    parse_document,
+    plugins::syntect::SyntectAdapterBuilder,
+    Arena, ExtensionOptions, Options, Plugins,
 };
 use leptos::prelude::*;
 use std::cmp::min;
 
 
-#[component]
-pub fn Markdown(text: String) -> impl IntoView {
-    let html = Memo::new(move |_| {
-        let arena = Arena::new();
-
-        let extension = ExtensionOptions::builder()
-            .alerts(true)
-            .table(true)
-            .underline(true)
-            .build();
-
-        let options = Options {
-            extension,
-            ..Options::default()
-        };
+/// Renders story Markdown to HTML, demoting headings below the page's own
+/// and linking `mentions` to their users' profiles.
+pub fn render_markdown(text: &str, mentions: &[Mention]) -> String {
+    let arena = Arena::new();
+
+    let extension = ExtensionOptions::builder()
+        .alerts(true)
+        .table(true)
+        .underline(true)
+        .build();
+
+    let options = Options {
+        extension,
+        ..Options::default()
+    };
+
+    let syntect = SyntectAdapterBuilder::new()
+        .theme("base16-ocean.light")
+        .build();
+    let mut plugins = Plugins::default();
+
+    plugins.render.codefence_syntax_highlighter = Some(&syntect);
+
+    let root = parse_document(&arena, text, &options);
+
+    for node in root.children() {
+        if let NodeValue::Heading(ref mut heading) = node.data.borrow_mut().value {
+            heading.level = min(heading.level + 3, 6);
+        }
+    }
+
+    if !mentions.is_empty() {
+        link_mentions(&arena, root, mentions);
+    }
 
-        let syntect = SyntectAdapterBuilder::new()
-            .theme("base16-ocean.light")
-            .build();
-        let mut plugins = Plugins::default();
+    let mut html = vec![];
+    format_html_with_plugins(root, &options, &mut html, &plugins).unwrap();
+
+    String::from_utf8(html).unwrap()
+}
 
-        plugins.render.codefence_syntax_highlighter = Some(&syntect);
+/// Splits text outside of links and code around each resolved mention,
+/// putting a profile link in its place. Unresolved mentions stay text.
+fn link_mentions<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, mentions: &[Mention]) {
+    let texts: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Text(_)))
+        .filter(|node| {
+            !node
+                .ancestors()
+                .any(|ancestor| matches!(ancestor.data.borrow().value, NodeValue::Link(_) | NodeValue::Image(_)))
+        })
+        .collect();
 
-        let root = parse_document(&arena, &text, &options);
+    for node in texts {
+        let NodeValue::Text(text) = node.data.borrow().value.clone() else {
+            continue;
+        };
 
-        for node in root.children() {
-            if let NodeValue::Heading(ref mut heading) = node.data.borrow_mut().value {
-                heading.level = min(heading.level + 3, 6);
+        let mut rest = 0;
+        for span in mention_spans(&text) {
+            let name = &text[span.start + 1..span.end];
+            let Some(mention) = mentions.iter().find(|mention| mention.name == name) else {
+                continue;
+            };
+            if span.start > rest {
+                node.insert_before(arena.alloc(NodeValue::Text(text[rest..span.start].to_string()).into()));
             }
+            let link = arena.alloc(
+                NodeValue::Link(NodeLink {
+                    url: format!("/{PROFILE}/{}", mention.user_name),
+                    title: String::new(),
+                })
+                .into(),
+            );
+            link.append(arena.alloc(NodeValue::Text(format!("@{}", mention.user_name)).into()));
+            node.insert_before(link);
+            rest = span.end;
         }
 
-        let mut html = vec![];
-        format_html_with_plugins(root, &options, &mut html, &plugins).unwrap();
+        if rest == text.len() {
+            node.detach();
+        } else if rest > 0 {
+            node.data.borrow_mut().value = NodeValue::Text(text[rest..].to_string());
+        }
+    }
+}
 
-        String::from_utf8(html).unwrap()
-    });
+/// Shows `html` when it was rendered ahead of time, otherwise renders `text`.
+#[component]
+pub fn Markdown(text: String, #[prop(optional_no_strip)] html: Option<String>) -> impl IntoView {
+    let html = Memo::new(move |_| html.clone().unwrap_or_else(|| render_markdown(&text, &[])));
     view! { <div inner_html=html /> }
 }
@@ -1,2 +1,2 @@
-
+pub mod editor;
 pub mod markdown;
\ No newline at end of file
@@ -6,35 +6,46 @@ async fn main() {
         extract::{Path, State},
         http::Request,
         response::{IntoResponse, Response},
-        routing::{get, post},
+        middleware,
+        routing::get,
         Router,
     };
+    use std::net::SocketAddr;
+    use axum_tracing_opentelemetry::middleware::{OtelAxumLayer, OtelInResponseLayer};
     use leptos::logging::log;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, handle_server_fns_with_context, render_route_with_context, LeptosRoutes};
     use news::app::*;
+    use news::constants::LIVE_PATH;
     use news::model::ssr::AppState;
-    use opentelemetry::global::ObjectSafeSpan;
-    use opentelemetry::trace::{SpanKind, Status};
-    use opentelemetry::{global, trace::Tracer};
-    use opentelemetry_sdk::propagation::TraceContextPropagator;
-    use opentelemetry_sdk::trace::SdkTracerProvider;
-    use opentelemetry_stdout::SpanExporter;
-    use sqlx::PgPool;
+    use news::server::{
+        account, admin, bookmarks, cache::StoryCache, config::Config, health, metrics, preview::PreviewFetcher,
+        live::{self, LiveHub}, ratelimit::RateLimiter, repo::{PgRepo, Repo}, rest, tags, telemetry,
+        webhooks::WebhookDispatcher,
+    };
+    use tracing::Instrument;
 
     async fn server_fn_handler(
         State(app_state): State<AppState>,
         path: Path<String>,
         request: Request<AxumBody>,
     ) -> impl IntoResponse {
+        let span = telemetry::server_fn_span(request.uri().path());
         log!("{:?}", path);
 
         handle_server_fns_with_context(
             move || {
                 provide_context(app_state.pool.clone());
+                provide_context(app_state.repo.clone());
+                provide_context(app_state.preview_fetcher.clone());
+                provide_context(app_state.webhook_dispatcher.clone());
+                provide_context(app_state.story_cache.clone());
+                provide_context(app_state.rate_limiter.clone());
+                provide_context(app_state.site.clone());
             },
             request,
         )
+        .instrument(span)
         .await
     }
 
@@ -47,56 +58,138 @@ async fn main() {
             app_state.routes.clone(),
             move || {
                 provide_context(app_state.pool.clone());
+                provide_context(app_state.repo.clone());
+                provide_context(app_state.preview_fetcher.clone());
+                provide_context(app_state.webhook_dispatcher.clone());
+                provide_context(app_state.story_cache.clone());
+                provide_context(app_state.rate_limiter.clone());
+                provide_context(app_state.site.clone());
             },
             move || shell(app_state.leptos_options.clone()),
         );
         handler(state, request).await.into_response()
     }
 
-    let database_url = std::option_env!("DATABASE_URL").expect("Missing DATABASE_URL");
-    let pool = PgPool::connect(database_url)
+    let tracer_provider = telemetry::init().expect("Failed to initialise telemetry");
+
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            log!("Invalid configuration: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let pool = config
+        .database
+        .connect()
         .await
         .expect("Failed to create Postgres pool");
     
-    if let Err(e) = sqlx::migrate!().run(&pool).await {
+    if let Err(e) = admin::MIGRATOR.run(&pool).await {
         log!("Migration failed: {:?}", e);
         std::process::exit(1);
     }
 
-    global::set_text_map_propagator(TraceContextPropagator::new());
-    let provider = SdkTracerProvider::builder()
-        .with_simple_exporter(SpanExporter::default())
-        .build();
-    global::set_tracer_provider(provider);
-
     let conf = get_configuration(None).unwrap();
     let addr = conf.leptos_options.site_addr;
     let leptos_options = conf.leptos_options;
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(App);
     
+    let preview_fetcher = PreviewFetcher::builder()
+        .enabled(config.features.link_previews)
+        .build();
     let app_state = AppState {
         leptos_options,
         pool: pool.clone(),
+        repo: Repo::new(PgRepo::new(pool.clone(), preview_fetcher.clone())),
         routes: routes.clone(),
+        preview_fetcher,
+        webhook_dispatcher: WebhookDispatcher::default(),
+        story_cache: StoryCache::connect(&config.cache)
+            .await
+            .expect("Failed to connect to the cache"),
+        live: LiveHub::default(),
+        rate_limiter: RateLimiter::new(
+            config.rate_limit.writes_per_minute,
+            config.rate_limit.trusted_proxies.clone(),
+        ),
+        site: config.site.clone(),
+        shutdown: health::Shutdown::default(),
     };
+    let shutdown = app_state.shutdown.clone();
+    let preview_tasks = app_state.preview_fetcher.tasks.clone();
+
+    let dispatcher = config
+        .features
+        .webhooks
+        .then(|| tokio::spawn(app_state.webhook_dispatcher.clone().run(pool.clone(), shutdown.clone())));
+    let live_updates = config.features.live_updates.then(|| {
+        tokio::spawn(
+            app_state
+                .live
+                .clone()
+                .run(pool.clone(), app_state.repo.clone(), shutdown.clone()),
+        )
+    });
+    tokio::spawn(shutdown.clone().listen());
 
-    let app = Router::new()
+    let mut app = Router::new();
+    if config.features.metrics {
+        metrics::init();
+        app = app.route(metrics::PATH, get(metrics::handler));
+    }
+    if config.features.rest_api {
+        app = app.nest(rest::PREFIX, rest::router());
+    }
+    app = app.merge(bookmarks::router()).merge(tags::router()).merge(account::router());
+    let mut app = app
         .route(
             "/api/*fn_name",
             get(server_fn_handler).post(server_fn_handler),
         )
         .leptos_routes_with_handler(routes, get(leptos_routes_handler))
         .fallback(leptos_axum::file_and_error_handler::<AppState, _>(shell))
-        .with_state(app_state);
+        .layer(middleware::from_fn(metrics::track))
+        .layer(OtelInResponseLayer)
+        .layer(OtelAxumLayer::default())
+        // Probes and event streams are routed after the layers so they stay
+        // out of metrics and traces; streams would skew request durations.
+        .route(health::LIVENESS_PATH, get(health::healthz))
+        .route(health::READINESS_PATH, get(health::readyz));
+    if config.features.live_updates {
+        app = app.route(LIVE_PATH, get(live::handler));
+    }
+    let app = app.with_state(app_state);
 
     // run our app with hyper
     // `axum::Server` is a re-export of `hyper::Server`
     log!("listening on http://{}", &addr);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app.into_make_service())
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown({
+            let shutdown = shutdown.clone();
+            async move { shutdown.triggered().await }
+        })
         .await
         .expect("server failed");
+
+    // In-flight requests have drained; let background work finish before the
+    // pool goes away.
+    if let Some(dispatcher) = dispatcher {
+        let _ = dispatcher.await;
+    }
+    if let Some(live_updates) = live_updates {
+        let _ = live_updates.await;
+    }
+    preview_tasks.close();
+    preview_tasks.wait().await;
+    pool.close().await;
+
+    if let Err(err) = tracer_provider.shutdown() {
+        log!("Failed to flush traces: {err}");
+    }
 }
 
 #[cfg(not(feature = "ssr"))]
@@ -1,10 +1,13 @@
+use crate::{
+    constants::{LAMBDA_FUNCTION, POLL_MAX_OPTIONS, STORY_MAX_TAGS},
+    features::poll::{deserialize_closes_at, poll_options},
+};
 use chrono::{DateTime, FixedOffset};
-use leptos::{error, Params};
+use leptos::Params;
 use leptos_router::params::Params;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use typed_builder::TypedBuilder;
-use url::Url;
 use validator::{Validate, ValidationError};
 
 #[cfg(feature = "ssr")]
@@ -14,15 +17,114 @@ pub mod ssr {
     use leptos_axum::AxumRouteListing;
     use sqlx::PgPool;
 
+    use axum::{
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        Json,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{LambdaError, SiteSettings};
+    use crate::server::{
+        cache::StoryCache, health::Shutdown, live::LiveHub, preview::PreviewFetcher,
+        ratelimit::RateLimiter, repo::Repo, webhooks::WebhookDispatcher,
+    };
+
     #[derive(FromRef, Debug, Clone)]
     pub struct AppState {
         pub leptos_options: LeptosOptions,
         pub pool: PgPool,
+        pub repo: Repo,
         pub routes: Vec<AxumRouteListing>,
+        pub preview_fetcher: PreviewFetcher,
+        pub webhook_dispatcher: WebhookDispatcher,
+        pub story_cache: StoryCache,
+        pub live: LiveHub,
+        pub rate_limiter: RateLimiter,
+        pub site: SiteSettings,
+        pub shutdown: Shutdown,
+    }
+
+    /// Foreign keys to the authors, stories, comments, tags and poll options a
+    /// write names. Others, such as a notification's recipient, only break
+    /// through a bug.
+    const CALLER_REFERENCES: &[&str] = &[
+        "stories_author_id_fkey",
+        "comments_author_id_fkey",
+        "comments_story_id_fkey",
+        "comments_parent_id_fkey",
+        "votes_story_id_fkey",
+        "votes_comment_id_fkey",
+        "bookmarks_story_id_fkey",
+        "bookmarks_comment_id_fkey",
+        "story_visits_story_id_fkey",
+        "story_tags_story_id_fkey",
+        "story_tags_tag_id_fkey",
+        "tag_preferences_tag_id_fkey",
+        "ballots_story_id_fkey",
+        "ballots_option_id_fkey",
+        "drafts_story_id_fkey",
+        "drafts_parent_id_fkey",
+    ];
+
+    impl From<sqlx::Error> for LambdaError {
+        fn from(err: sqlx::Error) -> Self {
+            match err {
+                sqlx::Error::RowNotFound => LambdaError::NotFound,
+                sqlx::Error::PoolTimedOut => LambdaError::Timeout,
+                // A reference to a row the caller named that doesn't exist,
+                // e.g. voting on an unknown story.
+                sqlx::Error::Database(err)
+                    if err.is_foreign_key_violation()
+                        && err.constraint().is_some_and(|name| CALLER_REFERENCES.contains(&name)) =>
+                {
+                    LambdaError::NotFound
+                }
+                err => {
+                    leptos::logging::error!("Database error: {err}");
+                    LambdaError::InternalServerError
+                }
+            }
+        }
+    }
+
+    impl LambdaError {
+        pub fn status_code(&self) -> StatusCode {
+            match self {
+                LambdaError::NotFound => StatusCode::NOT_FOUND,
+                LambdaError::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+                LambdaError::InvalidData(_) => StatusCode::BAD_REQUEST,
+                LambdaError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+                LambdaError::AuthError => StatusCode::UNAUTHORIZED,
+                LambdaError::Forbidden(_) => StatusCode::FORBIDDEN,
+                LambdaError::ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                LambdaError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            }
+        }
+    }
+
+    /// Body of every error response returned by the REST API.
+    #[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
+    pub struct ErrorBody {
+        pub error: LambdaError,
+        pub message: String,
+    }
+
+    impl IntoResponse for LambdaError {
+        fn into_response(self) -> Response {
+            let status = self.status_code();
+            let body = ErrorBody {
+                message: self.to_string(),
+                error: self,
+            };
+            (status, Json(body)).into_response()
+        }
     }
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 pub enum LambdaError {
     #[error("No adjoint functor exists.")]
     NotFound,
@@ -34,11 +136,41 @@ pub enum LambdaError {
     Timeout,
     #[error("Type inhabitance failure.")]
     AuthError,
+    #[error("Insufficient proof obligations: {0} scope required.")]
+    Forbidden(String),
     #[error("Coherence conditions not satisfied: {0}.")]
     ValidationError(String),
+    #[error("Reduction budget exhausted—try again later.")]
+    RateLimited,
 }
 
-#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug)]
+/// Site-wide settings shared with the client during hydration.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteSettings {
+    pub name: String,
+    /// Where the site is served from, used for absolute links in feeds.
+    pub url: String,
+}
+
+impl SiteSettings {
+    /// [`Self::url`] without a trailing slash, ready to prefix paths with.
+    pub fn origin(&self) -> &str {
+        self.url.trim_end_matches('/')
+    }
+}
+
+impl Default for SiteSettings {
+    fn default() -> Self {
+        SiteSettings {
+            name: LAMBDA_FUNCTION.to_string(),
+            url: "http://localhost:3000".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, TypedBuilder, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 pub struct Story {
     pub id: i32,
     pub title: String,
@@ -48,9 +180,19 @@ pub struct Story {
     pub url: Option<String>,
     pub created_at: DateTime<FixedOffset>,
     pub author_id: i32,
+    /// `text` rendered from Markdown when the story was stored.
+    #[builder(default, setter(strip_option))]
+    pub text_html: Option<String>,
+    #[builder(default)]
+    #[serde(default)]
+    pub kind: StoryKind,
+    #[builder(default)]
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 pub struct StoryListItem {
     pub id: i32,
     pub title: String,
@@ -62,22 +204,32 @@ pub struct StoryListItem {
     pub author_name: String,
     pub rating: Option<i32>,
     pub comment_count: Option<i32>,
+    #[builder(default)]
+    #[serde(default)]
+    pub kind: StoryKind,
+    #[builder(default)]
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-impl Into<Story> for StoryListItem {
-    fn into(self) -> Story {
+impl From<StoryListItem> for Story {
+    fn from(item: StoryListItem) -> Self {
         Story {
-            id: self.id,
-            title: self.title,
-            text: self.text,
-            url: self.url,
-            created_at: self.created_at,
+            id: item.id,
+            title: item.title,
+            text: item.text,
+            url: item.url,
+            created_at: item.created_at,
             author_id: 0,
+            text_html: None,
+            kind: item.kind,
+            tags: item.tags,
         }
     }
 }
 
 #[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug, Validate)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[validate(schema(function = "validate_story_create_args"))]
 pub struct StoryCreateArgs {
     #[validate(length(min = 1, message = "is empty"))]
@@ -87,23 +239,267 @@ pub struct StoryCreateArgs {
     #[builder(default, setter(strip_option))]
     #[validate(url)]
     pub url: Option<String>,
+    /// Inferred when missing: a link with a URL, an ask without one.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    pub kind: Option<StoryKind>,
+    /// Names from the tag vocabulary.
+    #[builder(default)]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Required for polls, refused for every other kind.
+    #[builder(default, setter(strip_option))]
+    #[serde(default)]
+    pub poll: Option<PollArgs>,
+}
+
+impl StoryCreateArgs {
+    pub fn kind(&self) -> StoryKind {
+        self.kind.unwrap_or(match self.url.as_deref().is_none_or(str::is_empty) {
+            true => StoryKind::Ask,
+            false => StoryKind::Link,
+        })
+    }
 }
 
 fn validate_story_create_args(story: &&StoryCreateArgs) -> Result<(), ValidationError> {
-    if story.text.as_deref().map_or(true, str::is_empty)
-        && story.url.as_deref().map_or(true, str::is_empty)
-    {
+    let has_text = !story.text.as_deref().is_none_or(str::is_empty);
+    let has_url = !story.url.as_deref().is_none_or(str::is_empty);
+    if !has_text && !has_url && story.kind() != StoryKind::Poll {
         return Err(ValidationError::new("Text or URL is required."));
     }
+    match story.kind() {
+        StoryKind::Link | StoryKind::Show | StoryKind::Job if !has_url => {
+            return Err(ValidationError::new("Links, shows and jobs need a URL."));
+        }
+        StoryKind::Ask | StoryKind::Poll if has_url => {
+            return Err(ValidationError::new("Asks and polls are discussions here and take no URL."));
+        }
+        _ => {}
+    }
+    if story.tags.len() > STORY_MAX_TAGS {
+        return Err(ValidationError::new("At most 3 tags are allowed."));
+    }
+    let options = story.poll.as_ref().map(|poll| poll_options(&poll.options)).unwrap_or_default();
+    if story.kind() != StoryKind::Poll {
+        if !options.is_empty() {
+            return Err(ValidationError::new("Only polls have options."));
+        }
+        return Ok(());
+    }
+    if !(2..=POLL_MAX_OPTIONS).contains(&options.len()) {
+        return Err(ValidationError::new("A poll has 2 to 10 options."));
+    }
+    if options.iter().enumerate().any(|(index, option)| options[..index].contains(option)) {
+        return Err(ValidationError::new("Poll options must differ."));
+    }
+    if story
+        .poll
+        .as_ref()
+        .and_then(|poll| poll.closes_at)
+        .is_some_and(|closes_at| closes_at <= chrono::Utc::now())
+    {
+        return Err(ValidationError::new("A poll must close in the future."));
+    }
     Ok(())
 }
 
+/// The options and rules of a new poll.
+#[derive(Clone, Serialize, Deserialize, PartialEq, TypedBuilder, Debug, Default)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct PollArgs {
+    /// In the order they are shown; blank ones are left out.
+    pub options: Vec<String>,
+    /// Whether a ballot may choose more than one option.
+    #[builder(default)]
+    #[serde(default)]
+    pub multiple: bool,
+    /// No ballots are accepted from then on.
+    #[builder(default, setter(strip_option))]
+    #[serde(default, deserialize_with = "deserialize_closes_at")]
+    pub closes_at: Option<DateTime<FixedOffset>>,
+}
+
+/// A poll's options with their votes so far.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, TypedBuilder, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct Poll {
+    pub story_id: i32,
+    pub multiple: bool,
+    #[builder(default, setter(strip_option))]
+    pub closes_at: Option<DateTime<FixedOffset>>,
+    /// Whether `closes_at` has passed.
+    #[builder(default)]
+    pub closed: bool,
+    pub options: Vec<PollOption>,
+    /// How many users cast a ballot.
+    pub voters: i64,
+    /// The options the reader chose; empty when they haven't voted.
+    #[builder(default)]
+    #[serde(default)]
+    pub ballot: Vec<i32>,
+}
+
+impl Poll {
+    /// Checks a ballot against the poll before it replaces the voter's last.
+    pub fn check_ballot(&self, option_ids: &[i32]) -> Result<(), LambdaError> {
+        if self.closed {
+            return Err(LambdaError::ValidationError("the poll is closed".into()));
+        }
+        if let Some(unknown) = option_ids.iter().find(|id| self.options.iter().all(|option| option.id != **id)) {
+            return Err(LambdaError::ValidationError(format!("option {unknown} is not in this poll")));
+        }
+        if option_ids.iter().enumerate().any(|(index, id)| option_ids[..index].contains(id)) {
+            return Err(LambdaError::ValidationError("an option is chosen twice".into()));
+        }
+        if !self.multiple && option_ids.len() > 1 {
+            return Err(LambdaError::ValidationError("this poll takes a single choice".into()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, TypedBuilder, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct PollOption {
+    pub id: i32,
+    pub text: String,
+    pub votes: i64,
+}
+
+/// A reader's choices in a poll, replacing any earlier ballot. An empty
+/// ballot withdraws it.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, TypedBuilder, Debug, Default)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct BallotArgs {
+    /// Ids of the chosen options; exactly one unless the poll allows several.
+    #[serde(default)]
+    pub option_ids: Vec<i32>,
+}
+
+/// What a story is, which decides what it must carry and how its own
+/// listing ranks it.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema, sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "story_kind", rename_all = "lowercase"))]
+#[serde(rename_all = "lowercase")]
+pub enum StoryKind {
+    /// A URL, optionally with text. Only on the front page, newest first.
+    #[default]
+    Link,
+    /// A question for everyone: text and no URL. Ranked by latest activity,
+    /// so answered questions come back up.
+    Ask,
+    /// The submitter's own work: a URL, optionally with text. Ranked by
+    /// score decayed with age.
+    Show,
+    /// A survey: options to vote on and no URL. Open polls first, then
+    /// newest first.
+    Poll,
+    /// A job posting: a URL, optionally with text. Newest first, for
+    /// [`JOB_LISTING_DAYS`](crate::constants::JOB_LISTING_DAYS) days.
+    Job,
+}
+
+impl StoryKind {
+    pub const ALL: [StoryKind; 5] = [
+        StoryKind::Link,
+        StoryKind::Ask,
+        StoryKind::Show,
+        StoryKind::Poll,
+        StoryKind::Job,
+    ];
+
+    /// Also the path of the kind's listing, e.g. `/ask`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StoryKind::Link => "link",
+            StoryKind::Ask => "ask",
+            StoryKind::Show => "show",
+            StoryKind::Poll => "poll",
+            StoryKind::Job => "job",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StoryKind::Link => "Link",
+            StoryKind::Ask => "Ask",
+            StoryKind::Show => "Show",
+            StoryKind::Poll => "Poll",
+            StoryKind::Job => "Job",
+        }
+    }
+}
+
+/// A topic from the curated vocabulary.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, TypedBuilder, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct Tag {
+    pub name: String,
+    #[builder(default, setter(strip_option))]
+    pub description: Option<String>,
+    #[builder(default)]
+    pub story_count: i64,
+}
+
+/// How a reader treats a tag: stories with a muted tag are left off their
+/// front page, followed tags stand out on it.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TagPreference {
+    Follow,
+    Mute,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub struct TagPreferences {
+    pub followed: Vec<String>,
+    pub muted: Vec<String>,
+}
+
+/// What a reader leaves off their listings: the stories they hid and the
+/// tags they muted. Token holders keep theirs on the server; browsers keep
+/// theirs locally and send it along.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, TypedBuilder, Debug, Default)]
+pub struct StoryFilter {
+    #[builder(default)]
+    #[serde(default)]
+    pub hidden: Vec<i32>,
+    #[builder(default)]
+    #[serde(default)]
+    pub muted: Vec<String>,
+}
+
+impl StoryFilter {
+    pub fn is_empty(&self) -> bool {
+        self.hidden.is_empty() && self.muted.is_empty()
+    }
+
+    pub fn keeps(&self, story_id: i32, tags: &[String]) -> bool {
+        !self.hidden.contains(&story_id) && !tags.iter().any(|tag| self.muted.contains(tag))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, TypedBuilder, Debug, Default)]
+pub struct LinkPreview {
+    #[builder(default, setter(strip_option))]
+    pub title: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub description: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub image_url: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub site_name: Option<String>,
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Params, TypedBuilder, Debug)]
 pub struct StoryGetArgs {
     pub id: i32,
 }
 
-#[derive(Clone, Serialize, Deserialize, TypedBuilder)]
+#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 pub struct User {
     pub id: i32,
     pub username: String,
@@ -111,6 +507,7 @@ pub struct User {
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 pub struct Comment {
     pub id: i32,
     pub text: String,
@@ -118,11 +515,450 @@ pub struct Comment {
     pub story_id: i32,
     pub created_at: DateTime<FixedOffset>,
     pub author_name: String,
+    #[builder(default)]
+    #[serde(default)]
+    pub mentions: Vec<Mention>,
 }
 
-#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug)]
+/// An `@name` that matched a user when it was written. `user_name` is that
+/// user's current name, which differs from `name` after a rename.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema, sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "mention"))]
+pub struct Mention {
+    pub name: String,
+    pub user_name: String,
+}
+
+/// Unposted text as it will read once posted: `html` as a story's Markdown
+/// renders, `mentions` as they resolve now.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TextPreview {
+    pub html: String,
+    pub mentions: Vec<Mention>,
+}
+
+#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug, Validate)]
 pub struct CommentCreateArgs {
+    #[validate(custom(function = "validate_comment_text"))]
     pub text: String,
     pub parent_id: Option<i32>,
     pub story_id: i32,
 }
+
+fn validate_comment_text(text: &str) -> Result<(), ValidationError> {
+    if text.trim().is_empty() {
+        return Err(ValidationError::new("is empty"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug, Validate)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct VoteArgs {
+    #[validate(range(min = -1, max = 1, message = "must be -1, 0 or 1"))]
+    pub value: i32,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, TypedBuilder, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+pub struct VoteTally {
+    pub score: i32,
+    pub count: i32,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    Read,
+    Submit,
+    Comment,
+    Vote,
+    /// Change the token owner's private state, such as bookmarks.
+    Write,
+    /// Export or delete the token owner's account.
+    Account,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 6] = [
+        TokenScope::Read,
+        TokenScope::Submit,
+        TokenScope::Comment,
+        TokenScope::Vote,
+        TokenScope::Write,
+        TokenScope::Account,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::Submit => "submit",
+            TokenScope::Comment => "comment",
+            TokenScope::Vote => "vote",
+            TokenScope::Write => "write",
+            TokenScope::Account => "account",
+        }
+    }
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = LambdaError;
+
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        TokenScope::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == scope)
+            .ok_or_else(|| LambdaError::InvalidData(format!("unknown scope {scope}")))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: DateTime<FixedOffset>,
+    #[builder(default, setter(strip_option))]
+    pub last_used_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug, Validate)]
+pub struct ApiTokenCreateArgs {
+    #[validate(length(min = 1, max = 64, message = "must be between 1 and 64 characters"))]
+    pub name: String,
+    #[builder(default)]
+    #[serde(default)]
+    pub read: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub submit: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub comment: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub vote: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub write: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub account: bool,
+}
+
+impl ApiTokenCreateArgs {
+    pub fn scopes(&self) -> Vec<TokenScope> {
+        [
+            (self.read, TokenScope::Read),
+            (self.submit, TokenScope::Submit),
+            (self.comment, TokenScope::Comment),
+            (self.vote, TokenScope::Vote),
+            (self.write, TokenScope::Write),
+            (self.account, TokenScope::Account),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, scope)| enabled.then_some(scope))
+        .collect()
+    }
+}
+
+/// Returned once on creation; only a hash of `secret` is stored.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct ApiTokenCreated {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub enum WebhookEvent {
+    #[serde(rename = "story.created")]
+    StoryCreated,
+    #[serde(rename = "comment.created")]
+    CommentCreated,
+    #[serde(rename = "story.deleted")]
+    StoryDeleted,
+    #[serde(rename = "moderation.action")]
+    ModerationAction,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 4] = [
+        WebhookEvent::StoryCreated,
+        WebhookEvent::CommentCreated,
+        WebhookEvent::StoryDeleted,
+        WebhookEvent::ModerationAction,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::StoryCreated => "story.created",
+            WebhookEvent::CommentCreated => "comment.created",
+            WebhookEvent::StoryDeleted => "story.deleted",
+            WebhookEvent::ModerationAction => "moderation.action",
+        }
+    }
+}
+
+impl std::str::FromStr for WebhookEvent {
+    type Err = LambdaError;
+
+    fn from_str(event: &str) -> Result<Self, Self::Err> {
+        WebhookEvent::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == event)
+            .ok_or_else(|| LambdaError::InvalidData(format!("unknown event {event}")))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug, Validate)]
+pub struct WebhookCreateArgs {
+    #[validate(url)]
+    pub url: String,
+    #[builder(default)]
+    #[serde(default)]
+    pub story_created: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub comment_created: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub story_deleted: bool,
+    #[builder(default)]
+    #[serde(default)]
+    pub moderation_action: bool,
+}
+
+impl WebhookCreateArgs {
+    pub fn events(&self) -> Vec<WebhookEvent> {
+        [
+            (self.story_created, WebhookEvent::StoryCreated),
+            (self.comment_created, WebhookEvent::CommentCreated),
+            (self.story_deleted, WebhookEvent::StoryDeleted),
+            (self.moderation_action, WebhookEvent::ModerationAction),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, event)| enabled.then_some(event))
+        .collect()
+    }
+}
+
+/// Returned once on creation; the secret signs every delivery.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct WebhookCreated {
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event: String,
+    pub status: String,
+    pub attempts: i32,
+    #[builder(default, setter(strip_option))]
+    pub last_status_code: Option<i32>,
+    #[builder(default, setter(strip_option))]
+    pub last_error: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    #[builder(default, setter(strip_option))]
+    pub delivered_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// A top-level comment on the recipient's story.
+    StoryReply,
+    /// A reply to one of the recipient's comments.
+    CommentReply,
+    Mention,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 3] = [
+        NotificationKind::StoryReply,
+        NotificationKind::CommentReply,
+        NotificationKind::Mention,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::StoryReply => "story_reply",
+            NotificationKind::CommentReply => "comment_reply",
+            NotificationKind::Mention => "mention",
+        }
+    }
+}
+
+impl std::str::FromStr for NotificationKind {
+    type Err = LambdaError;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        NotificationKind::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == kind)
+            .ok_or_else(|| LambdaError::InvalidData(format!("unknown notification kind {kind}")))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+pub struct Notification {
+    pub id: i64,
+    pub kind: NotificationKind,
+    /// `None` once the actor's account is deleted.
+    #[builder(default, setter(strip_option))]
+    pub actor_name: Option<String>,
+    pub story_id: i32,
+    pub story_title: String,
+    #[builder(default, setter(strip_option))]
+    pub comment_id: Option<i32>,
+    /// The start of the comment, or of the story text for mentions in stories.
+    #[builder(default, setter(strip_option))]
+    pub excerpt: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    #[builder(default, setter(strip_option))]
+    pub read_at: Option<DateTime<FixedOffset>>,
+}
+
+/// Pushed to readers over Server-Sent Events as stories, comments and votes
+/// are written.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    StoryCreated { story: Story },
+    CommentCreated { comment: Comment },
+    ScoreChanged { story_id: i32, tally: VoteTally },
+    /// The subscriber fell behind and missed events; refetch instead.
+    Lagged,
+}
+
+impl LiveEvent {
+    pub fn story_id(&self) -> Option<i32> {
+        match self {
+            LiveEvent::StoryCreated { story } => Some(story.id),
+            LiveEvent::CommentCreated { comment } => Some(comment.story_id),
+            LiveEvent::ScoreChanged { story_id, .. } => Some(*story_id),
+            LiveEvent::Lagged => None,
+        }
+    }
+}
+
+/// A reader's history with a story: when they last opened it, which
+/// comments by others arrived since, and whether they hid it.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+pub struct StoryVisit {
+    pub story_id: i32,
+    #[builder(default, setter(strip_option))]
+    pub seen_at: Option<DateTime<FixedOffset>>,
+    #[builder(default)]
+    pub new_comment_ids: Vec<i32>,
+    #[builder(default)]
+    pub hidden: bool,
+}
+
+/// A story or comment saved by a user. Notes are private even when the
+/// collection is public.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+pub struct Bookmark {
+    pub id: i32,
+    pub story_id: i32,
+    pub story_title: String,
+    #[builder(default, setter(strip_option))]
+    pub story_url: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub comment_id: Option<i32>,
+    /// The start of the bookmarked comment.
+    #[builder(default, setter(strip_option))]
+    pub excerpt: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub note: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+#[derive(Clone, Serialize, Deserialize, TypedBuilder, Debug, Validate)]
+pub struct BookmarkArgs {
+    pub story_id: i32,
+    #[builder(default, setter(strip_option))]
+    pub comment_id: Option<i32>,
+    #[builder(default, setter(strip_option))]
+    #[validate(length(max = 1000, message = "must be at most 1000 characters"))]
+    pub note: Option<String>,
+}
+
+/// A user's bookmarks as someone else may see them.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Favorites {
+    pub user_name: String,
+    pub public: bool,
+    /// Whether the viewer owns the collection, and so sees notes.
+    pub owned: bool,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// An unposted story, or a comment on `story_id` replying to `parent_id`
+/// when set, as its author last left it.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, TypedBuilder)]
+pub struct Draft {
+    #[builder(default, setter(strip_option))]
+    pub story_id: Option<i32>,
+    /// The title of the story a comment draft is on.
+    #[builder(default, setter(strip_option))]
+    pub story_title: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub parent_id: Option<i32>,
+    #[builder(default, setter(strip_option))]
+    pub title: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub url: Option<String>,
+    pub text: String,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+/// What an editor saves as its author writes, and keeps in the browser too.
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, TypedBuilder, Debug, Validate)]
+pub struct DraftArgs {
+    #[builder(default, setter(strip_option))]
+    pub story_id: Option<i32>,
+    #[builder(default, setter(strip_option))]
+    pub parent_id: Option<i32>,
+    #[builder(default, setter(strip_option))]
+    #[validate(length(max = 300, message = "must be at most 300 characters"))]
+    pub title: Option<String>,
+    #[builder(default, setter(strip_option))]
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub url: Option<String>,
+    #[builder(default)]
+    #[serde(default)]
+    #[validate(length(max = 100000, message = "must be at most 100000 characters"))]
+    pub text: String,
+}
+
+impl DraftArgs {
+    /// Whether there is nothing worth keeping.
+    pub fn is_blank(&self) -> bool {
+        let blank = |field: &Option<String>| field.as_deref().is_none_or(|field| field.trim().is_empty());
+        self.text.trim().is_empty() && blank(&self.title) && blank(&self.url)
+    }
+}
+
+/// A user's page, with their open drafts when they view it themselves.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Profile {
+    pub user_name: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub favorites_public: bool,
+    /// Whether the viewer is the user, and so sees their drafts.
+    pub owned: bool,
+    pub drafts: Vec<Draft>,
+}
@@ -1,15 +1,169 @@
+pub mod account;
+pub mod admin;
+pub mod archive;
+pub mod audit;
+pub mod auth;
+pub mod bookmarks;
+pub mod cache;
+pub mod config;
+pub mod drafts;
+pub mod health;
+pub mod hn;
+pub mod live;
+pub mod mentions;
+pub mod metrics;
+pub mod notifications;
+pub mod polls;
+pub mod preview;
+pub mod ratelimit;
+pub mod repo;
+pub mod rest;
+pub mod tags;
+pub mod telemetry;
+pub mod visits;
+pub mod webhooks;
 
-use crate::model::Comment;
-use chrono::{DateTime, Local};
+use crate::{
+    constants::{DUPLICATE_WINDOW_DAYS, JOB_LISTING_DAYS, PAGE_SIZE},
+    features::{canonical::canonicalize_url, ui::markdown::render_markdown},
+    model::{
+        Comment, CommentCreateArgs, LambdaError, Mention, SiteSettings, Story, StoryCreateArgs, StoryFilter, StoryKind,
+        StoryListItem, TokenScope, User, VoteArgs, VoteTally, WebhookEvent,
+    },
+};
+use chrono::{DateTime, Duration, Local};
 use leptos::prelude::*;
 use sqlx::PgPool;
 use sqlx::postgres::PgRow;
 use sqlx::Row;
+use tracing::{field::Empty, instrument, Instrument};
+use validator::Validate;
 
 pub fn pool() -> Result<PgPool, ServerFnError> {
     use_context::<PgPool>().ok_or_else(|| ServerFnError::ServerError("Pool missing.".into()))
 }
 
+pub fn repo() -> Result<repo::Repo, ServerFnError> {
+    use_context::<repo::Repo>().ok_or_else(|| ServerFnError::ServerError("Repository missing.".into()))
+}
+
+pub fn preview_fetcher() -> preview::PreviewFetcher {
+    use_context::<preview::PreviewFetcher>().unwrap_or_default()
+}
+
+pub fn webhook_dispatcher() -> webhooks::WebhookDispatcher {
+    use_context::<webhooks::WebhookDispatcher>().unwrap_or_default()
+}
+
+pub fn story_cache() -> cache::StoryCache {
+    use_context::<cache::StoryCache>().unwrap_or_default()
+}
+
+/// Counts a write server function call against the caller's rate limit.
+pub async fn throttle() -> Result<(), ServerFnError> {
+    let limiter = use_context::<ratelimit::RateLimiter>().unwrap_or_default();
+    let peer: Option<axum::extract::ConnectInfo<std::net::SocketAddr>> = leptos_axum::extract().await.ok();
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+
+    Ok(limiter.throttle(peer.as_ref(), &headers)?)
+}
+
+/// The credentials of a server function call: a bearer token, or else the
+/// browser's session. Without either the caller is an anonymous browser. A
+/// session that has ended is cleared from the browser, which carries on
+/// anonymously.
+pub async fn bearer() -> Result<auth::Bearer, ServerFnError> {
+    let headers: axum::http::HeaderMap = leptos_axum::extract().await?;
+    if headers.contains_key(axum::http::header::AUTHORIZATION) {
+        let pool = pool()?;
+        return Ok(auth::Bearer {
+            token: auth::authenticate(&pool, &headers).await?,
+            session: None,
+        });
+    }
+    let Some(secret) = auth::session_secret(&headers) else {
+        return Ok(auth::Bearer::default());
+    };
+    let pool = pool()?;
+
+    match auth::session_user(&pool, secret).await {
+        Ok(user) => Ok(auth::Bearer {
+            token: None,
+            session: Some(user.id),
+        }),
+        Err(LambdaError::AuthError) => {
+            set_session_cookie(None);
+            Ok(auth::Bearer::default())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Keeps a session's secret in the browser's cookie, or with `None` removes
+/// it. The cookie is only sent over HTTPS when the site is served so.
+pub fn set_session_cookie(secret: Option<&str>) {
+    let secure = use_context::<SiteSettings>().unwrap_or_default().url.starts_with("https://");
+    let cookie = axum::http::HeaderValue::from_str(&auth::session_cookie(secret, secure));
+    if let (Some(response), Ok(cookie)) = (use_context::<leptos_axum::ResponseOptions>(), cookie) {
+        response.append_header(axum::http::header::SET_COOKIE, cookie);
+    }
+}
+
+/// Resolves the caller of a server function: the owner of a bearer token, the
+/// signed-in user, or otherwise the site user.
+pub async fn principal() -> Result<auth::Principal, ServerFnError> {
+    Ok(bearer().await?.principal())
+}
+
+pub async fn authorize(scope: TokenScope) -> Result<i32, ServerFnError> {
+    Ok(principal().await?.require(scope)?)
+}
+
+/// The user signed in to the browser, for managing their tokens and
+/// webhooks. Refuses anonymous browsers, which share the site user, and
+/// bearer tokens, so a token can't be used to mint or revoke other tokens.
+pub async fn session_user() -> Result<i32, ServerFnError> {
+    let bearer = bearer().await?;
+    if bearer.token.is_some() {
+        return Err(LambdaError::Forbidden("session".into()).into());
+    }
+
+    Ok(bearer.session.ok_or(LambdaError::AuthError)?)
+}
+
+/// Like [`authorize`], but only for admins, who moderate the site.
+pub async fn moderator(scope: TokenScope) -> Result<i32, ServerFnError> {
+    let user_id = authorize(scope).await?;
+    let pool = pool()?;
+
+    let is_admin = sqlx::query_scalar!("SELECT is_admin FROM users WHERE id = $1", user_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(LambdaError::from)?;
+    if !is_admin {
+        return Err(LambdaError::Forbidden("moderator".into()).into());
+    }
+    Ok(user_id)
+}
+
+/// What to leave off the caller's listings: a token holder's hidden stories
+/// and muted tags, or what an anonymous browser sent along, since browsers all
+/// share the site user.
+pub async fn story_filter(
+    repo: &repo::Repo,
+    bearer: &auth::Bearer,
+    local: Option<StoryFilter>,
+) -> Result<StoryFilter, LambdaError> {
+    let Some(user_id) = bearer.owner() else {
+        return Ok(local.unwrap_or_default());
+    };
+
+    Ok(StoryFilter {
+        hidden: repo.hidden_story_ids(user_id).await?,
+        muted: repo.tag_preferences(user_id).await?.muted,
+    })
+}
+
 pub fn row_to_comment(row: PgRow) -> Comment {
     Comment {
         id: row.get("id"),
@@ -18,5 +172,542 @@ pub fn row_to_comment(row: PgRow) -> Comment {
         story_id: row.get("story_id"),
         created_at: row.get::<DateTime<Local>, _>("created_at").into(),
         author_name: row.get("author_name"),
+        mentions: row.get("mentions"),
     }
-}
\ No newline at end of file
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_page(pool: &PgPool, page: i64, filter: &StoryFilter) -> Result<Vec<StoryListItem>, LambdaError> {
+    let offset: i64 = page * PAGE_SIZE;
+
+    let stories = sqlx::query_as!(
+        StoryListItem,
+        r#"
+            SELECT
+                s.id,
+                s.title,
+                s.text,
+                s.url,
+                s.created_at,
+                u.display_name as author_name,
+                s.score as "rating?",
+                s.comment_count as "comment_count?",
+                s.kind as "kind: StoryKind",
+                COALESCE(stn.tags, '{}') as "tags!"
+            FROM
+                stories s
+            JOIN
+                users u ON s.author_id = u.id
+            LEFT JOIN
+                story_tag_names stn ON stn.story_id = s.id
+            WHERE
+                NOT s.id = ANY($3) AND NOT COALESCE(stn.tags && $4, FALSE)
+            ORDER BY
+                s.created_at DESC
+            LIMIT $1
+            OFFSET $2
+        "#,
+        PAGE_SIZE,
+        offset,
+        &filter.hidden,
+        &filter.muted,
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(stories.len());
+
+    Ok(stories)
+}
+
+/// A page of one kind of stories, ranked by that kind's rules: asks by
+/// their latest activity, shows by score decayed with age, open polls before
+/// closed ones, and recent jobs and everything else newest first.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_kind_page(
+    pool: &PgPool,
+    kind: StoryKind,
+    page: i64,
+    filter: &StoryFilter,
+) -> Result<Vec<StoryListItem>, LambdaError> {
+    let job_cutoff = Local::now() - Duration::days(JOB_LISTING_DAYS);
+
+    let stories = sqlx::query_as!(
+        StoryListItem,
+        r#"
+            SELECT
+                s.id,
+                s.title,
+                s.text,
+                s.url,
+                s.created_at,
+                u.display_name as author_name,
+                s.score as "rating?",
+                s.comment_count as "comment_count?",
+                s.kind as "kind: StoryKind",
+                COALESCE(stn.tags, '{}') as "tags!"
+            FROM
+                stories s
+            JOIN
+                users u ON s.author_id = u.id
+            LEFT JOIN
+                story_tag_names stn ON stn.story_id = s.id
+            LEFT JOIN
+                polls p ON p.story_id = s.id
+            WHERE
+                s.kind = $1 AND (s.kind <> 'job' OR s.created_at > $2)
+                AND NOT s.id = ANY($5) AND NOT COALESCE(stn.tags && $6, FALSE)
+            ORDER BY
+                CASE WHEN s.kind = 'ask' THEN
+                    GREATEST(s.created_at, (SELECT MAX(c.created_at) FROM comments c WHERE c.story_id = s.id))
+                END DESC,
+                CASE WHEN s.kind = 'show' THEN
+                    s.score / POWER(EXTRACT(EPOCH FROM NOW() - s.created_at) / 3600 + 2, 1.8)
+                END DESC,
+                CASE WHEN s.kind = 'poll' THEN COALESCE(p.closes_at <= NOW(), FALSE) END,
+                s.created_at DESC
+            LIMIT $3
+            OFFSET $4
+        "#,
+        kind as StoryKind,
+        job_cutoff.into(),
+        PAGE_SIZE,
+        page * PAGE_SIZE,
+        &filter.hidden,
+        &filter.muted,
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(stories.len());
+
+    Ok(stories)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_page_count(pool: &PgPool, filter: &StoryFilter) -> Result<i64, LambdaError> {
+    let count = sqlx::query!(
+        r#"
+            SELECT COUNT(*)
+            FROM stories s
+            LEFT JOIN story_tag_names stn ON stn.story_id = s.id
+            WHERE NOT s.id = ANY($1) AND NOT COALESCE(stn.tags && $2, FALSE)
+        "#,
+        &filter.hidden,
+        &filter.muted
+    )
+    .fetch_one(pool)
+    .await?
+    .count;
+    telemetry::record_rows(1);
+
+    Ok(count.map(|count| count / PAGE_SIZE + 1).unwrap_or(0))
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_by_id(pool: &PgPool, id: i32) -> Result<Story, LambdaError> {
+    let story = sqlx::query_as!(
+        Story,
+        r#"
+            SELECT s.id, s.title, s.text, s.url, s.created_at, s.author_id, s.text_html, s.kind as "kind: StoryKind",
+                COALESCE(stn.tags, '{}') as "tags!"
+            FROM stories s
+            LEFT JOIN story_tag_names stn ON stn.story_id = s.id
+            WHERE s.id = $1
+        "#,
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(story)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_duplicate(pool: &PgPool, canonical_url: &str) -> Result<Option<Story>, sqlx::Error> {
+    let cutoff = Local::now() - Duration::days(DUPLICATE_WINDOW_DAYS);
+
+    let story = sqlx::query_as!(
+        Story,
+        r#"
+            SELECT s.id, s.title, s.text, s.url, s.created_at, s.author_id, s.text_html, s.kind as "kind: StoryKind",
+                COALESCE(stn.tags, '{}') as "tags!"
+            FROM stories s
+            LEFT JOIN story_tag_names stn ON stn.story_id = s.id
+            WHERE s.canonical_url = $1 AND s.created_at > $2
+            ORDER BY s.created_at DESC
+            LIMIT 1
+        "#,
+        canonical_url,
+        cutoff.into()
+    )
+    .fetch_optional(pool)
+    .await?;
+    telemetry::record_rows(story.is_some() as u8);
+
+    Ok(story)
+}
+
+/// Validates and inserts a story, returning the existing one instead when the
+/// URL was recently submitted. Link previews are fetched in the background.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_insert(
+    pool: &PgPool,
+    fetcher: preview::PreviewFetcher,
+    story: StoryCreateArgs,
+    author_id: i32,
+) -> Result<Story, LambdaError> {
+    let timestamp = Local::now();
+
+    story.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+
+    let canonical_url = story.url.as_deref().and_then(canonicalize_url);
+    if let Some(canonical_url) = canonical_url.as_deref() {
+        if let Some(existing) = story_duplicate(pool, canonical_url).await? {
+            return Ok(existing);
+        }
+    }
+
+    let mentioned = match story.text.as_deref() {
+        Some(text) => mentions::resolve(pool, text).await?,
+        None => Vec::new(),
+    };
+    let text_html = story.text.as_deref().map(|text| {
+        let mentions: Vec<_> = mentioned.iter().map(|resolved| resolved.mention.clone()).collect();
+        render_markdown(text, &mentions)
+    });
+
+    let mut tx = pool.begin().await?;
+    let mut result = sqlx::query_as!(
+        Story,
+        r#"
+            INSERT INTO stories (title, text, url, canonical_url, author_id, created_at, text_html, kind)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING
+                id, title, text, url, created_at, author_id, text_html, kind as "kind: StoryKind",
+                ARRAY[]::text[] as "tags!"
+        "#,
+        story.title,
+        story.text,
+        story.url,
+        canonical_url,
+        author_id,
+        timestamp.into(),
+        text_html,
+        story.kind() as StoryKind
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    mentions::save(&mut *tx, result.id, None, &mentioned).await?;
+    result.tags = tags::save(&mut tx, result.id, &story.tags).await?;
+    if let Some(poll) = story.poll.as_ref().filter(|_| result.kind == StoryKind::Poll) {
+        polls::save(&mut tx, result.id, poll).await?;
+    }
+    tx.commit().await?;
+    telemetry::record_rows(1);
+
+    metrics::STORIES_CREATED.inc();
+    webhooks::emit(pool, WebhookEvent::StoryCreated, &result).await;
+    notifications::story_created(pool, &result).await;
+    live::publish(pool, live::Change::Story { id: result.id }).await;
+
+    if let Some(url) = result.url.clone().filter(|_| fetcher.enabled) {
+        let pool = pool.clone();
+        let story_id = result.id;
+        fetcher.tasks.clone().spawn(async move {
+            match fetcher.fetch(&url).await {
+                Ok(link_preview) => {
+                    if let Err(err) = preview::save(&pool, story_id, &link_preview).await {
+                        leptos::logging::error!("Failed to store preview for {story_id}: {err}");
+                    }
+                }
+                Err(err) => leptos::logging::log!("No preview for {url}: {err}"),
+            }
+        }.in_current_span());
+    }
+
+    Ok(result)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn comment_insert(
+    pool: &PgPool,
+    comment: CommentCreateArgs,
+    author_id: i32,
+) -> Result<Comment, LambdaError> {
+    comment.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+    let timestamp = Local::now();
+    let mentioned = mentions::resolve(pool, &comment.text).await?;
+
+    let mut tx = pool.begin().await?;
+    if let Some(parent_id) = comment.parent_id {
+        let parent_story_id = sqlx::query_scalar!("SELECT story_id FROM comments WHERE id = $1", parent_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if parent_story_id != comment.story_id {
+            return Err(LambdaError::ValidationError("the parent comment is on another story".into()));
+        }
+    }
+    let mut comment = sqlx::query_as!(
+        Comment,
+        r#"
+            WITH inserted AS (
+                INSERT INTO comments (story_id, parent_id, text, author_id, created_at)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING *
+            ), counted AS (
+                UPDATE stories SET comment_count = comment_count + 1 WHERE id = $1
+            )
+            SELECT
+                c.id,
+                c.text,
+                c.parent_id,
+                c.story_id,
+                c.created_at,
+                u.display_name as author_name,
+                ARRAY[]::mention[] as "mentions!: Vec<Mention>"
+            FROM inserted c
+            JOIN users u ON c.author_id = u.id
+        "#,
+        comment.story_id,
+        comment.parent_id,
+        comment.text,
+        author_id,
+        timestamp.into()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    mentions::save(&mut *tx, comment.story_id, Some(comment.id), &mentioned).await?;
+    tx.commit().await?;
+    telemetry::record_rows(1);
+    comment.mentions = mentioned.into_iter().map(|resolved| resolved.mention).collect();
+
+    metrics::COMMENTS_CREATED.inc();
+    webhooks::emit(pool, WebhookEvent::CommentCreated, &comment).await;
+    notifications::comment_created(pool, &comment).await;
+    live::publish(pool, live::Change::Comment { id: comment.id }).await;
+
+    Ok(comment)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn comments_for_story(pool: &PgPool, story_id: i32) -> Result<Vec<Comment>, LambdaError> {
+    let comments = sqlx::query_as!(
+        Comment,
+        r#"
+            SELECT
+                c.id,
+                c.text,
+                c.parent_id,
+                c.story_id,
+                c.created_at,
+                u.display_name as author_name,
+                COALESCE(cm.mentions, '{}') as "mentions!: Vec<Mention>"
+            FROM comments c
+            JOIN
+                users u ON c.author_id = u.id
+            LEFT JOIN comment_mentions cm ON cm.comment_id = c.id
+            WHERE c.story_id = $1
+            ORDER BY c.created_at DESC"#,
+        story_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(comments.len());
+
+    Ok(comments)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn comment_by_id(pool: &PgPool, id: i32) -> Result<Comment, LambdaError> {
+    let comment = sqlx::query_as!(
+        Comment,
+        r#"
+            SELECT
+                c.id,
+                c.text,
+                c.parent_id,
+                c.story_id,
+                c.created_at,
+                u.display_name as author_name,
+                COALESCE(cm.mentions, '{}') as "mentions!: Vec<Mention>"
+            FROM comments c
+            JOIN users u ON c.author_id = u.id
+            LEFT JOIN comment_mentions cm ON cm.comment_id = c.id
+            WHERE c.id = $1"#,
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(comment)
+}
+
+/// A comment and its ancestors, oldest first.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn comment_with_parents(pool: &PgPool, id: i32) -> Result<Vec<Comment>, LambdaError> {
+    let rows = sqlx::query(
+        r#"
+        WITH RECURSIVE comment_hierarchy AS (
+            -- Base case: the starting comment
+            SELECT 
+                c.id,
+                c.text,
+                c.parent_id,
+                c.story_id,
+                c.created_at,
+                u.display_name as author_name
+            FROM 
+                comments c
+            JOIN 
+                users u ON c.author_id = u.id
+            WHERE 
+                c.id = $1
+            
+            UNION ALL
+            
+            -- Recursive case: parent comments
+            SELECT 
+                c.id,
+                c.text,
+                c.parent_id,
+                c.story_id,
+                c.created_at,
+                u.display_name as author_name
+            FROM 
+                comments c
+            JOIN 
+                users u ON c.author_id = u.id
+            JOIN 
+                comment_hierarchy ch ON c.id = ch.parent_id
+        )
+        SELECT ch.*, COALESCE(cm.mentions, '{}') as mentions
+        FROM comment_hierarchy ch
+        LEFT JOIN comment_mentions cm ON cm.comment_id = ch.id
+        ORDER BY ch.created_at ASC -- Order from oldest to newest
+        "#,
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    telemetry::record_rows(rows.len());
+
+    Ok(rows.into_iter().map(row_to_comment).collect())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn user_by_name(pool: &PgPool, username: &str) -> Result<User, LambdaError> {
+    let user = sqlx::query_as!(
+        User,
+        r#"SELECT id, display_name as username, created_at FROM users WHERE display_name = $1"#,
+        username
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(user)
+}
+
+/// Records a vote on either a story or a comment, replacing the voter's
+/// previous vote on the same item. A value of zero retracts the vote.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn vote_cast(
+    pool: &PgPool,
+    story_id: Option<i32>,
+    comment_id: Option<i32>,
+    vote: VoteArgs,
+    user_id: i32,
+) -> Result<VoteTally, LambdaError> {
+    vote.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+
+    let result = match (story_id, comment_id) {
+        (Some(_), Some(_)) | (None, None) => {
+            return Err(LambdaError::InvalidData("vote needs exactly one target".into()))
+        }
+        _ if vote.value == 0 => sqlx::query!(
+            r#"
+                DELETE FROM votes
+                WHERE user_id = $1
+                    AND story_id IS NOT DISTINCT FROM $2
+                    AND comment_id IS NOT DISTINCT FROM $3
+            "#,
+            user_id,
+            story_id,
+            comment_id
+        )
+        .execute(pool)
+        .await?,
+        (Some(story_id), None) => sqlx::query!(
+            r#"
+                INSERT INTO votes (user_id, story_id, value, created_at)
+                VALUES ($1, $2, $3, NOW())
+                ON CONFLICT (user_id, story_id) WHERE story_id IS NOT NULL
+                DO UPDATE SET value = EXCLUDED.value, created_at = EXCLUDED.created_at
+            "#,
+            user_id,
+            story_id,
+            vote.value as i16
+        )
+        .execute(pool)
+        .await?,
+        (None, Some(comment_id)) => sqlx::query!(
+            r#"
+                INSERT INTO votes (user_id, comment_id, value, created_at)
+                VALUES ($1, $2, $3, NOW())
+                ON CONFLICT (user_id, comment_id) WHERE comment_id IS NOT NULL
+                DO UPDATE SET value = EXCLUDED.value, created_at = EXCLUDED.created_at
+            "#,
+            user_id,
+            comment_id,
+            vote.value as i16
+        )
+        .execute(pool)
+        .await?,
+    };
+    telemetry::record_rows(result.rows_affected());
+
+    if let Some(story_id) = story_id {
+        sqlx::query!(
+            r#"UPDATE stories SET score = (SELECT COALESCE(SUM(value), 0) FROM votes WHERE story_id = $1) WHERE id = $1"#,
+            story_id
+        )
+        .execute(pool)
+        .await?;
+        live::publish(pool, live::Change::Score { story_id }).await;
+    }
+
+    if vote.value == 0 {
+        metrics::VOTES_RETRACTED.inc();
+    } else {
+        metrics::VOTES_CAST.inc();
+    }
+
+    vote_tally(pool, story_id, comment_id).await
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn vote_tally(
+    pool: &PgPool,
+    story_id: Option<i32>,
+    comment_id: Option<i32>,
+) -> Result<VoteTally, LambdaError> {
+    let tally = sqlx::query_as!(
+        VoteTally,
+        r#"
+            SELECT
+                COALESCE(SUM(value), 0)::integer as "score!",
+                COUNT(*)::integer as "count!"
+            FROM votes
+            WHERE story_id IS NOT DISTINCT FROM $1 AND comment_id IS NOT DISTINCT FROM $2
+        "#,
+        story_id,
+        comment_id
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(tally)
+}
@@ -0,0 +1,521 @@
+use crate::{
+    constants::ACCOUNT,
+    model::{ssr::AppState, LambdaError, TokenScope, WebhookEvent},
+    server::{
+        audit,
+        auth::{token_principal, SITE_USER_ID},
+        telemetry, webhooks,
+    },
+};
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::post,
+    Form, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::str::FromStr;
+use tracing::{field::Empty, instrument};
+use utoipa::ToSchema;
+
+/// Author of deleted accounts' stories and comments, created by migration.
+pub const TOMBSTONE_NAME: &str = "[deleted]";
+/// Replaces the text of content removed with its account.
+pub const REMOVED: &str = "[removed]";
+
+/// Everything stored about an account. Password hashes, token hashes and
+/// webhook secrets are left out; API tokens are the only sessions there are.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct PersonalData {
+    pub exported_at: DateTime<Utc>,
+    pub profile: AccountProfile,
+    pub stories: Vec<AccountStory>,
+    pub comments: Vec<AccountComment>,
+    pub votes: Vec<AccountVote>,
+    pub api_tokens: Vec<AccountToken>,
+    pub webhooks: Vec<AccountWebhook>,
+    pub notifications: Vec<AccountNotification>,
+    pub bookmarks: Vec<AccountBookmark>,
+    pub visits: Vec<AccountVisit>,
+    pub tag_preferences: Vec<AccountTagPreference>,
+    pub ballots: Vec<AccountBallot>,
+    pub drafts: Vec<AccountDraft>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountProfile {
+    pub id: i32,
+    pub display_name: String,
+    pub is_admin: bool,
+    pub has_password: bool,
+    pub banned_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountStory {
+    pub id: i32,
+    pub title: String,
+    pub text: Option<String>,
+    pub url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountComment {
+    pub id: i32,
+    pub story_id: i32,
+    pub parent_id: Option<i32>,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountVote {
+    pub story_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub value: i16,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountToken {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountWebhook {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountNotification {
+    pub kind: String,
+    pub story_id: i32,
+    pub comment_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountBookmark {
+    pub story_id: i32,
+    pub comment_id: Option<i32>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountVisit {
+    pub story_id: i32,
+    pub seen_at: Option<DateTime<Utc>>,
+    pub hidden_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountTagPreference {
+    pub tag: String,
+    pub muted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountBallot {
+    pub story_id: i32,
+    pub option: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountDraft {
+    pub story_id: Option<i32>,
+    pub parent_id: Option<i32>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub text: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What happens to an account's stories and comments. Either way they move
+/// to the tombstone user, so replies by others keep their place.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletionMode {
+    /// Keep the text, drop the authorship.
+    Anonymize,
+    /// Replace titles and text with a placeholder as well.
+    Remove,
+}
+
+impl DeletionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeletionMode::Anonymize => "anonymize",
+            DeletionMode::Remove => "remove",
+        }
+    }
+}
+
+impl FromStr for DeletionMode {
+    type Err = LambdaError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        [DeletionMode::Anonymize, DeletionMode::Remove]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == mode)
+            .ok_or_else(|| LambdaError::InvalidData(format!("unknown deletion mode {mode}")))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AccountDeleteArgs {
+    pub content: DeletionMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct AccountDeleted {
+    pub stories: u64,
+    pub comments: u64,
+    pub votes: u64,
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn personal_data(pool: &PgPool, user_id: i32) -> Result<PersonalData, LambdaError> {
+    let profile = sqlx::query_as!(
+        AccountProfile,
+        r#"
+            SELECT id, display_name, is_admin, password_hash IS NOT NULL as "has_password!", banned_at, created_at
+            FROM users
+            WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let stories = sqlx::query_as!(
+        AccountStory,
+        "SELECT id, title, text, url, created_at FROM stories WHERE author_id = $1 ORDER BY id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let comments = sqlx::query_as!(
+        AccountComment,
+        "SELECT id, story_id, parent_id, text, created_at FROM comments WHERE author_id = $1 ORDER BY id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let votes = sqlx::query_as!(
+        AccountVote,
+        "SELECT story_id, comment_id, value, created_at FROM votes WHERE user_id = $1 ORDER BY id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let api_tokens = sqlx::query_as!(
+        AccountToken,
+        r#"
+            SELECT id, name, scopes, created_at, last_used_at, revoked_at
+            FROM api_tokens
+            WHERE user_id = $1
+            ORDER BY id
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let webhooks = sqlx::query_as!(
+        AccountWebhook,
+        "SELECT id, url, events, created_at, deleted_at FROM webhooks WHERE user_id = $1 ORDER BY id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let notifications = sqlx::query_as!(
+        AccountNotification,
+        "SELECT kind, story_id, comment_id, created_at, read_at FROM notifications WHERE user_id = $1 ORDER BY id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let bookmarks = sqlx::query_as!(
+        AccountBookmark,
+        "SELECT story_id, comment_id, note, created_at FROM bookmarks WHERE user_id = $1 ORDER BY id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let visits = sqlx::query_as!(
+        AccountVisit,
+        "SELECT story_id, seen_at, hidden_at FROM story_visits WHERE user_id = $1 ORDER BY story_id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let tag_preferences = sqlx::query_as!(
+        AccountTagPreference,
+        r#"
+            SELECT t.name as tag, p.muted
+            FROM tag_preferences p
+            JOIN tags t ON t.id = p.tag_id
+            WHERE p.user_id = $1
+            ORDER BY t.name
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let ballots = sqlx::query_as!(
+        AccountBallot,
+        r#"
+            SELECT b.story_id, o.text as option, b.created_at
+            FROM ballots b
+            JOIN poll_options o ON o.id = b.option_id
+            WHERE b.user_id = $1
+            ORDER BY b.story_id, o.position
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let drafts = sqlx::query_as!(
+        AccountDraft,
+        r#"
+            SELECT story_id, parent_id, title, url, text, updated_at
+            FROM drafts
+            WHERE user_id = $1
+            ORDER BY updated_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(
+        1 + stories.len()
+            + comments.len()
+            + votes.len()
+            + api_tokens.len()
+            + webhooks.len()
+            + notifications.len()
+            + bookmarks.len()
+            + visits.len()
+            + tag_preferences.len()
+            + ballots.len()
+            + drafts.len(),
+    );
+
+    Ok(PersonalData {
+        exported_at: Utc::now(),
+        profile,
+        stories,
+        comments,
+        votes,
+        api_tokens,
+        webhooks,
+        notifications,
+        bookmarks,
+        visits,
+        tag_preferences,
+        ballots,
+        drafts,
+    })
+}
+
+/// Deletes an account in one transaction. Stories and comments are handed to
+/// the tombstone user, anonymized or removed; votes, tokens and webhooks go
+/// with the account. The deletion is recorded in the audit trail, and
+/// subscribers hear of removed stories and of deletions by moderators.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn account_delete(
+    pool: &PgPool,
+    user_id: i32,
+    mode: DeletionMode,
+    actor_id: Option<i32>,
+) -> Result<AccountDeleted, LambdaError> {
+    if user_id == SITE_USER_ID {
+        return Err(LambdaError::ValidationError("the site account cannot be deleted".into()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let tombstone_id = sqlx::query_scalar!(
+        r#"
+            INSERT INTO users (display_name, banned_at, created_at)
+            VALUES ($1, NOW(), NOW())
+            ON CONFLICT (display_name) DO UPDATE SET display_name = EXCLUDED.display_name
+            RETURNING id
+        "#,
+        TOMBSTONE_NAME
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if user_id == tombstone_id {
+        return Err(LambdaError::ValidationError("the tombstone account cannot be deleted".into()));
+    }
+    sqlx::query_scalar!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let remove = mode == DeletionMode::Remove;
+    if remove {
+        sqlx::query!(
+            "DELETE FROM story_previews WHERE story_id IN (SELECT id FROM stories WHERE author_id = $1)",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+                DELETE FROM mentions
+                WHERE (comment_id IS NULL AND story_id IN (SELECT id FROM stories WHERE author_id = $1))
+                    OR comment_id IN (SELECT id FROM comments WHERE author_id = $1)
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let story_ids = sqlx::query_scalar!(
+        r#"
+            UPDATE stories SET
+                author_id = $2,
+                title = CASE WHEN $3 THEN $4 ELSE title END,
+                text = CASE WHEN $3 THEN NULL ELSE text END,
+                text_html = CASE WHEN $3 THEN NULL ELSE text_html END,
+                url = CASE WHEN $3 THEN NULL ELSE url END,
+                canonical_url = CASE WHEN $3 THEN NULL ELSE canonical_url END
+            WHERE author_id = $1
+            RETURNING id
+        "#,
+        user_id,
+        tombstone_id,
+        remove,
+        REMOVED
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let stories = story_ids.len() as u64;
+    let comments = sqlx::query!(
+        r#"
+            UPDATE comments SET
+                author_id = $2,
+                text = CASE WHEN $3 THEN $4 ELSE text END
+            WHERE author_id = $1
+        "#,
+        user_id,
+        tombstone_id,
+        remove,
+        REMOVED
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let votes = sqlx::query!(
+        r#"
+            WITH deleted AS (
+                DELETE FROM votes WHERE user_id = $1 RETURNING story_id
+            ), rescored AS (
+                UPDATE stories s
+                SET score = (SELECT COALESCE(SUM(v.value), 0) FROM votes v WHERE v.story_id = s.id AND v.user_id <> $1)
+                WHERE s.id IN (SELECT story_id FROM deleted)
+            )
+            SELECT COUNT(*) as "count!" FROM deleted
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .count as u64;
+
+    sqlx::query!(
+        "DELETE FROM webhook_deliveries WHERE webhook_id IN (SELECT id FROM webhooks WHERE user_id = $1)",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM webhooks WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM api_tokens WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let deleted = AccountDeleted { stories, comments, votes };
+    let self_service = actor_id == Some(user_id);
+    audit::record(
+        &mut *tx,
+        actor_id.filter(|&actor_id| actor_id != user_id),
+        audit::ACCOUNT_DELETED,
+        Some(user_id),
+        json!({
+            "content": mode.as_str(),
+            "self_service": self_service,
+            "stories": deleted.stories,
+            "comments": deleted.comments,
+            "votes": deleted.votes,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+    telemetry::record_rows(deleted.stories + deleted.comments + deleted.votes);
+
+    if remove {
+        for id in story_ids {
+            webhooks::emit(pool, WebhookEvent::StoryDeleted, &json!({ "id": id })).await;
+        }
+    }
+    if !self_service {
+        let data = json!({ "action": audit::ACCOUNT_DELETED, "user_id": user_id, "content": mode.as_str() });
+        webhooks::emit(pool, WebhookEvent::ModerationAction, &data).await;
+    }
+
+    Ok(deleted)
+}
+
+/// A token with the `account` scope, pasted into the account page.
+#[derive(Deserialize, Debug)]
+pub struct AccountForm {
+    pub token: String,
+}
+
+/// The account page's export, a plain form post so the browser downloads
+/// the file itself.
+pub fn router() -> Router<AppState> {
+    Router::new().route(&format!("/{ACCOUNT}/export.json"), post(export_json))
+}
+
+async fn export_json(State(state): State<AppState>, Form(form): Form<AccountForm>) -> Result<Response, LambdaError> {
+    let user_id = token_principal(&state.pool, &form.token).await?.require(TokenScope::Account)?;
+    let data = personal_data(&state.pool, user_id).await?;
+    let disposition = format!("attachment; filename=\"account-{user_id}.json\"");
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(data)).into_response())
+}
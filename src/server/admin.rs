@@ -0,0 +1,249 @@
+use crate::{
+    features::{canonical::canonicalize_url, ui::markdown::render_markdown},
+    model::{LambdaError, User, WebhookEvent},
+    server::{audit, auth::password_hash, mentions, user_by_name, webhooks},
+};
+use serde_json::json;
+use sqlx::{
+    migrate::{MigrateError, MigrationType, Migrator},
+    PgPool,
+};
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// How often long-running tasks report progress, in rows.
+pub const PROGRESS_EVERY: u64 = 1000;
+
+/// Reverts applied migrations newer than `target`. Fails without touching
+/// anything when one of them has no down script, or one that only holds
+/// comments because what its up script changed can't be undone.
+pub async fn migrations_revert(pool: &PgPool, target: i64) -> Result<Vec<i64>, MigrateError> {
+    let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE version > $1")
+        .bind(target)
+        .fetch_all(pool)
+        .await?;
+
+    let mut reverted = Vec::new();
+    for migration in MIGRATOR.iter().filter(|migration| applied.contains(&migration.version)) {
+        match migration.migration_type {
+            MigrationType::ReversibleDown if !has_statements(&migration.sql) => {
+                return Err(MigrateError::Source(
+                    format!("migration {} ({}) can't be undone", migration.version, migration.description).into(),
+                ))
+            }
+            MigrationType::ReversibleDown => reverted.push(migration.version),
+            MigrationType::Simple => {
+                return Err(MigrateError::Source(
+                    format!("migration {} ({}) is not reversible", migration.version, migration.description).into(),
+                ))
+            }
+            MigrationType::ReversibleUp => {}
+        }
+    }
+
+    MIGRATOR.undo(pool, target).await?;
+    Ok(reverted)
+}
+
+fn has_statements(sql: &str) -> bool {
+    sql.lines()
+        .map(|line| line.split("--").next().unwrap_or_default().trim())
+        .any(|line| !line.is_empty())
+}
+
+pub async fn user_create(
+    pool: &PgPool,
+    name: &str,
+    password: Option<&str>,
+    is_admin: bool,
+) -> Result<User, LambdaError> {
+    if name.trim().is_empty() {
+        return Err(LambdaError::ValidationError("name is empty".into()));
+    }
+    let hash = password.map(password_hash).transpose()?;
+
+    let user = sqlx::query_as!(
+        User,
+        r#"
+            INSERT INTO users (display_name, password_hash, is_admin, created_at)
+            VALUES ($1, $2, $3, NOW())
+            RETURNING id, display_name as username, created_at
+        "#,
+        name,
+        hash,
+        is_admin
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(err) if err.is_unique_violation() => {
+            LambdaError::ValidationError(format!("user {name} already exists"))
+        }
+        err => err.into(),
+    })?;
+
+    Ok(user)
+}
+
+/// Sets a user's password, signing them out of every browser.
+pub async fn user_set_password(pool: &PgPool, name: &str, password: &str) -> Result<(), LambdaError> {
+    let user = user_by_name(pool, name).await?;
+    sqlx::query!(
+        "UPDATE users SET password_hash = $2 WHERE id = $1",
+        user.id,
+        password_hash(password)?
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query!("DELETE FROM sessions WHERE user_id = $1", user.id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Bans or unbans a user. Banned users' API tokens stop authenticating.
+pub async fn user_set_banned(pool: &PgPool, name: &str, banned: bool) -> Result<(), LambdaError> {
+    let user = user_by_name(pool, name).await?;
+    sqlx::query!(
+        "UPDATE users SET banned_at = CASE WHEN $2 THEN COALESCE(banned_at, NOW()) END WHERE id = $1",
+        user.id,
+        banned
+    )
+    .execute(pool)
+    .await?;
+
+    let action = if banned { audit::USER_BANNED } else { audit::USER_UNBANNED };
+    audit::record(pool, None, action, Some(user.id), json!({})).await?;
+    let data = json!({ "action": action, "user_id": user.id, "user_name": user.username });
+    webhooks::emit(pool, WebhookEvent::ModerationAction, &data).await;
+
+    Ok(())
+}
+
+/// Renames a user. Existing mentions keep pointing at them, and stories that
+/// mention them are rendered again to link to the new name.
+pub async fn user_rename(pool: &PgPool, name: &str, new_name: &str) -> Result<u64, LambdaError> {
+    if new_name.trim().is_empty() {
+        return Err(LambdaError::ValidationError("name is empty".into()));
+    }
+    let user = user_by_name(pool, name).await?;
+    sqlx::query!("UPDATE users SET display_name = $2 WHERE id = $1", user.id, new_name)
+        .execute(pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(err) if err.is_unique_violation() => {
+                LambdaError::ValidationError(format!("user {new_name} already exists"))
+            }
+            err => err.into(),
+        })?;
+
+    mentions::stories_rerender(pool, user.id).await
+}
+
+/// Recomputes the per-story score and comment count columns from the
+/// underlying votes and comments, returning how many stories changed.
+pub async fn counts_rebuild(pool: &PgPool) -> Result<u64, LambdaError> {
+    let result = sqlx::query!(
+        r#"
+            UPDATE stories s SET
+                score = counts.score,
+                comment_count = counts.comment_count
+            FROM (
+                SELECT
+                    s.id,
+                    (SELECT COALESCE(SUM(v.value), 0) FROM votes v WHERE v.story_id = s.id)::integer as score,
+                    (SELECT COUNT(*) FROM comments c WHERE c.story_id = s.id)::integer as comment_count
+                FROM stories s
+            ) counts
+            WHERE s.id = counts.id
+                AND (s.score, s.comment_count) IS DISTINCT FROM (counts.score, counts.comment_count)
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Records the mentions in every story and comment, resolving names against
+/// the current users, e.g. after restoring an archive. Mentions already
+/// recorded are kept.
+pub async fn mentions_rebuild(pool: &PgPool, mut progress: impl FnMut(u64)) -> Result<u64, LambdaError> {
+    let texts = sqlx::query!(
+        r#"
+            SELECT id as "story_id!", NULL::integer as comment_id, text as "text!"
+            FROM stories WHERE text IS NOT NULL
+            UNION ALL
+            SELECT story_id, id, text FROM comments
+            ORDER BY 1, 2 NULLS FIRST
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut scanned = 0;
+    for row in texts {
+        let resolved = mentions::resolve(pool, &row.text).await?;
+        mentions::save(pool, row.story_id, row.comment_id, &resolved).await?;
+        scanned += 1;
+        if scanned % PROGRESS_EVERY == 0 {
+            progress(scanned);
+        }
+    }
+    progress(scanned);
+
+    Ok(scanned)
+}
+
+/// Recomputes the canonical form of every story link, e.g. for stories
+/// posted before duplicate detection. Returns how many changed.
+pub async fn canonical_urls_rebuild(pool: &PgPool, mut progress: impl FnMut(u64)) -> Result<u64, LambdaError> {
+    let stories = sqlx::query!("SELECT id, url, canonical_url FROM stories WHERE url IS NOT NULL ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    let (mut scanned, mut changed) = (0, 0);
+    for story in stories {
+        let canonical_url = story.url.as_deref().and_then(canonicalize_url);
+        if canonical_url != story.canonical_url {
+            sqlx::query!("UPDATE stories SET canonical_url = $2 WHERE id = $1", story.id, canonical_url)
+                .execute(pool)
+                .await?;
+            changed += 1;
+        }
+        scanned += 1;
+        if scanned % PROGRESS_EVERY == 0 {
+            progress(scanned);
+        }
+    }
+    progress(scanned);
+
+    Ok(changed)
+}
+
+/// Renders every story's Markdown again, e.g. after changing the renderer.
+pub async fn markdown_rerender(pool: &PgPool, mut progress: impl FnMut(u64)) -> Result<u64, LambdaError> {
+    let stories = sqlx::query!("SELECT id, text FROM stories WHERE text IS NOT NULL ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    let mut rendered = 0;
+    for story in stories {
+        let mentions = mentions::story_mentions(pool, story.id).await?;
+        sqlx::query!(
+            "UPDATE stories SET text_html = $2 WHERE id = $1",
+            story.id,
+            story.text.as_deref().map(|text| render_markdown(text, &mentions))
+        )
+        .execute(pool)
+        .await?;
+        rendered += 1;
+        if rendered % PROGRESS_EVERY == 0 {
+            progress(rendered);
+        }
+    }
+    progress(rendered);
+
+    Ok(rendered)
+}
@@ -0,0 +1,704 @@
+use crate::{
+    model::{LambdaError, StoryKind},
+    server::admin::{counts_rebuild, markdown_rerender, mentions_rebuild, PROGRESS_EVERY},
+};
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures::{Stream, TryStreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, Write},
+};
+
+/// Identifies an archive in its manifest.
+pub const FORMAT: &str = "lambda-function-archive";
+/// Bumped whenever a row shape or the set of tables changes; older archives
+/// stay importable.
+pub const VERSION: u32 = 2;
+pub const MANIFEST_PATH: &str = "manifest.json";
+
+/// Tables in the order they are written and restored, parents first.
+/// Moderation state lives on `users` (`banned_at`, `is_admin`) and in
+/// `audit_log`.
+pub const TABLES: [&str; 10] = [
+    "users",
+    "stories",
+    "comments",
+    "votes",
+    "tags",
+    "story_tags",
+    "polls",
+    "poll_options",
+    "ballots",
+    "audit_log",
+];
+
+/// Tables whose `id` comes from a sequence, moved past the restored ids.
+const SEQUENCED: [&str; 7] = ["users", "stories", "comments", "votes", "tags", "poll_options", "audit_log"];
+
+/// The tables an archive of `version` holds: version 1 predates tags, polls
+/// and the audit trail.
+pub fn tables(version: u32) -> &'static [&'static str] {
+    match version {
+        1 => &TABLES[..4],
+        _ => &TABLES,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<ManifestFile>,
+}
+
+/// One JSON lines file of an archive, with its row count and hex SHA-256.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFile {
+    pub table: String,
+    pub path: String,
+    pub rows: u64,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserRow {
+    pub id: i32,
+    pub display_name: String,
+    pub is_admin: bool,
+    pub password_hash: Option<String>,
+    pub banned_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoryRow {
+    pub id: i32,
+    pub title: String,
+    pub text: Option<String>,
+    pub url: Option<String>,
+    pub canonical_url: Option<String>,
+    pub author_id: i32,
+    pub created_at: DateTime<Utc>,
+    /// Missing from archives made before story kinds, which restore as links.
+    #[serde(default)]
+    pub kind: StoryKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentRow {
+    pub id: i32,
+    pub story_id: i32,
+    pub parent_id: Option<i32>,
+    pub author_id: i32,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VoteRow {
+    pub user_id: i32,
+    pub story_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub value: i16,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TagRow {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoryTagRow {
+    pub story_id: i32,
+    pub tag_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PollRow {
+    pub story_id: i32,
+    pub multiple: bool,
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PollOptionRow {
+    pub id: i32,
+    pub story_id: i32,
+    pub position: i32,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BallotRow {
+    pub user_id: i32,
+    pub option_id: i32,
+    pub story_id: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditRow {
+    pub id: i64,
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub subject_id: Option<i32>,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+fn invalid(err: impl ToString) -> LambdaError {
+    LambdaError::InvalidData(err.to_string())
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Hashes everything read from or written to `inner`.
+struct Hashed<T> {
+    inner: T,
+    hasher: Sha256,
+}
+
+impl<T> Hashed<T> {
+    fn new(inner: T) -> Self {
+        Hashed {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+}
+
+impl<T: Write> Write for Hashed<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T: Read> Read for Hashed<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// A table being spooled to a temporary file before it goes into the tarball,
+/// whose headers need each file's size up front.
+struct Spool {
+    table: &'static str,
+    file: Hashed<BufWriter<File>>,
+    rows: u64,
+}
+
+impl Spool {
+    fn new(table: &'static str) -> Result<Self, LambdaError> {
+        let file = tempfile::tempfile().map_err(invalid)?;
+        Ok(Spool {
+            table,
+            file: Hashed::new(BufWriter::new(file)),
+            rows: 0,
+        })
+    }
+
+    fn write(&mut self, row: &impl Serialize) -> Result<(), LambdaError> {
+        serde_json::to_writer(&mut self.file, row).map_err(invalid)?;
+        self.file.write_all(b"\n").map_err(invalid)?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(ManifestFile, File), LambdaError> {
+        let Hashed { inner, hasher } = self.file;
+        let mut file = inner.into_inner().map_err(invalid)?;
+        file.rewind().map_err(invalid)?;
+        let entry = ManifestFile {
+            table: self.table.to_string(),
+            path: format!("{}.jsonl", self.table),
+            rows: self.rows,
+            sha256: hex(&hasher.finalize()),
+        };
+        Ok((entry, file))
+    }
+}
+
+fn append(builder: &mut tar::Builder<impl Write>, path: &str, size: u64, mtime: u64, data: impl Read) -> Result<(), LambdaError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    builder.append_data(&mut header, path, data).map_err(invalid)
+}
+
+/// Spools every row of `table`, reporting progress across all tables.
+async fn spool<T: Serialize>(
+    table: &'static str,
+    mut rows: impl Stream<Item = Result<T, sqlx::Error>> + Unpin,
+    total: &mut u64,
+    progress: &mut impl FnMut(&str, u64),
+) -> Result<Spool, LambdaError> {
+    let mut spool = Spool::new(table)?;
+    while let Some(row) = rows.try_next().await? {
+        spool.write(&row)?;
+        *total += 1;
+        if *total % PROGRESS_EVERY == 0 {
+            progress(spool.table, *total);
+        }
+    }
+    Ok(spool)
+}
+
+/// Writes a gzipped tarball holding `manifest.json` followed by one JSON lines
+/// file per table, read from a single consistent snapshot.
+pub async fn export(
+    pool: &PgPool,
+    out: impl Write,
+    mut progress: impl FnMut(&str, u64),
+) -> Result<Manifest, LambdaError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut total = 0;
+    let mut spools = Vec::new();
+
+    let rows = sqlx::query_as!(
+        UserRow,
+        "SELECT id, display_name, is_admin, password_hash, banned_at, created_at FROM users ORDER BY id"
+    )
+    .fetch(&mut *tx);
+    spools.push(spool("users", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(
+        StoryRow,
+        r#"
+            SELECT id, title, text, url, canonical_url, author_id, created_at, kind as "kind: StoryKind"
+            FROM stories ORDER BY id
+        "#
+    )
+    .fetch(&mut *tx);
+    spools.push(spool("stories", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(
+        CommentRow,
+        "SELECT id, story_id, parent_id, author_id, text, created_at FROM comments ORDER BY id"
+    )
+    .fetch(&mut *tx);
+    spools.push(spool("comments", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(
+        VoteRow,
+        "SELECT user_id, story_id, comment_id, value, created_at FROM votes ORDER BY id"
+    )
+    .fetch(&mut *tx);
+    spools.push(spool("votes", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(TagRow, "SELECT id, name, description, created_at FROM tags ORDER BY id")
+        .fetch(&mut *tx);
+    spools.push(spool("tags", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(StoryTagRow, "SELECT story_id, tag_id FROM story_tags ORDER BY story_id, tag_id")
+        .fetch(&mut *tx);
+    spools.push(spool("story_tags", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(PollRow, "SELECT story_id, multiple, closes_at FROM polls ORDER BY story_id")
+        .fetch(&mut *tx);
+    spools.push(spool("polls", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(
+        PollOptionRow,
+        "SELECT id, story_id, position, text FROM poll_options ORDER BY id"
+    )
+    .fetch(&mut *tx);
+    spools.push(spool("poll_options", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(
+        BallotRow,
+        "SELECT user_id, option_id, story_id, created_at FROM ballots ORDER BY story_id, user_id, option_id"
+    )
+    .fetch(&mut *tx);
+    spools.push(spool("ballots", rows, &mut total, &mut progress).await?);
+
+    let rows = sqlx::query_as!(
+        AuditRow,
+        "SELECT id, actor_id, action, subject_id, details, created_at FROM audit_log ORDER BY id"
+    )
+    .fetch(&mut *tx);
+    spools.push(spool("audit_log", rows, &mut total, &mut progress).await?);
+
+    tx.commit().await?;
+
+    let created_at = Utc::now();
+    let mut files = Vec::new();
+    let mut data = Vec::new();
+    for spool in spools {
+        let (entry, file) = spool.finish()?;
+        files.push(entry);
+        data.push(file);
+    }
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created_at,
+        files,
+    };
+
+    let mtime = created_at.timestamp().max(0) as u64;
+    let mut builder = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+    let json = serde_json::to_vec_pretty(&manifest).map_err(invalid)?;
+    append(&mut builder, MANIFEST_PATH, json.len() as u64, mtime, json.as_slice())?;
+    for (entry, file) in manifest.files.iter().zip(data) {
+        let size = file.metadata().map_err(invalid)?.len();
+        append(&mut builder, &entry.path, size, mtime, file)?;
+    }
+    builder.into_inner().map_err(invalid)?.finish().map_err(invalid)?.flush().map_err(invalid)?;
+    Ok(manifest)
+}
+
+/// Ids restored so far, to check every reference before committing.
+#[derive(Default)]
+struct Restored {
+    users: HashSet<i32>,
+    stories: HashSet<i32>,
+    comments: HashSet<i32>,
+    /// `(line, parent_id)` of replies, checked once all comments are in.
+    parents: Vec<(usize, i32)>,
+    tags: HashSet<i32>,
+    polls: HashSet<i32>,
+    /// The poll each option belongs to.
+    options: HashMap<i32, i32>,
+}
+
+fn parse<T: DeserializeOwned>(path: &str, number: usize, line: &str) -> Result<T, LambdaError> {
+    serde_json::from_str(line).map_err(|err| invalid(format!("{path} line {number}: {err}")))
+}
+
+fn require(path: &str, number: usize, kind: &str, id: i32, ids: &HashSet<i32>) -> Result<(), LambdaError> {
+    if ids.contains(&id) {
+        Ok(())
+    } else {
+        Err(invalid(format!("{path} line {number}: {kind} {id} does not exist")))
+    }
+}
+
+async fn restore_row(
+    tx: &mut Transaction<'static, Postgres>,
+    restored: &mut Restored,
+    file: &ManifestFile,
+    number: usize,
+    line: &str,
+) -> Result<(), LambdaError> {
+    let path = file.path.as_str();
+    match file.table.as_str() {
+        "users" => {
+            let row: UserRow = parse(path, number, line)?;
+            sqlx::query!(
+                r#"
+                    INSERT INTO users (id, display_name, is_admin, password_hash, banned_at, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    ON CONFLICT (id) DO UPDATE SET
+                        display_name = EXCLUDED.display_name,
+                        is_admin = EXCLUDED.is_admin,
+                        password_hash = EXCLUDED.password_hash,
+                        banned_at = EXCLUDED.banned_at,
+                        created_at = EXCLUDED.created_at
+                "#,
+                row.id,
+                row.display_name,
+                row.is_admin,
+                row.password_hash,
+                row.banned_at,
+                row.created_at
+            )
+            .execute(&mut **tx)
+            .await?;
+            restored.users.insert(row.id);
+        }
+        "stories" => {
+            let row: StoryRow = parse(path, number, line)?;
+            require(path, number, "user", row.author_id, &restored.users)?;
+            sqlx::query!(
+                r#"
+                    INSERT INTO stories (id, title, text, url, canonical_url, author_id, created_at, kind)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+                row.id,
+                row.title,
+                row.text,
+                row.url,
+                row.canonical_url,
+                row.author_id,
+                row.created_at,
+                row.kind as StoryKind
+            )
+            .execute(&mut **tx)
+            .await?;
+            restored.stories.insert(row.id);
+        }
+        "comments" => {
+            let row: CommentRow = parse(path, number, line)?;
+            require(path, number, "user", row.author_id, &restored.users)?;
+            require(path, number, "story", row.story_id, &restored.stories)?;
+            if let Some(parent_id) = row.parent_id {
+                restored.parents.push((number, parent_id));
+            }
+            sqlx::query!(
+                r#"
+                    INSERT INTO comments (id, story_id, parent_id, author_id, text, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                row.id,
+                row.story_id,
+                row.parent_id,
+                row.author_id,
+                row.text,
+                row.created_at
+            )
+            .execute(&mut **tx)
+            .await?;
+            restored.comments.insert(row.id);
+        }
+        "votes" => {
+            let row: VoteRow = parse(path, number, line)?;
+            require(path, number, "user", row.user_id, &restored.users)?;
+            match (row.story_id, row.comment_id) {
+                (Some(story_id), None) => require(path, number, "story", story_id, &restored.stories)?,
+                (None, Some(comment_id)) => require(path, number, "comment", comment_id, &restored.comments)?,
+                _ => return Err(invalid(format!("{path} line {number}: a vote needs a story or a comment"))),
+            }
+            sqlx::query!(
+                r#"
+                    INSERT INTO votes (user_id, story_id, comment_id, value, created_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                "#,
+                row.user_id,
+                row.story_id,
+                row.comment_id,
+                row.value,
+                row.created_at
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        "tags" => {
+            let row: TagRow = parse(path, number, line)?;
+            sqlx::query!(
+                r#"
+                    INSERT INTO tags (id, name, description, created_at)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (id) DO UPDATE SET
+                        name = EXCLUDED.name,
+                        description = EXCLUDED.description,
+                        created_at = EXCLUDED.created_at
+                "#,
+                row.id,
+                row.name,
+                row.description,
+                row.created_at
+            )
+            .execute(&mut **tx)
+            .await?;
+            restored.tags.insert(row.id);
+        }
+        "story_tags" => {
+            let row: StoryTagRow = parse(path, number, line)?;
+            require(path, number, "story", row.story_id, &restored.stories)?;
+            require(path, number, "tag", row.tag_id, &restored.tags)?;
+            sqlx::query!(
+                "INSERT INTO story_tags (story_id, tag_id) VALUES ($1, $2)",
+                row.story_id,
+                row.tag_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        "polls" => {
+            let row: PollRow = parse(path, number, line)?;
+            require(path, number, "story", row.story_id, &restored.stories)?;
+            sqlx::query!(
+                "INSERT INTO polls (story_id, multiple, closes_at) VALUES ($1, $2, $3)",
+                row.story_id,
+                row.multiple,
+                row.closes_at
+            )
+            .execute(&mut **tx)
+            .await?;
+            restored.polls.insert(row.story_id);
+        }
+        "poll_options" => {
+            let row: PollOptionRow = parse(path, number, line)?;
+            require(path, number, "poll", row.story_id, &restored.polls)?;
+            sqlx::query!(
+                "INSERT INTO poll_options (id, story_id, position, text) VALUES ($1, $2, $3, $4)",
+                row.id,
+                row.story_id,
+                row.position,
+                row.text
+            )
+            .execute(&mut **tx)
+            .await?;
+            restored.options.insert(row.id, row.story_id);
+        }
+        "ballots" => {
+            let row: BallotRow = parse(path, number, line)?;
+            require(path, number, "user", row.user_id, &restored.users)?;
+            if restored.options.get(&row.option_id) != Some(&row.story_id) {
+                return Err(invalid(format!(
+                    "{path} line {number}: poll {} has no option {}",
+                    row.story_id, row.option_id
+                )));
+            }
+            sqlx::query!(
+                "INSERT INTO ballots (user_id, option_id, story_id, created_at) VALUES ($1, $2, $3, $4)",
+                row.user_id,
+                row.option_id,
+                row.story_id,
+                row.created_at
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        "audit_log" => {
+            let row: AuditRow = parse(path, number, line)?;
+            if let Some(actor_id) = row.actor_id {
+                require(path, number, "user", actor_id, &restored.users)?;
+            }
+            sqlx::query!(
+                r#"
+                    INSERT INTO audit_log (id, actor_id, action, subject_id, details, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                row.id,
+                row.actor_id,
+                row.action,
+                row.subject_id,
+                row.details,
+                row.created_at
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        table => return Err(invalid(format!("unknown table {table}"))),
+    }
+    Ok(())
+}
+
+/// Restores an [`export`] into a database without content, keeping ids, in a
+/// single transaction. Nothing is committed unless every file matches its
+/// checksum and row count and every reference resolves. Counts and rendered
+/// Markdown are rebuilt afterwards.
+pub async fn import(pool: &PgPool, input: impl Read, mut progress: impl FnMut(u64)) -> Result<Manifest, LambdaError> {
+    let has_content = sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS(SELECT 1 FROM stories)
+                OR EXISTS(SELECT 1 FROM comments)
+                OR EXISTS(SELECT 1 FROM votes) as "exists!"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+    if has_content {
+        return Err(invalid("import needs a database without stories, comments or votes"));
+    }
+
+    let mut archive = tar::Archive::new(GzDecoder::new(input));
+    let mut entries = archive.entries().map_err(invalid)?;
+
+    let mut entry = entries
+        .next()
+        .ok_or_else(|| invalid("archive is empty"))?
+        .map_err(invalid)?;
+    if entry.path().map_err(invalid)?.to_str() != Some(MANIFEST_PATH) {
+        return Err(invalid(format!("archive does not start with {MANIFEST_PATH}")));
+    }
+    let manifest: Manifest = serde_json::from_reader(&mut entry).map_err(|err| invalid(format!("{MANIFEST_PATH}: {err}")))?;
+    if manifest.format != FORMAT {
+        return Err(invalid(format!("not a {FORMAT}, found {}", manifest.format)));
+    }
+    if manifest.version > VERSION {
+        return Err(invalid(format!(
+            "archive version {} is newer than supported version {VERSION}",
+            manifest.version
+        )));
+    }
+    let expected = tables(manifest.version);
+    let found: Vec<&str> = manifest.files.iter().map(|file| file.table.as_str()).collect();
+    if found != expected {
+        return Err(invalid(format!("expected tables {expected:?}, found {found:?}")));
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut restored = Restored::default();
+    let mut total = 0;
+    for file in &manifest.files {
+        let entry = entries
+            .next()
+            .ok_or_else(|| invalid(format!("{} is missing", file.path)))?
+            .map_err(invalid)?;
+        if entry.path().map_err(invalid)?.to_str() != Some(file.path.as_str()) {
+            return Err(invalid(format!("expected {} next in the archive", file.path)));
+        }
+
+        let mut reader = BufReader::new(Hashed::new(entry));
+        let mut rows = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).map_err(invalid)? == 0 {
+                break;
+            }
+            rows += 1;
+            restore_row(&mut tx, &mut restored, file, rows as usize, line.trim_end()).await?;
+            total += 1;
+            if total % PROGRESS_EVERY == 0 {
+                progress(total);
+            }
+        }
+
+        let sha256 = hex(&reader.into_inner().hasher.finalize());
+        if sha256 != file.sha256 {
+            return Err(invalid(format!("{} checksum mismatch", file.path)));
+        }
+        if rows != file.rows {
+            return Err(invalid(format!("{} has {rows} rows, manifest says {}", file.path, file.rows)));
+        }
+    }
+
+    for (number, parent_id) in &restored.parents {
+        require("comments.jsonl", *number, "parent comment", *parent_id, &restored.comments)?;
+    }
+
+    for table in SEQUENCED {
+        sqlx::query(&format!(
+            "SELECT setval(pg_get_serial_sequence('{table}', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM {table}"
+        ))
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    progress(total);
+
+    counts_rebuild(pool).await?;
+    mentions_rebuild(pool, |_| {}).await?;
+    markdown_rerender(pool, |_| {}).await?;
+
+    Ok(manifest)
+}
@@ -0,0 +1,33 @@
+use crate::model::LambdaError;
+use serde_json::Value;
+use sqlx::{Executor, Postgres};
+
+pub const ACCOUNT_DELETED: &str = "account.deleted";
+pub const STORY_TAGGED: &str = "story.tagged";
+pub const USER_BANNED: &str = "user.banned";
+pub const USER_UNBANNED: &str = "user.unbanned";
+
+/// Appends an entry to the audit trail. `actor_id` is `None` for operators
+/// using `news-admin` and for the system.
+pub async fn record<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    actor_id: Option<i32>,
+    action: &str,
+    subject_id: Option<i32>,
+    details: Value,
+) -> Result<(), LambdaError> {
+    sqlx::query!(
+        r#"
+            INSERT INTO audit_log (actor_id, action, subject_id, details, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+        "#,
+        actor_id,
+        action,
+        subject_id,
+        details
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
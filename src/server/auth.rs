@@ -0,0 +1,388 @@
+use crate::{
+    constants::{SESSION_COOKIE, SESSION_DAYS},
+    model::{ssr::AppState, ApiToken, ApiTokenCreateArgs, ApiTokenCreated, LambdaError, TokenScope, User},
+    server::telemetry,
+};
+use axum::{
+    extract::FromRequestParts,
+    http::{
+        header::{AUTHORIZATION, COOKIE},
+        request::Parts,
+        HeaderMap,
+    },
+};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{Duration, Local};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use tracing::{field::Empty, instrument};
+use validator::Validate;
+
+const TOKEN_PREFIX: &str = "lf_";
+
+/// Browsers that aren't signed in carry no credentials and act as the
+/// fixture user.
+pub const SITE_USER_ID: i32 = 1;
+
+/// The user a request acts on behalf of, and what it may do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Principal {
+    pub user_id: i32,
+    pub scopes: Vec<TokenScope>,
+}
+
+impl Principal {
+    pub fn site() -> Self {
+        Principal {
+            user_id: SITE_USER_ID,
+            scopes: TokenScope::ALL.to_vec(),
+        }
+    }
+
+    /// A user signed in to a browser, who may do anything there.
+    pub fn signed_in(user_id: i32) -> Self {
+        Principal {
+            user_id,
+            scopes: TokenScope::ALL.to_vec(),
+        }
+    }
+
+    pub fn require(&self, scope: TokenScope) -> Result<i32, LambdaError> {
+        if self.scopes.contains(&scope) {
+            Ok(self.user_id)
+        } else {
+            Err(LambdaError::Forbidden(scope.as_str().to_string()))
+        }
+    }
+}
+
+/// Credentials presented with a request: the principal of a bearer token,
+/// `None` when there is none and an error when it isn't valid, and for
+/// server functions the user signed in to the browser.
+#[derive(Clone, Debug, Default)]
+pub struct Bearer {
+    pub token: Option<Principal>,
+    pub session: Option<i32>,
+}
+
+impl Bearer {
+    /// Anonymous requests may read; tokens must carry the `read` scope.
+    pub fn allow_read(&self) -> Result<(), LambdaError> {
+        match &self.token {
+            Some(principal) => principal.require(TokenScope::Read).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn require(&self, scope: TokenScope) -> Result<i32, LambdaError> {
+        self.token.as_ref().ok_or(LambdaError::AuthError)?.require(scope)
+    }
+
+    /// Who a server function acts for: the token's owner, the signed-in
+    /// user, or for anonymous browsers the site user.
+    pub fn principal(&self) -> Principal {
+        match (&self.token, self.session) {
+            (Some(principal), _) => principal.clone(),
+            (None, Some(user_id)) => Principal::signed_in(user_id),
+            (None, None) => Principal::site(),
+        }
+    }
+
+    /// A named user acting for themselves: a token's holder with `scope`, or
+    /// the user signed in to the browser, who may do anything there.
+    /// Anonymous browsers all share the site user, so they have no inbox.
+    pub fn member(&self, scope: TokenScope) -> Result<i32, LambdaError> {
+        match (&self.token, self.session) {
+            (Some(principal), _) => principal.require(scope),
+            (None, Some(user_id)) => Ok(user_id),
+            (None, None) => Err(LambdaError::AuthError),
+        }
+    }
+
+    /// The user whose private state, such as drafts, the caller may keep on
+    /// the server. Browsers keep theirs locally, so only tokens have one.
+    pub fn owner(&self) -> Option<i32> {
+        self.token.as_ref().map(|principal| principal.user_id)
+    }
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for Bearer {
+    type Rejection = LambdaError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        Ok(Bearer {
+            token: authenticate(&state.pool, &parts.headers).await?,
+            session: None,
+        })
+    }
+}
+
+/// Resolves an `Authorization: Bearer` header to its principal, recording the
+/// token as used. Returns `None` when no bearer token is present.
+pub async fn authenticate(pool: &PgPool, headers: &HeaderMap) -> Result<Option<Principal>, LambdaError> {
+    let Some(header) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let secret = header
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(LambdaError::AuthError)?;
+
+    token_principal(pool, secret).await.map(Some)
+}
+
+/// Resolves a token secret to its principal, recording the token as used,
+/// e.g. one pasted into a form by a browser. Tokens of the shared site user
+/// don't resolve: any browser could once mint them.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn token_principal(pool: &PgPool, secret: &str) -> Result<Principal, LambdaError> {
+    let token = sqlx::query!(
+        r#"
+            UPDATE api_tokens t
+            SET last_used_at = NOW()
+            FROM users u
+            WHERE t.token_hash = $1 AND t.revoked_at IS NULL
+                AND u.id = t.user_id AND u.id <> $2 AND u.banned_at IS NULL
+            RETURNING t.user_id, t.scopes
+        "#,
+        hash(secret.trim()),
+        SITE_USER_ID
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(LambdaError::AuthError)?;
+    telemetry::record_rows(1);
+
+    Ok(Principal {
+        user_id: token.user_id,
+        scopes: parse_scopes(&token.scopes),
+    })
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn token_create(
+    pool: &PgPool,
+    user_id: i32,
+    args: ApiTokenCreateArgs,
+) -> Result<ApiTokenCreated, LambdaError> {
+    args.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+    if user_id == SITE_USER_ID {
+        return Err(LambdaError::ValidationError("the site account cannot hold tokens".into()));
+    }
+    let scopes = args.scopes();
+    if scopes.is_empty() {
+        return Err(LambdaError::ValidationError("at least one scope is required".into()));
+    }
+
+    let secret = secret();
+    let scope_names: Vec<String> = scopes.iter().map(|scope| scope.as_str().to_string()).collect();
+
+    let row = sqlx::query!(
+        r#"
+            INSERT INTO api_tokens (user_id, name, token_hash, scopes, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, created_at
+        "#,
+        user_id,
+        args.name,
+        hash(&secret),
+        &scope_names,
+        Local::now().into()
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(ApiTokenCreated {
+        token: ApiToken {
+            id: row.id,
+            name: args.name,
+            scopes,
+            created_at: row.created_at.into(),
+            last_used_at: None,
+        },
+        secret,
+    })
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn token_list(pool: &PgPool, user_id: i32) -> Result<Vec<ApiToken>, LambdaError> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT id, name, scopes, created_at, last_used_at
+            FROM api_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ApiToken {
+            id: row.id,
+            name: row.name,
+            scopes: parse_scopes(&row.scopes),
+            created_at: row.created_at.into(),
+            last_used_at: row.last_used_at.map(Into::into),
+        })
+        .collect())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn token_revoke(pool: &PgPool, user_id: i32, id: i32) -> Result<(), LambdaError> {
+    let result = sqlx::query!(
+        r#"UPDATE api_tokens SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"#,
+        id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    if result.rows_affected() == 0 {
+        return Err(LambdaError::NotFound);
+    }
+    Ok(())
+}
+
+/// Checks a user's password for signing in. The shared site user, banned
+/// users and users without a password can't sign in.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn password_login(pool: &PgPool, name: &str, password: &str) -> Result<i32, LambdaError> {
+    let user = sqlx::query!(
+        "SELECT id, password_hash FROM users WHERE display_name = $1 AND banned_at IS NULL",
+        name.trim()
+    )
+    .fetch_optional(pool)
+    .await?;
+    telemetry::record_rows(user.iter().len());
+
+    match user {
+        Some(user)
+            if user.id != SITE_USER_ID
+                && user.password_hash.as_deref().is_some_and(|hash| password_verify(password, hash)) =>
+        {
+            Ok(user.id)
+        }
+        _ => Err(LambdaError::AuthError),
+    }
+}
+
+/// Starts a browser session for a signed-in user, returning the secret for
+/// its cookie. The user's expired sessions are cleared on the way.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn session_create(pool: &PgPool, user_id: i32) -> Result<String, LambdaError> {
+    sqlx::query!("DELETE FROM sessions WHERE user_id = $1 AND expires_at <= NOW()", user_id)
+        .execute(pool)
+        .await?;
+    let secret = secret();
+    sqlx::query!(
+        "INSERT INTO sessions (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        user_id,
+        hash(&secret),
+        (Local::now() + Duration::days(SESSION_DAYS)).into()
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(secret)
+}
+
+/// The user signed in with a session cookie's secret, unless the session
+/// expired or the user was banned since.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn session_user(pool: &PgPool, secret: &str) -> Result<User, LambdaError> {
+    let user = sqlx::query_as!(
+        User,
+        r#"
+            SELECT u.id, u.display_name as username, u.created_at
+            FROM sessions s
+            JOIN users u ON u.id = s.user_id
+            WHERE s.token_hash = $1 AND s.expires_at > NOW() AND u.banned_at IS NULL
+        "#,
+        hash(secret)
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(LambdaError::AuthError)?;
+    telemetry::record_rows(1);
+
+    Ok(user)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn session_delete(pool: &PgPool, secret: &str) -> Result<(), LambdaError> {
+    let result = sqlx::query!("DELETE FROM sessions WHERE token_hash = $1", hash(secret))
+        .execute(pool)
+        .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(())
+}
+
+/// The session secret among a request's cookies, if any.
+pub fn session_secret(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+        .filter(|secret| !secret.is_empty())
+}
+
+/// A `Set-Cookie` value keeping a session's secret in the browser, or with
+/// `None` removing it. `Lax` keeps the cookie off cross-site posts, which
+/// is how server functions are called.
+pub fn session_cookie(secret: Option<&str>, secure: bool) -> String {
+    let (value, max_age) = match secret {
+        Some(secret) => (secret, Duration::days(SESSION_DAYS).num_seconds()),
+        None => ("", 0),
+    };
+    let secure = if secure { "; Secure" } else { "" };
+    format!("{SESSION_COOKIE}={value}; Path=/; Max-Age={max_age}; HttpOnly; SameSite=Lax{secure}")
+}
+
+/// Hashes a password into a PHC string with Argon2id and a random salt.
+pub fn password_hash(password: &str) -> Result<String, LambdaError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| LambdaError::InvalidData(err.to_string()))
+}
+
+pub fn password_verify(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+fn parse_scopes(scopes: &[String]) -> Vec<TokenScope> {
+    scopes.iter().filter_map(|scope| scope.parse().ok()).collect()
+}
+
+/// A fresh token or session secret.
+fn secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{TOKEN_PREFIX}{}", hex(&bytes))
+}
+
+fn hash(secret: &str) -> String {
+    hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
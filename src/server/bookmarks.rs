@@ -0,0 +1,291 @@
+use crate::{
+    constants::{FAVORITES, PROFILE, STORY},
+    model::{ssr::AppState, Bookmark, BookmarkArgs, Favorites, LambdaError},
+    server::{
+        auth::Bearer,
+        telemetry,
+    },
+};
+use atom_syndication::{Content, Entry, Feed, Link, Person, Text};
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::Local;
+use sqlx::PgPool;
+use tracing::{field::Empty, instrument};
+use validator::Validate;
+
+/// Saves a story, or one of its comments, replacing the note of an existing
+/// bookmark. Blank notes are stored as none.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn bookmark_set(pool: &PgPool, user_id: i32, args: BookmarkArgs) -> Result<Bookmark, LambdaError> {
+    args.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+    let note = args.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+
+    let id = sqlx::query_scalar!(
+        r#"
+            INSERT INTO bookmarks (user_id, story_id, comment_id, note, created_at)
+            SELECT $1, s.id, c.id, $4, $5
+            FROM stories s
+            LEFT JOIN comments c ON c.id = $3 AND c.story_id = s.id
+            WHERE s.id = $2 AND ($3::integer IS NULL OR c.id IS NOT NULL)
+            ON CONFLICT (user_id, story_id, comment_id) DO UPDATE SET note = EXCLUDED.note
+            RETURNING id
+        "#,
+        user_id,
+        args.story_id,
+        args.comment_id,
+        note,
+        Local::now().into()
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    let mut bookmarks = bookmark_list(pool, user_id, Some(id)).await?;
+    bookmarks.pop().ok_or(LambdaError::NotFound)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn bookmark_remove(
+    pool: &PgPool,
+    user_id: i32,
+    story_id: i32,
+    comment_id: Option<i32>,
+) -> Result<(), LambdaError> {
+    let result = sqlx::query!(
+        r#"
+            DELETE FROM bookmarks
+            WHERE user_id = $1 AND story_id = $2 AND comment_id IS NOT DISTINCT FROM $3
+        "#,
+        user_id,
+        story_id,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    if result.rows_affected() == 0 {
+        return Err(LambdaError::NotFound);
+    }
+    Ok(())
+}
+
+/// A user's bookmarks, newest first, or just the one with `id`.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn bookmark_list(pool: &PgPool, user_id: i32, id: Option<i32>) -> Result<Vec<Bookmark>, LambdaError> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                b.id,
+                b.story_id,
+                s.title as story_title,
+                s.url as story_url,
+                b.comment_id,
+                LEFT(c.text, 200) as excerpt,
+                b.note,
+                b.created_at
+            FROM bookmarks b
+            JOIN stories s ON s.id = b.story_id
+            LEFT JOIN comments c ON c.id = b.comment_id
+            WHERE b.user_id = $1 AND ($2::integer IS NULL OR b.id = $2)
+            ORDER BY b.created_at DESC, b.id DESC
+        "#,
+        user_id,
+        id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Bookmark {
+            id: row.id,
+            story_id: row.story_id,
+            story_title: row.story_title,
+            story_url: row.story_url,
+            comment_id: row.comment_id,
+            excerpt: row.excerpt,
+            note: row.note,
+            created_at: row.created_at.into(),
+        })
+        .collect())
+}
+
+/// A bookmark of the story, or of one of its comments, saved now and without
+/// a note, for a browser to keep to itself; its `id` is 0.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn bookmark_target(pool: &PgPool, story_id: i32, comment_id: Option<i32>) -> Result<Bookmark, LambdaError> {
+    let row = sqlx::query!(
+        r#"
+            SELECT
+                s.id as story_id,
+                s.title as story_title,
+                s.url as story_url,
+                c.id as "comment_id?",
+                LEFT(c.text, 200) as excerpt
+            FROM stories s
+            LEFT JOIN comments c ON c.id = $2 AND c.story_id = s.id
+            WHERE s.id = $1 AND ($2::integer IS NULL OR c.id IS NOT NULL)
+        "#,
+        story_id,
+        comment_id
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(Bookmark {
+        id: 0,
+        story_id: row.story_id,
+        story_title: row.story_title,
+        story_url: row.story_url,
+        comment_id: row.comment_id,
+        excerpt: row.excerpt,
+        note: None,
+        created_at: Local::now().into(),
+    })
+}
+
+/// What the user bookmarked, as story and comment ids.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn bookmark_keys(pool: &PgPool, user_id: i32) -> Result<Vec<(i32, Option<i32>)>, LambdaError> {
+    let rows = sqlx::query!("SELECT story_id, comment_id FROM bookmarks WHERE user_id = $1", user_id)
+        .fetch_all(pool)
+        .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows.into_iter().map(|row| (row.story_id, row.comment_id)).collect())
+}
+
+/// `name`'s collection, or the viewer's own without a name, as `viewer_id`
+/// may see it. Private collections are not found for anyone but their owner,
+/// and notes are left out for others; anonymous viewers own nothing.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn favorites(pool: &PgPool, viewer_id: Option<i32>, name: Option<&str>) -> Result<Favorites, LambdaError> {
+    let user = sqlx::query!(
+        r#"
+            SELECT id, display_name, favorites_public
+            FROM users
+            WHERE display_name = $1 OR ($1 IS NULL AND id = $2)
+        "#,
+        name,
+        viewer_id
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    let owned = Some(user.id) == viewer_id;
+    if !user.favorites_public && !owned {
+        return Err(LambdaError::NotFound);
+    }
+
+    let mut bookmarks = bookmark_list(pool, user.id, None).await?;
+    if !owned {
+        bookmarks.iter_mut().for_each(|bookmark| bookmark.note = None);
+    }
+
+    Ok(Favorites {
+        user_name: user.display_name,
+        public: user.favorites_public,
+        owned,
+        bookmarks,
+    })
+}
+
+pub async fn favorites_set_public(pool: &PgPool, user_id: i32, public: bool) -> Result<(), LambdaError> {
+    sqlx::query!("UPDATE users SET favorites_public = $2 WHERE id = $1", user_id, public)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Exports of a collection next to its page, e.g. `/closure/ada/favorites.atom`,
+/// visible to the same people as the page.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(&format!("/{PROFILE}/:name/{FAVORITES}.json"), get(export_json))
+        .route(&format!("/{PROFILE}/:name/{FAVORITES}.atom"), get(export_atom))
+}
+
+/// Tokens act as their owner. Exports are plain links anyone can follow, so
+/// requests without a token only see public collections, without notes.
+fn viewer(bearer: &Bearer) -> Result<Option<i32>, LambdaError> {
+    bearer.allow_read()?;
+    Ok(bearer.owner())
+}
+
+async fn export_json(State(state): State<AppState>, bearer: Bearer, Path(name): Path<String>) -> Result<Response, LambdaError> {
+    let favorites = favorites(&state.pool, viewer(&bearer)?, Some(&name)).await?;
+    let disposition = format!("attachment; filename=\"{FAVORITES}.json\"");
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(favorites)).into_response())
+}
+
+async fn export_atom(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(name): Path<String>,
+) -> Result<Response, LambdaError> {
+    let favorites = favorites(&state.pool, viewer(&bearer)?, Some(&name)).await?;
+    let feed = favorites_feed(&favorites, &state.site.name, state.site.origin());
+
+    Ok(([(header::CONTENT_TYPE, "application/atom+xml")], feed.to_string()).into_response())
+}
+
+pub fn favorites_feed(favorites: &Favorites, site_name: &str, origin: &str) -> Feed {
+    let page = format!("{origin}/{PROFILE}/{}/{FAVORITES}", favorites.user_name);
+    let link = |href: String, rel: &str| Link {
+        href,
+        rel: rel.to_string(),
+        ..Link::default()
+    };
+
+    let entries: Vec<Entry> = favorites
+        .bookmarks
+        .iter()
+        .map(|bookmark| {
+            let story_page = format!("{origin}/{STORY}/{}", bookmark.story_id);
+            let mut links = vec![link(story_page, "alternate")];
+            links.extend(bookmark.story_url.clone().map(|url| link(url, "related")));
+
+            let mut entry = Entry::default();
+            entry.set_id(format!("{page}#{}", bookmark.id));
+            entry.set_title(match bookmark.comment_id {
+                Some(_) => format!("Comment on {}", bookmark.story_title),
+                None => bookmark.story_title.clone(),
+            });
+            entry.set_updated(bookmark.created_at);
+            entry.set_links(links);
+            entry.set_summary(bookmark.excerpt.clone().map(Text::plain));
+            entry.set_content(bookmark.note.clone().map(|note| Content {
+                value: Some(note),
+                content_type: Some("text".into()),
+                ..Content::default()
+            }));
+            entry
+        })
+        .collect();
+
+    let mut feed = Feed::default();
+    feed.set_id(page.clone());
+    feed.set_title(format!("{}'s favorites on {site_name}", favorites.user_name));
+    feed.set_authors(vec![Person {
+        name: favorites.user_name.clone(),
+        ..Person::default()
+    }]);
+    feed.set_links(vec![link(format!("{page}.atom"), "self"), link(page, "alternate")]);
+    if let Some(latest) = favorites.bookmarks.first() {
+        feed.set_updated(latest.created_at);
+    }
+    feed.set_entries(entries);
+    feed
+}
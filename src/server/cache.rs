@@ -0,0 +1,162 @@
+use crate::{
+    model::{LambdaError, StoryFilter, StoryListItem},
+    server::{config::CacheConfig, repo::StoryRepo},
+};
+use redis::{aio::ConnectionManager, AsyncCommands, RedisError};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Listing keys embed a generation number, so invalidation is a single
+/// `INCR` and stale pages simply expire.
+const GENERATION_KEY: &str = "news:stories:generation";
+/// Pages kept by the in-process store. Page numbers come from requests, so
+/// without a bound anyone could grow it without end.
+const MEMORY_MAX_PAGES: usize = 256;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+    #[default]
+    None,
+    Memory,
+    Valkey,
+}
+
+type Pages = HashMap<i64, (Instant, Vec<StoryListItem>)>;
+
+#[derive(Clone)]
+enum Store {
+    Memory(Arc<Mutex<Pages>>),
+    Valkey(ConnectionManager),
+}
+
+/// Caches story listing pages, the most requested query, for a short time.
+/// Ratings and comment counts may lag by up to the TTL; new stories clear it.
+/// Cache failures are logged and fall through to the database.
+#[derive(Clone, Default)]
+pub struct StoryCache {
+    ttl: Duration,
+    store: Option<Store>,
+}
+
+impl fmt::Debug for StoryCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backend = match self.store {
+            None => CacheBackend::None,
+            Some(Store::Memory(_)) => CacheBackend::Memory,
+            Some(Store::Valkey(_)) => CacheBackend::Valkey,
+        };
+        f.debug_struct("StoryCache").field("backend", &backend).field("ttl", &self.ttl).finish()
+    }
+}
+
+impl StoryCache {
+    pub async fn connect(config: &CacheConfig) -> Result<Self, RedisError> {
+        let store = match config.backend {
+            CacheBackend::None => None,
+            CacheBackend::Memory => Some(Store::Memory(Arc::default())),
+            CacheBackend::Valkey => {
+                let client = redis::Client::open(config.url.as_str())?;
+                Some(Store::Valkey(ConnectionManager::new(client).await?))
+            }
+        };
+        Ok(StoryCache {
+            ttl: Duration::from_secs(config.ttl_secs),
+            store,
+        })
+    }
+
+    /// A page of the front page. Only unfiltered pages are shared, so
+    /// filtered ones go straight to the repository.
+    pub async fn story_page(
+        &self,
+        repo: &dyn StoryRepo,
+        page: i64,
+        filter: &StoryFilter,
+    ) -> Result<Vec<StoryListItem>, LambdaError> {
+        if !filter.is_empty() {
+            return repo.story_page(page, filter).await;
+        }
+        match &self.store {
+            None => repo.story_page(page, filter).await,
+            Some(Store::Memory(pages)) => {
+                if let Some((cached_at, stories)) = pages.lock().unwrap().get(&page) {
+                    if cached_at.elapsed() < self.ttl {
+                        return Ok(stories.clone());
+                    }
+                }
+                let stories = repo.story_page(page, filter).await?;
+                let mut pages = pages.lock().unwrap();
+                if pages.len() >= MEMORY_MAX_PAGES {
+                    pages.retain(|_, (cached_at, _)| cached_at.elapsed() < self.ttl);
+                }
+                if pages.len() >= MEMORY_MAX_PAGES {
+                    let oldest = pages.iter().min_by_key(|(_, (cached_at, _))| *cached_at).map(|(page, _)| *page);
+                    if let Some(oldest) = oldest {
+                        pages.remove(&oldest);
+                    }
+                }
+                pages.insert(page, (Instant::now(), stories.clone()));
+                Ok(stories)
+            }
+            Some(Store::Valkey(connection)) => {
+                let mut connection = connection.clone();
+                let generation = connection
+                    .get::<_, Option<u64>>(GENERATION_KEY)
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or(0);
+                let key = format!("news:stories:{generation}:{page}");
+                match connection.get::<_, Option<String>>(&key).await {
+                    Ok(Some(cached)) => {
+                        if let Ok(stories) = serde_json::from_str(&cached) {
+                            return Ok(stories);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => leptos::logging::error!("Story cache read failed: {err}"),
+                }
+                let stories = repo.story_page(page, filter).await?;
+                if let Ok(value) = serde_json::to_string(&stories) {
+                    if let Err(err) = connection.set_ex::<_, _, ()>(&key, value, self.ttl.as_secs()).await {
+                        leptos::logging::error!("Story cache write failed: {err}");
+                    }
+                }
+                Ok(stories)
+            }
+        }
+    }
+
+    /// Checks that the backend answers; always succeeds for in-process ones.
+    pub async fn ping(&self) -> Result<(), String> {
+        match &self.store {
+            Some(Store::Valkey(connection)) => {
+                let mut connection = connection.clone();
+                redis::cmd("PING")
+                    .query_async::<()>(&mut connection)
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn invalidate(&self) {
+        match &self.store {
+            None => {}
+            Some(Store::Memory(pages)) => pages.lock().unwrap().clear(),
+            Some(Store::Valkey(connection)) => {
+                let mut connection = connection.clone();
+                if let Err(err) = connection.incr::<_, _, ()>(GENERATION_KEY, 1).await {
+                    leptos::logging::error!("Story cache invalidation failed: {err}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{model::SiteSettings, server::cache::CacheBackend};
+use figment::{
+    providers::{Env, Format, Serialized, Toml},
+    Figment,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::{
+    env,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use thiserror::Error;
+use url::Url;
+
+/// Names an explicit configuration file. Without it, `lambda.toml` in the
+/// working directory is read when present.
+pub const CONFIG_VAR: &str = "LAMBDA_CONFIG";
+/// Prefix of environment overrides, nested with `__`, e.g.
+/// `LAMBDA_DATABASE__MAX_CONNECTIONS=20`.
+pub const ENV_PREFIX: &str = "LAMBDA_";
+
+/// Top-level keys of [`Config`]. Other `LAMBDA_` variables belong to
+/// someone else and are left alone.
+const SECTIONS: [&str; 5] = ["database", "site", "cache", "rate_limit", "features"];
+const DEFAULT_CONFIG_FILE: &str = "lambda.toml";
+const DATABASE_URL_VAR: &str = "DATABASE_URL";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("configuration file {} does not exist", .0.display())]
+    MissingFile(PathBuf),
+    #[error(transparent)]
+    Load(#[from] Box<figment::Error>),
+    #[error("{key}: {message}")]
+    Invalid { key: &'static str, message: String },
+}
+
+impl ConfigError {
+    fn invalid(key: &'static str, message: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            key,
+            message: message.into(),
+        }
+    }
+}
+
+/// Runtime configuration, layered from defaults, an optional TOML file and the
+/// environment, in increasing order of precedence.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub site: SiteSettings,
+    pub cache: CacheConfig,
+    pub rate_limit: RateLimitConfig,
+    pub features: FeatureToggles,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// Also read from `DATABASE_URL`, so the value sqlx uses at build time
+    /// works at runtime too.
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    pub async fn connect(&self) -> Result<PgPool, sqlx::Error> {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_secs))
+            .connect(&self.url)
+            .await
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub backend: CacheBackend,
+    /// Connection URL for the `valkey` backend, e.g. `redis://valkey:6379`.
+    pub url: String,
+    pub ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            backend: CacheBackend::None,
+            url: String::new(),
+            ttl_secs: 10,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Writes allowed per client address each minute; zero disables the limit.
+    pub writes_per_minute: u32,
+    /// Reverse proxies whose `X-Forwarded-For` names the client. Empty, the
+    /// default, limits by the connecting address alone.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            writes_per_minute: 60,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureToggles {
+    pub link_previews: bool,
+    pub webhooks: bool,
+    pub rest_api: bool,
+    pub metrics: bool,
+    pub live_updates: bool,
+}
+
+impl Default for FeatureToggles {
+    fn default() -> Self {
+        FeatureToggles {
+            link_previews: true,
+            webhooks: true,
+            rest_api: true,
+            metrics: true,
+            live_updates: true,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the file named by [`CONFIG_VAR`], or
+    /// `lambda.toml` when present, and the environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let file = match env::var_os(CONFIG_VAR) {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                if !path.is_file() {
+                    return Err(ConfigError::MissingFile(path));
+                }
+                Some(path)
+            }
+            None => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.is_file()),
+        };
+        Self::from_figment(Self::figment(file.as_deref()))
+    }
+
+    pub fn figment(file: Option<&Path>) -> Figment {
+        let mut figment = Figment::from(Serialized::defaults(Config::default()));
+        if let Some(file) = file {
+            figment = figment.merge(Toml::file_exact(file));
+        }
+        figment
+            .merge(Env::raw().only(&[DATABASE_URL_VAR]).map(|_| "database.url".into()))
+            .merge(
+                Env::prefixed(ENV_PREFIX)
+                    .split("__")
+                    .filter(|key| {
+                        let section = key.as_str().split('.').next().unwrap_or_default();
+                        SECTIONS.iter().any(|known| known.eq_ignore_ascii_case(section))
+                    }),
+            )
+    }
+
+    pub fn from_figment(figment: Figment) -> Result<Self, ConfigError> {
+        let config: Config = figment.extract().map_err(Box::new)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let database = &self.database;
+        if database.url.is_empty() {
+            return Err(ConfigError::invalid(
+                "database.url",
+                format!("missing, set {DATABASE_URL_VAR} or {ENV_PREFIX}DATABASE__URL"),
+            ));
+        }
+        let url = Url::parse(&database.url).map_err(|err| ConfigError::invalid("database.url", err.to_string()))?;
+        if !matches!(url.scheme(), "postgres" | "postgresql") {
+            return Err(ConfigError::invalid(
+                "database.url",
+                format!("expected a postgres:// URL, found {}://", url.scheme()),
+            ));
+        }
+        if database.max_connections == 0 {
+            return Err(ConfigError::invalid("database.max_connections", "must be at least 1"));
+        }
+        if database.min_connections > database.max_connections {
+            return Err(ConfigError::invalid(
+                "database.min_connections",
+                format!("{} exceeds max_connections {}", database.min_connections, database.max_connections),
+            ));
+        }
+        if database.acquire_timeout_secs == 0 {
+            return Err(ConfigError::invalid("database.acquire_timeout_secs", "must be at least 1"));
+        }
+        if self.site.name.trim().is_empty() {
+            return Err(ConfigError::invalid("site.name", "must not be empty"));
+        }
+        let url = Url::parse(&self.site.url).map_err(|err| ConfigError::invalid("site.url", err.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ConfigError::invalid(
+                "site.url",
+                format!("expected an http:// or https:// URL, found {}://", url.scheme()),
+            ));
+        }
+        if self.cache.backend != CacheBackend::None && self.cache.ttl_secs == 0 {
+            return Err(ConfigError::invalid("cache.ttl_secs", "must be at least 1 when caching"));
+        }
+        if self.cache.backend == CacheBackend::Valkey {
+            let url = Url::parse(&self.cache.url).map_err(|err| ConfigError::invalid("cache.url", err.to_string()))?;
+            if !matches!(url.scheme(), "redis" | "rediss") {
+                return Err(ConfigError::invalid(
+                    "cache.url",
+                    format!("expected a redis:// URL, found {}://", url.scheme()),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    model::{Draft, DraftArgs, LambdaError, Profile},
+    server::telemetry,
+};
+use sqlx::PgPool;
+use tracing::{field::Empty, instrument};
+use validator::Validate;
+
+/// Keeps the latest state of an editor, replacing what it saved before. A
+/// blank draft is discarded instead, as is one on a story or comment that no
+/// longer exists.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn draft_save(pool: &PgPool, user_id: i32, draft: DraftArgs) -> Result<(), LambdaError> {
+    draft.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+    if draft.is_blank() {
+        return draft_discard(pool, user_id, draft.story_id, draft.parent_id).await;
+    }
+
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO drafts (user_id, story_id, parent_id, title, url, text)
+            SELECT $1, $2, $3, $4, $5, $6
+            WHERE ($2::integer IS NULL OR EXISTS (SELECT 1 FROM stories WHERE id = $2))
+                AND ($3::integer IS NULL OR EXISTS (SELECT 1 FROM comments WHERE id = $3 AND story_id = $2))
+            ON CONFLICT (user_id, story_id, parent_id) DO UPDATE
+            SET title = EXCLUDED.title, url = EXCLUDED.url, text = EXCLUDED.text, updated_at = NOW()
+        "#,
+        user_id,
+        draft.story_id,
+        draft.parent_id,
+        draft.title,
+        draft.url,
+        draft.text
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    if result.rows_affected() == 0 {
+        return Err(LambdaError::NotFound);
+    }
+    Ok(())
+}
+
+/// Forgets a draft, as posting it does. There being none is fine.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn draft_discard(
+    pool: &PgPool,
+    user_id: i32,
+    story_id: Option<i32>,
+    parent_id: Option<i32>,
+) -> Result<(), LambdaError> {
+    let result = sqlx::query!(
+        r#"
+            DELETE FROM drafts
+            WHERE user_id = $1 AND story_id IS NOT DISTINCT FROM $2 AND parent_id IS NOT DISTINCT FROM $3
+        "#,
+        user_id,
+        story_id,
+        parent_id
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(())
+}
+
+/// A user's drafts, most recently saved first, or just the one for an editor.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn draft_list(
+    pool: &PgPool,
+    user_id: i32,
+    editor: Option<(Option<i32>, Option<i32>)>,
+) -> Result<Vec<Draft>, LambdaError> {
+    let (story_id, parent_id) = editor.unwrap_or_default();
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                d.story_id,
+                s.title as "story_title?",
+                d.parent_id,
+                d.title,
+                d.url,
+                d.text,
+                d.updated_at
+            FROM drafts d
+            LEFT JOIN stories s ON s.id = d.story_id
+            WHERE d.user_id = $1
+                AND (NOT $2 OR (d.story_id IS NOT DISTINCT FROM $3 AND d.parent_id IS NOT DISTINCT FROM $4))
+            ORDER BY d.updated_at DESC
+        "#,
+        user_id,
+        editor.is_some(),
+        story_id,
+        parent_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Draft {
+            story_id: row.story_id,
+            story_title: row.story_title,
+            parent_id: row.parent_id,
+            title: row.title,
+            url: row.url,
+            text: row.text,
+            updated_at: row.updated_at.into(),
+        })
+        .collect())
+}
+
+/// The named user's page, or the viewer's own without a name. Drafts are
+/// only ever shown to their author, and only when `with_drafts`: anonymous
+/// viewers share the site user and keep their drafts in the browser.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn profile(
+    pool: &PgPool,
+    viewer_id: i32,
+    with_drafts: bool,
+    name: Option<&str>,
+) -> Result<Profile, LambdaError> {
+    let user = sqlx::query!(
+        r#"
+            SELECT id, display_name, created_at, favorites_public
+            FROM users
+            WHERE display_name = $1 OR ($1 IS NULL AND id = $2)
+        "#,
+        name,
+        viewer_id
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    let owned = user.id == viewer_id;
+    let drafts = match owned && with_drafts {
+        true => draft_list(pool, user.id, None).await?,
+        false => Vec::new(),
+    };
+
+    Ok(Profile {
+        user_name: user.display_name,
+        created_at: user.created_at.into(),
+        favorites_public: user.favorites_public,
+        owned,
+        drafts,
+    })
+}
@@ -0,0 +1,116 @@
+use crate::{constants::READINESS_TIMEOUT_SECS, model::ssr::AppState};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::{sync::watch, time::timeout};
+
+pub const LIVENESS_PATH: &str = "/healthz";
+pub const READINESS_PATH: &str = "/readyz";
+
+/// Shared shutdown flag. Once triggered, readiness fails so the load balancer
+/// stops routing here, and background workers finish their current batch and
+/// return.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            sender: Arc::new(watch::channel(false).0),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolves once [`Shutdown::trigger`] has been called.
+    pub async fn triggered(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Triggers shutdown on SIGTERM or Ctrl-C.
+    pub async fn listen(self) {
+        let ctrl_c = async {
+            let _ = tokio::signal::ctrl_c().await;
+        };
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(_) => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => {},
+            _ = terminate => {},
+        }
+        leptos::logging::log!("Shutting down, draining in-flight requests");
+        self.trigger();
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Readiness {
+    database: String,
+    cache: String,
+    draining: bool,
+}
+
+/// Liveness only proves the process is serving requests.
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// A dependency check that fails rather than wait past the probe's patience,
+/// e.g. while every pooled connection is busy.
+async fn probe(check: impl Future<Output = Result<(), String>>) -> Result<(), String> {
+    let limit = Duration::from_secs(READINESS_TIMEOUT_SECS);
+    timeout(limit, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}s", limit.as_secs())))
+}
+
+/// Ready when the database and cache answer and shutdown has not begun.
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let database = probe(async {
+        sqlx::query("SELECT 1")
+            .execute(&state.pool)
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    })
+    .await;
+    let cache = probe(state.story_cache.ping()).await;
+    let draining = state.shutdown.is_triggered();
+
+    let status = if database.is_ok() && cache.is_ok() && !draining {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let describe = |result: Result<(), String>| result.map_or_else(|err| err, |_| "ok".to_string());
+
+    (
+        status,
+        Json(Readiness {
+            database: describe(database),
+            cache: describe(cache),
+            draining,
+        }),
+    )
+}
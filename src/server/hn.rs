@@ -0,0 +1,427 @@
+use crate::{
+    features::{canonical::canonicalize_url, ui::markdown::render_markdown},
+    model::{LambdaError, StoryKind},
+    server::admin::{counts_rebuild, PROGRESS_EVERY},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::{collections::HashMap, io::Read};
+
+/// One value of a Hacker News dump: an item, a user, or an array of either.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum HnRecord {
+    Item(HnItem),
+    User(HnUser),
+    Many(Vec<HnRecord>),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HnKind {
+    Story,
+    Job,
+    Poll,
+    Comment,
+    #[serde(other)]
+    Other,
+}
+
+/// An item as served by the Hacker News API. `kids` may hold ids or, in
+/// some dumps, the nested child items themselves.
+#[derive(Deserialize, Debug, Clone)]
+pub struct HnItem {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: HnKind,
+    pub by: Option<String>,
+    pub time: Option<i64>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub text: Option<String>,
+    pub parent: Option<i64>,
+    #[serde(default)]
+    pub kids: Vec<HnKid>,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub dead: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum HnKid {
+    Id(i64),
+    Item(Box<HnItem>),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct HnUser {
+    pub id: String,
+    pub created: i64,
+}
+
+/// What an [`import`] did. `existing` counts items mapped by an earlier run,
+/// `skipped` deleted, dead or unsupported items and replies whose parent
+/// never appeared.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HnImport {
+    pub users: u64,
+    pub stories: u64,
+    pub comments: u64,
+    pub existing: u64,
+    pub skipped: u64,
+}
+
+/// Local rows an item was imported as.
+#[derive(Clone, Copy)]
+struct Mapped {
+    story_id: i32,
+    comment_id: Option<i32>,
+}
+
+/// Hacker News marks asks and shows only by their title prefix. Polls come
+/// without their options and jobs may have no link, so both import as asks,
+/// which need neither.
+pub fn hn_story_kind(item: &HnItem) -> StoryKind {
+    let title = item.title.as_deref().unwrap_or_default();
+    match item.kind {
+        HnKind::Job if item.url.is_some() => StoryKind::Job,
+        HnKind::Job | HnKind::Poll => StoryKind::Ask,
+        _ if title.starts_with("Show HN") => StoryKind::Show,
+        _ if title.starts_with("Ask HN") || item.url.is_none() => StoryKind::Ask,
+        _ => StoryKind::Link,
+    }
+}
+
+fn timestamp(seconds: Option<i64>) -> DateTime<Utc> {
+    seconds.and_then(|seconds| DateTime::from_timestamp(seconds, 0)).unwrap_or_else(Utc::now)
+}
+
+/// Turns the HTML subset Hacker News uses in `text` into Markdown: paragraphs,
+/// italics, links as their full URL and preformatted blocks as indented code.
+pub fn hn_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    let mut link = false;
+    let mut pre: Option<String> = None;
+
+    while let Some(start) = rest.find('<') {
+        let text = &rest[..start];
+        match pre.as_mut() {
+            Some(block) => block.push_str(text),
+            None if !link => out.push_str(&decode_entities(text)),
+            None => {}
+        }
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let name = tag.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+        match name.as_str() {
+            "p" if pre.is_none() => paragraph(&mut out),
+            "i" | "/i" if pre.is_none() => out.push('*'),
+            "a" => {
+                if let Some(href) = attribute(tag, "href") {
+                    out.push_str(&decode_entities(href));
+                    link = true;
+                }
+            }
+            "/a" => link = false,
+            "pre" => pre = Some(String::new()),
+            "/pre" => {
+                let block = decode_entities(&pre.take().unwrap_or_default());
+                paragraph(&mut out);
+                for line in block.trim_matches('\n').lines() {
+                    out.push_str("    ");
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+    out.push_str(&decode_entities(rest));
+
+    out.trim().to_string()
+}
+
+/// Starts a new paragraph unless one was just started.
+fn paragraph(out: &mut String) {
+    let trimmed = out.trim_end_matches([' ', '\n']).len();
+    out.truncate(trimmed);
+    out.push_str("\n\n");
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{name}=\""))? + name.len() + 2;
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+struct Importer {
+    tx: Transaction<'static, Postgres>,
+    users: HashMap<String, i32>,
+    /// Replies waiting for their parent, keyed by the parent's id.
+    pending: HashMap<i64, Vec<HnItem>>,
+    summary: HnImport,
+}
+
+impl Importer {
+    async fn user(&mut self, name: &str, created_at: DateTime<Utc>) -> Result<i32, LambdaError> {
+        if let Some(&id) = self.users.get(name) {
+            return Ok(id);
+        }
+        let mapped = sqlx::query_scalar!("SELECT user_id FROM hn_users WHERE name = $1", name)
+            .fetch_optional(&mut *self.tx)
+            .await?;
+        let id = match mapped {
+            Some(id) => id,
+            None => {
+                // Never attach imported content to an existing local account.
+                let mut id = None;
+                for display_name in [name.to_string(), format!("{name}-hn")] {
+                    id = sqlx::query_scalar!(
+                        r#"
+                            INSERT INTO users (display_name, created_at)
+                            VALUES ($1, $2)
+                            ON CONFLICT (display_name) DO NOTHING
+                            RETURNING id
+                        "#,
+                        display_name,
+                        created_at
+                    )
+                    .fetch_optional(&mut *self.tx)
+                    .await?;
+                    if id.is_some() {
+                        break;
+                    }
+                }
+                let id = id.ok_or_else(|| LambdaError::ValidationError(format!("user name {name} is taken")))?;
+                sqlx::query!("INSERT INTO hn_users (name, user_id) VALUES ($1, $2)", name, id)
+                    .execute(&mut *self.tx)
+                    .await?;
+                self.summary.users += 1;
+                id
+            }
+        };
+        self.users.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    async fn mapped(&mut self, hn_id: i64) -> Result<Option<Mapped>, LambdaError> {
+        let row = sqlx::query!("SELECT story_id, comment_id FROM hn_items WHERE hn_id = $1", hn_id)
+            .fetch_optional(&mut *self.tx)
+            .await?;
+        Ok(row.map(|row| Mapped {
+            story_id: row.story_id,
+            comment_id: row.comment_id,
+        }))
+    }
+
+    async fn user_record(&mut self, user: HnUser) -> Result<(), LambdaError> {
+        let created_at = timestamp(Some(user.created));
+        let id = self.user(&user.id, created_at).await?;
+        sqlx::query!("UPDATE users SET created_at = $2 WHERE id = $1", id, created_at)
+            .execute(&mut *self.tx)
+            .await?;
+        Ok(())
+    }
+
+    /// Imports an item, then any replies that were waiting for it.
+    async fn item(&mut self, item: HnItem) -> Result<(), LambdaError> {
+        let mut queue = vec![item];
+        while let Some(mut item) = queue.pop() {
+            for kid in std::mem::take(&mut item.kids) {
+                if let HnKid::Item(mut kid) = kid {
+                    kid.parent.get_or_insert(item.id);
+                    queue.push(*kid);
+                }
+            }
+
+            let id = item.id;
+            let imported = if self.mapped(id).await?.is_some() {
+                self.summary.existing += 1;
+                true
+            } else {
+                self.insert(item).await?
+            };
+            if imported {
+                queue.extend(self.pending.remove(&id).unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the item now has local rows.
+    async fn insert(&mut self, item: HnItem) -> Result<bool, LambdaError> {
+        let Some(by) = item.by.as_deref().filter(|_| !item.deleted && !item.dead) else {
+            self.summary.skipped += 1;
+            return Ok(false);
+        };
+        let created_at = timestamp(item.time);
+
+        let mapped = match item.kind {
+            HnKind::Story | HnKind::Job | HnKind::Poll => {
+                let Some(title) = item.title.as_deref().map(decode_entities) else {
+                    self.summary.skipped += 1;
+                    return Ok(false);
+                };
+                let author_id = self.user(by, created_at).await?;
+                let text = item.text.as_deref().map(hn_text).filter(|text| !text.is_empty());
+                let story_id = sqlx::query_scalar!(
+                    r#"
+                        INSERT INTO stories (title, text, url, canonical_url, author_id, created_at, text_html, kind)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                        RETURNING id
+                    "#,
+                    title,
+                    text,
+                    item.url,
+                    item.url.as_deref().and_then(canonicalize_url),
+                    author_id,
+                    created_at,
+                    text.as_deref().map(|text| render_markdown(text, &[])),
+                    hn_story_kind(&item) as StoryKind
+                )
+                .fetch_one(&mut *self.tx)
+                .await?;
+                self.summary.stories += 1;
+                Mapped {
+                    story_id,
+                    comment_id: None,
+                }
+            }
+            HnKind::Comment => {
+                let Some(parent_id) = item.parent else {
+                    self.summary.skipped += 1;
+                    return Ok(false);
+                };
+                let Some(parent) = self.mapped(parent_id).await? else {
+                    self.pending.entry(parent_id).or_default().push(item);
+                    return Ok(false);
+                };
+                let author_id = self.user(by, created_at).await?;
+                let comment_id = sqlx::query_scalar!(
+                    r#"
+                        INSERT INTO comments (story_id, parent_id, text, author_id, created_at)
+                        VALUES ($1, $2, $3, $4, $5)
+                        RETURNING id
+                    "#,
+                    parent.story_id,
+                    parent.comment_id,
+                    hn_text(item.text.as_deref().unwrap_or_default()),
+                    author_id,
+                    created_at
+                )
+                .fetch_one(&mut *self.tx)
+                .await?;
+                self.summary.comments += 1;
+                Mapped {
+                    story_id: parent.story_id,
+                    comment_id: Some(comment_id),
+                }
+            }
+            HnKind::Other => {
+                self.summary.skipped += 1;
+                return Ok(false);
+            }
+        };
+
+        sqlx::query!(
+            "INSERT INTO hn_items (hn_id, story_id, comment_id) VALUES ($1, $2, $3)",
+            item.id,
+            mapped.story_id,
+            mapped.comment_id
+        )
+        .execute(&mut *self.tx)
+        .await?;
+
+        Ok(true)
+    }
+}
+
+/// Imports a Hacker News dump of concatenated or line-separated JSON values.
+/// Items already imported are skipped, so an interrupted import can simply be
+/// run again. Work is committed every [`PROGRESS_EVERY`] records.
+pub async fn import(pool: &PgPool, input: impl Read, mut progress: impl FnMut(u64)) -> Result<HnImport, LambdaError> {
+    let mut importer = Importer {
+        tx: pool.begin().await?,
+        users: HashMap::new(),
+        pending: HashMap::new(),
+        summary: HnImport::default(),
+    };
+
+    let mut read = 0;
+    for record in serde_json::Deserializer::from_reader(input).into_iter::<HnRecord>() {
+        let record = record.map_err(|err| LambdaError::InvalidData(format!("line {}: {err}", err.line())))?;
+        let mut records = vec![record];
+        while let Some(record) = records.pop() {
+            match record {
+                HnRecord::Item(item) => importer.item(item).await?,
+                HnRecord::User(user) => importer.user_record(user).await?,
+                HnRecord::Many(many) => {
+                    records.extend(many.into_iter().rev());
+                    continue;
+                }
+            }
+            read += 1;
+            if read % PROGRESS_EVERY == 0 {
+                let tx = std::mem::replace(&mut importer.tx, pool.begin().await?);
+                tx.commit().await?;
+                progress(read);
+            }
+        }
+    }
+
+    importer.summary.skipped += importer.pending.values().map(|replies| replies.len() as u64).sum::<u64>();
+    importer.tx.commit().await?;
+    progress(read);
+
+    counts_rebuild(pool).await?;
+    Ok(importer.summary)
+}
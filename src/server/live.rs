@@ -0,0 +1,152 @@
+use crate::{
+    constants::{LIVE_BUFFER, LIVE_RETRY_SECS},
+    model::{ssr::AppState, LambdaError, LiveEvent},
+    server::{health::Shutdown, repo::Repo},
+};
+use axum::{
+    extract::{Query, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+};
+use futures::{future::ready, stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgListener, PgPool};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const CHANNEL: &str = "news_live";
+
+/// What changed, as sent through `NOTIFY`. Payloads are capped at 8000 bytes,
+/// so only ids travel through Postgres and every instance loads the rows
+/// once for all of its subscribers.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    Story { id: i32 },
+    Comment { id: i32 },
+    Score { story_id: i32 },
+}
+
+impl Change {
+    async fn load(self, repo: &Repo) -> Result<LiveEvent, LambdaError> {
+        Ok(match self {
+            Change::Story { id } => LiveEvent::StoryCreated {
+                story: repo.story_by_id(id).await?,
+            },
+            Change::Comment { id } => LiveEvent::CommentCreated {
+                comment: repo.comment_by_id(id).await?,
+            },
+            Change::Score { story_id } => LiveEvent::ScoreChanged {
+                story_id,
+                tally: repo.vote_tally(Some(story_id), None).await?,
+            },
+        })
+    }
+}
+
+/// Announces a change to every instance, logging rather than failing the
+/// request that caused it.
+pub async fn publish(pool: &PgPool, change: Change) {
+    let payload = serde_json::to_string(&change).expect("changes serialize");
+    let result = sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CHANNEL)
+        .bind(payload)
+        .execute(pool)
+        .await;
+    if let Err(err) = result {
+        leptos::logging::error!("Failed to publish {change:?}: {err}");
+    }
+}
+
+/// Fans changes heard from Postgres out to this instance's SSE subscribers.
+#[derive(Clone, Debug)]
+pub struct LiveHub {
+    sender: broadcast::Sender<LiveEvent>,
+}
+
+impl Default for LiveHub {
+    fn default() -> Self {
+        LiveHub {
+            sender: broadcast::channel(LIVE_BUFFER).0,
+        }
+    }
+}
+
+impl LiveHub {
+    /// Listens until shutdown, reconnecting when the connection drops.
+    pub async fn run(self, pool: PgPool, repo: Repo, shutdown: Shutdown) {
+        while !shutdown.is_triggered() {
+            if let Err(err) = self.listen(&pool, &repo, &shutdown).await {
+                leptos::logging::error!("Live updates interrupted: {err}");
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(LIVE_RETRY_SECS)) => {},
+                    _ = shutdown.triggered() => {},
+                }
+            }
+        }
+    }
+
+    async fn listen(&self, pool: &PgPool, repo: &Repo, shutdown: &Shutdown) -> Result<(), sqlx::Error> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen(CHANNEL).await?;
+
+        loop {
+            let notification = tokio::select! {
+                notification = listener.recv() => notification?,
+                _ = shutdown.triggered() => return Ok(()),
+            };
+            let change: Change = match serde_json::from_str(notification.payload()) {
+                Ok(change) => change,
+                Err(err) => {
+                    leptos::logging::error!("Ignoring live update {:?}: {err}", notification.payload());
+                    continue;
+                }
+            };
+            match change.load(repo).await {
+                // Sending only fails when nobody is subscribed.
+                Ok(event) => drop(self.sender.send(event)),
+                Err(err) => leptos::logging::error!("Failed to load {change:?}: {err}"),
+            }
+        }
+    }
+
+    /// Events about one story, or new submissions when `story_id` is `None`.
+    pub fn subscribe(&self, story_id: Option<i32>) -> impl Stream<Item = LiveEvent> {
+        stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => LiveEvent::Lagged,
+                Err(RecvError::Closed) => return None,
+            };
+            Some((event, receiver))
+        })
+        .filter(move |event| {
+            ready(match (event, story_id) {
+                (LiveEvent::Lagged, _) => true,
+                (LiveEvent::StoryCreated { .. }, None) => true,
+                (event, Some(story_id)) => event.story_id() == Some(story_id),
+                _ => false,
+            })
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LiveQuery {
+    pub story: Option<i32>,
+}
+
+/// Streams [`LiveEvent`]s as JSON until the client leaves or the server
+/// shuts down, so graceful shutdown isn't held up by open streams.
+pub async fn handler(State(state): State<AppState>, Query(query): Query<LiveQuery>) -> impl IntoResponse {
+    let shutdown = state.shutdown.clone();
+    let events = state
+        .live
+        .subscribe(query.story)
+        .map(|event| Event::default().json_data(event))
+        .take_until(async move { shutdown.triggered().await });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
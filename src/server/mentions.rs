@@ -0,0 +1,131 @@
+use crate::{
+    features::{mention::mentioned_names, ui::markdown::render_markdown},
+    model::{LambdaError, Mention},
+    server::telemetry,
+};
+use sqlx::{Executor, PgPool, Postgres};
+use tracing::{field::Empty, instrument};
+
+/// A mention and the user it was resolved to when written.
+#[derive(Clone, Debug)]
+pub struct Resolved {
+    pub user_id: i32,
+    pub mention: Mention,
+}
+
+/// Looks up the users `@mentioned` in `text`, in order of appearance. Names
+/// without a user are left out and stay plain text.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn resolve(pool: &PgPool, text: &str) -> Result<Vec<Resolved>, LambdaError> {
+    let names = mentioned_names(text);
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let users = sqlx::query!("SELECT id, display_name FROM users WHERE display_name = ANY($1)", &names)
+        .fetch_all(pool)
+        .await?;
+    telemetry::record_rows(users.len());
+
+    Ok(names
+        .into_iter()
+        .filter_map(|name| {
+            let user = users.iter().find(|user| user.display_name == name)?;
+            Some(Resolved {
+                user_id: user.id,
+                mention: Mention {
+                    name,
+                    user_name: user.display_name.clone(),
+                },
+            })
+        })
+        .collect())
+}
+
+/// Records the mentions in a story's text, or in one of its comments.
+pub async fn save<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    story_id: i32,
+    comment_id: Option<i32>,
+    mentions: &[Resolved],
+) -> Result<(), LambdaError> {
+    if mentions.is_empty() {
+        return Ok(());
+    }
+
+    let user_ids: Vec<i32> = mentions.iter().map(|resolved| resolved.user_id).collect();
+    let names: Vec<String> = mentions.iter().map(|resolved| resolved.mention.name.clone()).collect();
+    sqlx::query!(
+        r#"
+            INSERT INTO mentions (user_id, story_id, comment_id, name)
+            SELECT m.user_id, $1, $2, m.name
+            FROM UNNEST($3::integer[], $4::text[]) WITH ORDINALITY AS m(user_id, name, position)
+            ORDER BY m.position
+            ON CONFLICT DO NOTHING
+        "#,
+        story_id,
+        comment_id,
+        &user_ids,
+        &names
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// The mentions in a story's own text, under their users' current names.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_mentions<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    story_id: i32,
+) -> Result<Vec<Mention>, LambdaError> {
+    let mentions = sqlx::query_as!(
+        Mention,
+        r#"
+            SELECT m.name, u.display_name as user_name
+            FROM mentions m
+            JOIN users u ON u.id = m.user_id
+            WHERE m.story_id = $1 AND m.comment_id IS NULL
+            ORDER BY m.id
+        "#,
+        story_id
+    )
+    .fetch_all(executor)
+    .await?;
+    telemetry::record_rows(mentions.len());
+
+    Ok(mentions)
+}
+
+/// Renders again the stories whose text mentions `user_id`, so their links
+/// follow the user's current name.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn stories_rerender(pool: &PgPool, user_id: i32) -> Result<u64, LambdaError> {
+    let stories = sqlx::query!(
+        r#"
+            SELECT s.id, s.text as "text!"
+            FROM stories s
+            WHERE s.text IS NOT NULL AND s.id IN (
+                SELECT story_id FROM mentions WHERE user_id = $1 AND comment_id IS NULL
+            )
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(stories.len());
+
+    for story in &stories {
+        let mentions = story_mentions(pool, story.id).await?;
+        sqlx::query!(
+            "UPDATE stories SET text_html = $2 WHERE id = $1",
+            story.id,
+            render_markdown(&story.text, &mentions)
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(stories.len() as u64)
+}
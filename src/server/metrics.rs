@@ -0,0 +1,122 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge, Encoder,
+    HistogramVec, IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+use sqlx::PgPool;
+use std::{sync::LazyLock, time::Instant};
+
+pub const PATH: &str = "/metrics";
+
+const SERVER_FN_PREFIX: &str = "/api/";
+
+pub static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("http_requests_total", "HTTP requests by route.", &["method", "route", "status"])
+        .unwrap()
+});
+
+pub static HTTP_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!("http_request_duration_seconds", "HTTP request latency by route.", &["method", "route"])
+        .unwrap()
+});
+
+pub static SERVER_FN_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("server_fn_requests_total", "Server function calls.", &["function", "status"]).unwrap()
+});
+
+pub static SERVER_FN_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!("server_fn_duration_seconds", "Server function latency.", &["function"]).unwrap()
+});
+
+pub static DB_POOL_CONNECTIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("db_pool_connections", "Open connections in the Postgres pool.").unwrap()
+});
+
+pub static DB_POOL_IDLE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("db_pool_idle_connections", "Idle connections in the Postgres pool.").unwrap()
+});
+
+pub static DB_POOL_IN_USE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("db_pool_in_use_connections", "Checked-out connections in the Postgres pool.").unwrap()
+});
+
+pub static STORIES_CREATED: LazyLock<IntCounter> =
+    LazyLock::new(|| register_int_counter!("stories_created_total", "Stories submitted.").unwrap());
+
+pub static COMMENTS_CREATED: LazyLock<IntCounter> =
+    LazyLock::new(|| register_int_counter!("comments_created_total", "Comments posted.").unwrap());
+
+pub static VOTES_CAST: LazyLock<IntCounter> =
+    LazyLock::new(|| register_int_counter!("votes_cast_total", "Votes cast or changed.").unwrap());
+
+pub static VOTES_RETRACTED: LazyLock<IntCounter> =
+    LazyLock::new(|| register_int_counter!("votes_retracted_total", "Votes taken back.").unwrap());
+
+/// Registers every collector up front so that scrapes see all series from the
+/// first request, not only the ones touched so far.
+pub fn init() {
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&HTTP_DURATION);
+    LazyLock::force(&SERVER_FN_REQUESTS);
+    LazyLock::force(&SERVER_FN_DURATION);
+    LazyLock::force(&DB_POOL_CONNECTIONS);
+    LazyLock::force(&DB_POOL_IDLE);
+    LazyLock::force(&DB_POOL_IN_USE);
+    LazyLock::force(&STORIES_CREATED);
+    LazyLock::force(&COMMENTS_CREATED);
+    LazyLock::force(&VOTES_CAST);
+    LazyLock::force(&VOTES_RETRACTED);
+}
+
+/// Server function URLs are the function name followed by a numeric hash.
+pub(crate) fn server_fn_name(path: &str) -> Option<&str> {
+    let name = path.strip_prefix(SERVER_FN_PREFIX)?;
+    Some(name.trim_end_matches(|c: char| c.is_ascii_digit()))
+}
+
+/// Middleware recording request counts and latencies, labelled by the matched
+/// route pattern rather than the raw path to keep cardinality bounded.
+pub async fn track(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let function = server_fn_name(request.uri().path()).map(String::from);
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed().as_secs_f64();
+    let status = response.status().as_u16().to_string();
+
+    HTTP_REQUESTS.with_label_values(&[&method, &route, &status]).inc();
+    HTTP_DURATION.with_label_values(&[&method, &route]).observe(elapsed);
+    if let Some(function) = function {
+        SERVER_FN_REQUESTS.with_label_values(&[&function, &status]).inc();
+        SERVER_FN_DURATION.with_label_values(&[&function]).observe(elapsed);
+    }
+
+    response
+}
+
+pub async fn handler(State(pool): State<PgPool>) -> Response {
+    let idle = pool.num_idle() as i64;
+    let size = pool.size() as i64;
+    DB_POOL_CONNECTIONS.set(size);
+    DB_POOL_IDLE.set(idle);
+    DB_POOL_IN_USE.set(size - idle);
+
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    if encoder.encode(&prometheus::gather(), &mut body).is_err() {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    ([(CONTENT_TYPE, encoder.format_type().to_string())], body).into_response()
+}
@@ -0,0 +1,177 @@
+use crate::{
+    constants::INBOX_SIZE,
+    model::{Comment, LambdaError, Notification, NotificationKind, Story},
+    server::telemetry,
+};
+use sqlx::PgPool;
+use tracing::{field::Empty, instrument};
+
+/// Notifies the author of the story or comment being replied to, and anyone
+/// mentioned, logging rather than failing the request that caused it.
+pub async fn comment_created(pool: &PgPool, comment: &Comment) {
+    let result = async {
+        let replies = notify_reply(pool, comment.id).await?;
+        let mentions = notify_mentions(pool, comment.story_id, Some(comment.id)).await?;
+        Ok::<_, LambdaError>(replies + mentions)
+    };
+    if let Err(err) = result.await {
+        leptos::logging::error!("Failed to notify about comment {}: {err}", comment.id);
+    }
+}
+
+/// Notifies anyone mentioned in a new story's text.
+pub async fn story_created(pool: &PgPool, story: &Story) {
+    if let Err(err) = notify_mentions(pool, story.id, None).await {
+        leptos::logging::error!("Failed to notify about story {}: {err}", story.id);
+    }
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+async fn notify_reply(pool: &PgPool, comment_id: i32) -> Result<u64, LambdaError> {
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO notifications (user_id, kind, actor_id, story_id, comment_id, created_at)
+            SELECT
+                r.id,
+                CASE WHEN p.id IS NULL THEN $2 ELSE $3 END,
+                c.author_id,
+                c.story_id,
+                c.id,
+                c.created_at
+            FROM comments c
+            JOIN stories s ON s.id = c.story_id
+            LEFT JOIN comments p ON p.id = c.parent_id
+            JOIN users r ON r.id = COALESCE(p.author_id, s.author_id)
+            WHERE c.id = $1 AND r.id <> c.author_id AND r.banned_at IS NULL
+            ON CONFLICT DO NOTHING
+        "#,
+        comment_id,
+        NotificationKind::StoryReply.as_str(),
+        NotificationKind::CommentReply.as_str()
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(result.rows_affected())
+}
+
+/// Replies take precedence: someone both answered and mentioned is notified
+/// of the reply only. Mentions were resolved when the text was saved.
+#[instrument(skip_all, fields(rows = Empty))]
+async fn notify_mentions(pool: &PgPool, story_id: i32, comment_id: Option<i32>) -> Result<u64, LambdaError> {
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO notifications (user_id, kind, actor_id, story_id, comment_id, created_at)
+            SELECT u.id, $1, a.author_id, $2, $3, NOW()
+            FROM mentions m
+            JOIN users u ON u.id = m.user_id, (
+                SELECT author_id FROM comments WHERE id = $3
+                UNION ALL
+                SELECT author_id FROM stories WHERE id = $2 AND $3::integer IS NULL
+            ) a
+            WHERE m.story_id = $2 AND m.comment_id IS NOT DISTINCT FROM $3
+                AND u.id <> a.author_id AND u.banned_at IS NULL
+            ON CONFLICT DO NOTHING
+        "#,
+        NotificationKind::Mention.as_str(),
+        story_id,
+        comment_id
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(result.rows_affected())
+}
+
+/// The user's most recent notifications, unread or not.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn notification_list(pool: &PgPool, user_id: i32) -> Result<Vec<Notification>, LambdaError> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                n.id,
+                n.kind,
+                a.display_name as "actor_name?",
+                n.story_id,
+                s.title as story_title,
+                n.comment_id,
+                LEFT(COALESCE(c.text, s.text), 200) as excerpt,
+                n.created_at,
+                n.read_at
+            FROM notifications n
+            JOIN stories s ON s.id = n.story_id
+            LEFT JOIN comments c ON c.id = n.comment_id
+            LEFT JOIN users a ON a.id = n.actor_id
+            WHERE n.user_id = $1
+            ORDER BY n.created_at DESC, n.id DESC
+            LIMIT $2
+        "#,
+        user_id,
+        INBOX_SIZE
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(Notification {
+                id: row.id,
+                kind: row.kind.parse().ok()?,
+                actor_name: row.actor_name,
+                story_id: row.story_id,
+                story_title: row.story_title,
+                comment_id: row.comment_id,
+                excerpt: row.excerpt,
+                created_at: row.created_at.into(),
+                read_at: row.read_at.map(Into::into),
+            })
+        })
+        .collect())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn unread_count(pool: &PgPool, user_id: i32) -> Result<i64, LambdaError> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM notifications WHERE user_id = $1 AND read_at IS NULL"#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(count)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn mark_read(pool: &PgPool, user_id: i32, id: i64) -> Result<(), LambdaError> {
+    let result = sqlx::query!(
+        r#"UPDATE notifications SET read_at = COALESCE(read_at, NOW()) WHERE id = $1 AND user_id = $2"#,
+        id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    if result.rows_affected() == 0 {
+        return Err(LambdaError::NotFound);
+    }
+    Ok(())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn mark_all_read(pool: &PgPool, user_id: i32) -> Result<u64, LambdaError> {
+    let result = sqlx::query!(
+        r#"UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL"#,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,112 @@
+use crate::{
+    features::poll::poll_options,
+    model::{LambdaError, Poll, PollArgs, PollOption},
+    server::telemetry,
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::{field::Empty, instrument};
+
+/// Stores a new poll's options inside the caller's transaction.
+pub async fn save(conn: &mut PgConnection, story_id: i32, poll: &PollArgs) -> Result<(), LambdaError> {
+    sqlx::query!(
+        "INSERT INTO polls (story_id, multiple, closes_at) VALUES ($1, $2, $3)",
+        story_id,
+        poll.multiple,
+        poll.closes_at
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        r#"
+            INSERT INTO poll_options (story_id, position, text)
+            SELECT $1, position, text FROM UNNEST($2::text[]) WITH ORDINALITY AS o(text, position)
+        "#,
+        story_id,
+        &poll_options(&poll.options)
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// A poll and its results, with the reader's ballot when there is a reader.
+/// Stories that aren't polls are not found.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn poll(conn: &mut PgConnection, story_id: i32, user_id: Option<i32>) -> Result<Poll, LambdaError> {
+    let poll = sqlx::query!(
+        r#"
+            SELECT
+                p.multiple,
+                p.closes_at,
+                COALESCE(p.closes_at <= NOW(), FALSE) as "closed!",
+                (SELECT COUNT(DISTINCT b.user_id) FROM ballots b WHERE b.story_id = p.story_id) as "voters!"
+            FROM polls p
+            WHERE p.story_id = $1
+        "#,
+        story_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let options = sqlx::query_as!(
+        PollOption,
+        r#"
+            SELECT o.id, o.text, COUNT(b.user_id) as "votes!"
+            FROM poll_options o
+            LEFT JOIN ballots b ON b.option_id = o.id
+            WHERE o.story_id = $1
+            GROUP BY o.id
+            ORDER BY o.position
+        "#,
+        story_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let ballot = sqlx::query_scalar!(
+        "SELECT option_id FROM ballots WHERE story_id = $1 AND user_id = $2 ORDER BY option_id",
+        story_id,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    telemetry::record_rows(1 + options.len() + ballot.len());
+
+    Ok(Poll {
+        story_id,
+        multiple: poll.multiple,
+        closes_at: poll.closes_at.map(Into::into),
+        closed: poll.closed,
+        options,
+        voters: poll.voters,
+        ballot,
+    })
+}
+
+/// Replaces the voter's ballot, or withdraws it when `option_ids` is empty,
+/// returning the updated results.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn poll_vote(pool: &PgPool, story_id: i32, user_id: i32, option_ids: &[i32]) -> Result<Poll, LambdaError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query_scalar!("SELECT story_id FROM polls WHERE story_id = $1 FOR UPDATE", story_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    poll(&mut tx, story_id, Some(user_id)).await?.check_ballot(option_ids)?;
+
+    sqlx::query!("DELETE FROM ballots WHERE story_id = $1 AND user_id = $2", story_id, user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO ballots (user_id, option_id, story_id) SELECT $1, UNNEST($2::integer[]), $3",
+        user_id,
+        option_ids,
+        story_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let poll = poll(&mut tx, story_id, Some(user_id)).await?;
+    tx.commit().await?;
+
+    Ok(poll)
+}
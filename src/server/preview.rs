@@ -0,0 +1,298 @@
+use crate::{
+    constants::{PREVIEW_MAX_BYTES, PREVIEW_MAX_REDIRECTS, PREVIEW_TIMEOUT_SECS},
+    model::LinkPreview,
+    server::telemetry,
+};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::{ACCEPT, CONTENT_TYPE},
+    redirect, Client, Response,
+};
+use scraper::{Html, Selector};
+use sqlx::PgPool;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio_util::task::TaskTracker;
+use tracing::{field::Empty, instrument};
+use typed_builder::TypedBuilder;
+use url::{Host, Url};
+
+#[derive(Error, Debug)]
+pub enum PreviewError {
+    #[error("unsupported URL {0}")]
+    UnsupportedUrl(String),
+    #[error("refusing to fetch private address {0}")]
+    PrivateAddress(String),
+    #[error("response is not an HTML document")]
+    NotHtml,
+    #[error("link previews are disabled")]
+    Disabled,
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+/// Fetches a submitted page and extracts its title, OpenGraph and oEmbed
+/// metadata. Only public addresses are contacted unless `allow_private` is set.
+#[derive(Clone, Debug, TypedBuilder)]
+pub struct PreviewFetcher {
+    #[builder(default = Duration::from_secs(PREVIEW_TIMEOUT_SECS))]
+    pub timeout: Duration,
+    #[builder(default = PREVIEW_MAX_BYTES)]
+    pub max_bytes: usize,
+    #[builder(default = false)]
+    pub allow_private: bool,
+    #[builder(default = true)]
+    pub enabled: bool,
+    /// Background fetches, awaited on shutdown.
+    #[builder(default)]
+    pub tasks: TaskTracker,
+    /// Built on first use and shared by clones, so connections are reused.
+    #[builder(default, setter(skip))]
+    client: Arc<OnceLock<Client>>,
+}
+
+impl Default for PreviewFetcher {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl PreviewFetcher {
+    pub async fn fetch(&self, url: &str) -> Result<LinkPreview, PreviewError> {
+        if !self.enabled {
+            return Err(PreviewError::Disabled);
+        }
+        let url = Url::parse(url).map_err(|_| PreviewError::UnsupportedUrl(url.to_string()))?;
+        check_url(&url, self.allow_private)?;
+
+        let client = self.client()?;
+        let response = client
+            .get(url)
+            .header(ACCEPT, "text/html,application/xhtml+xml")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let is_html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("html"));
+        if !is_html {
+            return Err(PreviewError::NotHtml);
+        }
+
+        let base = response.url().clone();
+        let body = self.read_capped(response).await?;
+        let (mut preview, oembed) = parse_html(&body, &base);
+
+        if let Some(oembed) = oembed {
+            if let Ok(oembed) = self.fetch_oembed(&client, oembed).await {
+                preview.title = preview.title.or(oembed.title);
+                preview.site_name = preview.site_name.or(oembed.site_name);
+                preview.image_url = preview.image_url.or(oembed.image_url);
+            }
+        }
+
+        Ok(preview)
+    }
+
+    fn client(&self) -> Result<Client, PreviewError> {
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
+        let allow_private = self.allow_private;
+        let policy = redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= PREVIEW_MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if let Err(err) = check_url(attempt.url(), allow_private) {
+                attempt.error(err)
+            } else {
+                attempt.follow()
+            }
+        });
+
+        let client = Client::builder()
+            .timeout(self.timeout)
+            .redirect(policy)
+            // A proxy would resolve hosts itself, past the resolver's checks.
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver { allow_private }))
+            .user_agent(concat!("news/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        Ok(self.client.get_or_init(|| client).clone())
+    }
+
+    async fn read_capped(&self, mut response: Response) -> Result<String, PreviewError> {
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            let remaining = self.max_bytes - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+            if body.len() >= self.max_bytes {
+                break;
+            }
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    async fn fetch_oembed(&self, client: &Client, url: Url) -> Result<LinkPreview, PreviewError> {
+        check_url(&url, self.allow_private)?;
+        let response = client.get(url).send().await?.error_for_status()?;
+        let body = self.read_capped(response).await?;
+        let json: serde_json::Value =
+            serde_json::from_str(&body).map_err(|_| PreviewError::NotHtml)?;
+        let field = |name: &str| json.get(name).and_then(|v| v.as_str()).and_then(clean);
+
+        Ok(LinkPreview {
+            title: field("title"),
+            description: None,
+            image_url: field("thumbnail_url"),
+            site_name: field("provider_name"),
+        })
+    }
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn save(pool: &PgPool, story_id: i32, preview: &LinkPreview) -> Result<(), sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO story_previews (story_id, title, description, image_url, site_name, fetched_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT (story_id) DO UPDATE SET
+                title = EXCLUDED.title,
+                description = EXCLUDED.description,
+                image_url = EXCLUDED.image_url,
+                site_name = EXCLUDED.site_name,
+                fetched_at = EXCLUDED.fetched_at
+        "#,
+        story_id,
+        preview.title,
+        preview.description,
+        preview.image_url,
+        preview.site_name,
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(())
+}
+
+fn parse_html(body: &str, base: &Url) -> (LinkPreview, Option<Url>) {
+    let document = Html::parse_document(body);
+    let meta = |attribute: &str, name: &str| {
+        let selector = Selector::parse(&format!(r#"meta[{attribute}="{name}"]"#)).ok()?;
+        document
+            .select(&selector)
+            .find_map(|element| element.value().attr("content"))
+            .and_then(clean)
+    };
+    let title = Selector::parse("title").ok().and_then(|selector| {
+        document
+            .select(&selector)
+            .next()
+            .and_then(|element| clean(&element.text().collect::<String>()))
+    });
+    let oembed = Selector::parse(r#"link[type="application/json+oembed"]"#)
+        .ok()
+        .and_then(|selector| document.select(&selector).find_map(|element| element.value().attr("href")))
+        .and_then(|href| base.join(href).ok());
+
+    let preview = LinkPreview {
+        title: meta("property", "og:title").or(title),
+        description: meta("property", "og:description").or_else(|| meta("name", "description")),
+        image_url: meta("property", "og:image")
+            .and_then(|image| base.join(&image).ok())
+            .map(String::from),
+        site_name: meta("property", "og:site_name"),
+    };
+
+    (preview, oembed)
+}
+
+fn clean(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+pub(crate) fn check_url(url: &Url, allow_private: bool) -> Result<(), PreviewError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(PreviewError::UnsupportedUrl(url.to_string()));
+    }
+    let ip: IpAddr = match url.host() {
+        Some(Host::Ipv4(ip)) => ip.into(),
+        Some(Host::Ipv6(ip)) => ip.into(),
+        Some(Host::Domain(_)) => return Ok(()),
+        None => return Err(PreviewError::UnsupportedUrl(url.to_string())),
+    };
+    if allow_private || is_public(ip) {
+        Ok(())
+    } else {
+        Err(PreviewError::PrivateAddress(ip.to_string()))
+    }
+}
+
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match embedded_ipv4(ip) {
+            Some(ip) => is_public(ip.into()),
+            None => {
+                let [first, second, ..] = ip.segments();
+                !(ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+                    || (first == 0x2001 && second == 0x0db8))
+            }
+        },
+    }
+}
+
+/// The IPv4 address an IPv6 one reaches: IPv4-mapped `::ffff:a.b.c.d`,
+/// IPv4-compatible `::a.b.c.d`, which includes `::` and `::1`, and NAT64
+/// `64:ff9b::a.b.c.d`.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let [.., a, b, c, d] = ip.octets();
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0 | 0xffff, ..] | [0x64, 0xff9b, 0, 0, 0, 0, ..] => Some(Ipv4Addr::new(a, b, c, d)),
+        _ => None,
+    }
+}
+
+/// Resolves hostnames and drops any address that is not publicly routable, so
+/// DNS names pointing into the cluster can't be used to reach internal services.
+pub(crate) struct PublicResolver {
+    pub(crate) allow_private: bool,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private = self.allow_private;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| allow_private || is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(PreviewError::PrivateAddress(name.as_str().to_string()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
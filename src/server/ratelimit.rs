@@ -0,0 +1,99 @@
+use crate::model::{ssr::AppState, LambdaError};
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const WINDOW: Duration = Duration::from_secs(60);
+const PRUNE_ABOVE: usize = 10_000;
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Fixed-window limit on writes per client address. Handlers opt in through
+/// [`Throttle`] or [`crate::server::throttle`], so reads are never counted.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimiter {
+    per_window: u32,
+    trusted_proxies: Arc<[IpAddr]>,
+    windows: Arc<Mutex<HashMap<IpAddr, (Instant, u32)>>>,
+}
+
+impl RateLimiter {
+    /// A limiter allowing `per_minute` writes per address; zero disables it.
+    /// Requests through one of `trusted_proxies` count against the address
+    /// the proxy forwarded them for.
+    pub fn new(per_minute: u32, trusted_proxies: Vec<IpAddr>) -> Self {
+        RateLimiter {
+            per_window: per_minute,
+            trusted_proxies: trusted_proxies.into(),
+            windows: Arc::default(),
+        }
+    }
+
+    /// The client behind `peer`: the peer itself, unless it is a trusted
+    /// proxy, in which case the nearest `X-Forwarded-For` hop that isn't one.
+    /// Hops further out can be forged by the client, so they are never used.
+    pub fn client(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+        headers
+            .get_all(FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .rev()
+            .map_while(|hop| hop.trim().parse::<IpAddr>().ok())
+            .find(|hop| !self.trusted_proxies.contains(hop))
+            .unwrap_or(peer)
+    }
+
+    /// Counts one request from `ip`, returning whether it is within the limit.
+    pub fn check(&self, ip: IpAddr) -> bool {
+        if self.per_window == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > PRUNE_ABOVE {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < WINDOW);
+        }
+        let (started, count) = windows.entry(ip).or_insert((now, 0));
+        if now.duration_since(*started) >= WINDOW {
+            *started = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.per_window
+    }
+
+    /// Counts one write from `peer`. Requests without a known peer address,
+    /// such as in-process ones, are not limited.
+    pub fn throttle(&self, peer: Option<&ConnectInfo<SocketAddr>>, headers: &HeaderMap) -> Result<(), LambdaError> {
+        match peer {
+            Some(ConnectInfo(addr)) if !self.check(self.client(addr.ip(), headers)) => Err(LambdaError::RateLimited),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Extractor that counts a REST write against the caller's rate limit.
+#[derive(Clone, Copy, Debug)]
+pub struct Throttle;
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for Throttle {
+    type Rejection = LambdaError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        state
+            .rate_limiter
+            .throttle(parts.extensions.get(), &parts.headers)
+            .map(|_| Throttle)
+    }
+}
@@ -0,0 +1,142 @@
+pub mod memory;
+pub mod postgres;
+
+use crate::model::{
+    Comment, CommentCreateArgs, Draft, DraftArgs, LambdaError, Poll, Story, StoryCreateArgs, StoryFilter, StoryKind,
+    StoryListItem, StoryVisit, Tag, TagPreference, TagPreferences, User, VoteArgs, VoteTally,
+};
+use std::{fmt, ops::Deref, sync::Arc};
+
+pub use memory::MemoryRepo;
+pub use postgres::PgRepo;
+
+/// Stories and the front page listing, newest first. Listings leave out what
+/// `filter` does, before paging.
+#[axum::async_trait]
+pub trait StoryRepo: Send + Sync {
+    async fn story_page(&self, page: i64, filter: &StoryFilter) -> Result<Vec<StoryListItem>, LambdaError>;
+    async fn story_page_count(&self, filter: &StoryFilter) -> Result<i64, LambdaError>;
+    /// A page of one kind of stories, ranked by that kind's rules.
+    async fn story_kind_page(
+        &self,
+        kind: StoryKind,
+        page: i64,
+        filter: &StoryFilter,
+    ) -> Result<Vec<StoryListItem>, LambdaError>;
+    async fn story_by_id(&self, id: i32) -> Result<Story, LambdaError>;
+    /// The most recent story with this canonical URL inside the duplicate window.
+    async fn story_duplicate(&self, canonical_url: &str) -> Result<Option<Story>, LambdaError>;
+    /// Validates and stores a story, or returns the duplicate it would repeat.
+    async fn story_insert(&self, story: StoryCreateArgs, author_id: i32) -> Result<Story, LambdaError>;
+}
+
+#[axum::async_trait]
+pub trait CommentRepo: Send + Sync {
+    async fn comment_insert(&self, comment: CommentCreateArgs, author_id: i32) -> Result<Comment, LambdaError>;
+    /// A story's comments, newest first.
+    async fn comments_for_story(&self, story_id: i32) -> Result<Vec<Comment>, LambdaError>;
+    async fn comment_by_id(&self, id: i32) -> Result<Comment, LambdaError>;
+    /// A comment and its ancestors, oldest first; empty when it doesn't exist.
+    async fn comment_with_parents(&self, id: i32) -> Result<Vec<Comment>, LambdaError>;
+}
+
+#[axum::async_trait]
+pub trait UserRepo: Send + Sync {
+    async fn user_by_name(&self, name: &str) -> Result<User, LambdaError>;
+    async fn user_insert(&self, name: &str) -> Result<User, LambdaError>;
+}
+
+#[axum::async_trait]
+pub trait VoteRepo: Send + Sync {
+    /// Replaces the voter's previous vote on the target; zero retracts it.
+    async fn vote_cast(
+        &self,
+        story_id: Option<i32>,
+        comment_id: Option<i32>,
+        vote: VoteArgs,
+        user_id: i32,
+    ) -> Result<VoteTally, LambdaError>;
+    async fn vote_tally(&self, story_id: Option<i32>, comment_id: Option<i32>) -> Result<VoteTally, LambdaError>;
+}
+
+/// What each reader has opened and hidden.
+#[axum::async_trait]
+pub trait VisitRepo: Send + Sync {
+    /// Records a visit, returning the reader's state from before it.
+    async fn story_visit(&self, user_id: i32, story_id: i32) -> Result<StoryVisit, LambdaError>;
+    /// The reader's state for those of `story_ids` they opened or hid.
+    async fn story_visits(&self, user_id: i32, story_ids: &[i32]) -> Result<Vec<StoryVisit>, LambdaError>;
+    async fn story_hide(&self, user_id: i32, story_id: i32, hidden: bool) -> Result<(), LambdaError>;
+    async fn hidden_story_ids(&self, user_id: i32) -> Result<Vec<i32>, LambdaError>;
+}
+
+/// The tag vocabulary, the stories filed under each tag and what readers
+/// follow or mute.
+#[axum::async_trait]
+pub trait TagRepo: Send + Sync {
+    /// The vocabulary, alphabetically, with how many stories carry each tag.
+    async fn tag_list(&self) -> Result<Vec<Tag>, LambdaError>;
+    async fn tag_create(&self, name: &str, description: Option<&str>) -> Result<Tag, LambdaError>;
+    /// A page of the stories with the tag, newest first.
+    async fn tag_page(&self, name: &str, page: i64) -> Result<Vec<StoryListItem>, LambdaError>;
+    /// Replaces a story's tags, returning them sorted.
+    async fn story_tags_set(&self, story_id: i32, names: &[String]) -> Result<Vec<String>, LambdaError>;
+    async fn tag_preferences(&self, user_id: i32) -> Result<TagPreferences, LambdaError>;
+    /// Follows or mutes a tag, or forgets either with `None`.
+    async fn tag_prefer(&self, user_id: i32, name: &str, preference: Option<TagPreference>) -> Result<(), LambdaError>;
+}
+
+/// The options of poll stories and the ballots cast on them.
+#[axum::async_trait]
+pub trait PollRepo: Send + Sync {
+    /// A poll's results, with `user_id`'s ballot. Stories that aren't polls
+    /// are not found.
+    async fn poll(&self, story_id: i32, user_id: Option<i32>) -> Result<Poll, LambdaError>;
+    /// Replaces the voter's ballot; an empty one withdraws it.
+    async fn poll_vote(&self, story_id: i32, user_id: i32, option_ids: &[i32]) -> Result<Poll, LambdaError>;
+}
+
+/// What authors have written but not yet posted, one draft per editor: a
+/// new story's, or a comment's on a story, replying to `parent_id` when set.
+#[axum::async_trait]
+pub trait DraftRepo: Send + Sync {
+    /// Replaces the editor's draft; a blank one discards it. Drafts on
+    /// stories or comments that don't exist are not found.
+    async fn draft_save(&self, user_id: i32, draft: DraftArgs) -> Result<(), LambdaError>;
+    async fn draft_discard(&self, user_id: i32, story_id: Option<i32>, parent_id: Option<i32>) -> Result<(), LambdaError>;
+    /// The user's drafts, most recently saved first, or only the one for the
+    /// `(story_id, parent_id)` editor.
+    async fn drafts(&self, user_id: i32, editor: Option<(Option<i32>, Option<i32>)>) -> Result<Vec<Draft>, LambdaError>;
+}
+
+pub trait Repository:
+    StoryRepo + CommentRepo + UserRepo + VoteRepo + VisitRepo + TagRepo + PollRepo + DraftRepo
+{
+}
+
+impl<T: StoryRepo + CommentRepo + UserRepo + VoteRepo + VisitRepo + TagRepo + PollRepo + DraftRepo> Repository for T {}
+
+/// Shared handle to the storage backend used by server functions and the
+/// REST API.
+#[derive(Clone)]
+pub struct Repo(Arc<dyn Repository>);
+
+impl Repo {
+    pub fn new(repository: impl Repository + 'static) -> Self {
+        Repo(Arc::new(repository))
+    }
+}
+
+impl Deref for Repo {
+    type Target = dyn Repository;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::Debug for Repo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Repo").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,749 @@
+use super::{CommentRepo, DraftRepo, PollRepo, StoryRepo, TagRepo, UserRepo, VisitRepo, VoteRepo};
+use crate::{
+    constants::{DUPLICATE_WINDOW_DAYS, JOB_LISTING_DAYS, PAGE_SIZE, STORY_MAX_TAGS},
+    features::{
+        canonical::canonicalize_url,
+        mention::mentioned_names,
+        poll::poll_options,
+        tag::{is_tag_name, normalize_tags},
+        ui::markdown::render_markdown,
+    },
+    model::{
+        Comment, CommentCreateArgs, Draft, DraftArgs, LambdaError, Mention, Poll, PollOption, Story, StoryCreateArgs,
+        StoryFilter, StoryKind, StoryListItem, StoryVisit, Tag, TagPreference, TagPreferences, User, VoteArgs,
+        VoteTally,
+    },
+};
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use std::sync::{Arc, Mutex, MutexGuard};
+use validator::Validate;
+
+#[derive(Debug)]
+struct StoredStory {
+    story: Story,
+    canonical_url: Option<String>,
+}
+
+#[derive(Debug)]
+struct StoredVote {
+    user_id: i32,
+    story_id: Option<i32>,
+    comment_id: Option<i32>,
+    value: i32,
+}
+
+#[derive(Debug)]
+struct StoredVisit {
+    user_id: i32,
+    story_id: i32,
+    seen_at: Option<DateTime<FixedOffset>>,
+    hidden: bool,
+}
+
+#[derive(Debug)]
+struct StoredPreference {
+    user_id: i32,
+    name: String,
+    muted: bool,
+}
+
+#[derive(Debug)]
+struct StoredPoll {
+    story_id: i32,
+    multiple: bool,
+    closes_at: Option<DateTime<FixedOffset>>,
+    options: Vec<(i32, String)>,
+}
+
+#[derive(Debug)]
+struct StoredBallot {
+    story_id: i32,
+    user_id: i32,
+    option_id: i32,
+}
+
+#[derive(Debug)]
+struct StoredDraft {
+    user_id: i32,
+    draft: DraftArgs,
+    updated_at: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    users: Vec<User>,
+    stories: Vec<StoredStory>,
+    comments: Vec<Comment>,
+    votes: Vec<StoredVote>,
+    visits: Vec<StoredVisit>,
+    tags: Vec<Tag>,
+    preferences: Vec<StoredPreference>,
+    polls: Vec<StoredPoll>,
+    ballots: Vec<StoredBallot>,
+    drafts: Vec<StoredDraft>,
+}
+
+impl State {
+    fn user(&self, id: i32) -> Result<&User, LambdaError> {
+        self.users
+            .iter()
+            .find(|user| user.id == id)
+            .ok_or(LambdaError::NotFound)
+    }
+
+    fn story(&self, id: i32) -> Option<&Story> {
+        self.stories.iter().map(|stored| &stored.story).find(|story| story.id == id)
+    }
+
+    fn comment(&self, id: i32) -> Option<&Comment> {
+        self.comments.iter().find(|comment| comment.id == id)
+    }
+
+    /// Resolves mentions against the stored users. Renames aren't supported
+    /// here, so a mention's name and its user's name always agree.
+    fn mentions(&self, text: &str) -> Vec<Mention> {
+        mentioned_names(text)
+            .into_iter()
+            .filter(|name| self.users.iter().any(|user| &user.username == name))
+            .map(|name| Mention {
+                user_name: name.clone(),
+                name,
+            })
+            .collect()
+    }
+
+    fn list_item(&self, story: &Story) -> Result<StoryListItem, LambdaError> {
+        Ok(StoryListItem {
+            id: story.id,
+            title: story.title.clone(),
+            text: story.text.clone(),
+            url: story.url.clone(),
+            created_at: story.created_at,
+            author_name: self.user(story.author_id)?.username.clone(),
+            rating: Some(self.tally(Some(story.id), None).score),
+            comment_count: Some(self.comments.iter().filter(|comment| comment.story_id == story.id).count() as i32),
+            kind: story.kind,
+            tags: story.tags.clone(),
+        })
+    }
+
+    /// Stories newest first, as listings show them.
+    fn newest(&self) -> Vec<&Story> {
+        let mut stories: Vec<&Story> = self.stories.iter().map(|stored| &stored.story).collect();
+        stories.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+        stories
+    }
+
+    /// When the story was created or last commented on.
+    fn activity(&self, story: &Story) -> DateTime<FixedOffset> {
+        self.comments
+            .iter()
+            .filter(|comment| comment.story_id == story.id)
+            .map(|comment| comment.created_at)
+            .fold(story.created_at, Ord::max)
+    }
+
+    /// Score decayed with age, as shows are ranked.
+    fn gravity(&self, story: &Story) -> f64 {
+        let hours = (now() - story.created_at).num_seconds() as f64 / 3600.0;
+        f64::from(self.tally(Some(story.id), None).score) / (hours + 2.0).powf(1.8)
+    }
+
+    fn tag(&self, name: &str) -> Result<&Tag, LambdaError> {
+        self.tags.iter().find(|tag| tag.name == name).ok_or(LambdaError::NotFound)
+    }
+
+    /// Checks `names` against the vocabulary, as a story's tags.
+    fn story_tags(&self, names: &[String]) -> Result<Vec<String>, LambdaError> {
+        let names = normalize_tags(names);
+        if names.len() > STORY_MAX_TAGS {
+            return Err(LambdaError::ValidationError(format!(
+                "a story has at most {STORY_MAX_TAGS} tags"
+            )));
+        }
+        if let Some(unknown) = names.iter().find(|name| self.tag(name).is_err()) {
+            return Err(LambdaError::ValidationError(format!("tag {unknown} does not exist")));
+        }
+        Ok(names)
+    }
+
+    fn visit(&self, visit: &StoredVisit) -> Result<StoryVisit, LambdaError> {
+        let reader = &self.user(visit.user_id)?.username;
+        let mut new_comment_ids: Vec<i32> = self
+            .comments
+            .iter()
+            .filter(|comment| comment.story_id == visit.story_id && &comment.author_name != reader)
+            .filter(|comment| visit.seen_at.is_some_and(|seen_at| comment.created_at > seen_at))
+            .map(|comment| comment.id)
+            .collect();
+        new_comment_ids.sort_unstable();
+        Ok(StoryVisit {
+            story_id: visit.story_id,
+            seen_at: visit.seen_at,
+            new_comment_ids,
+            hidden: visit.hidden,
+        })
+    }
+
+    /// The reader's visit to a story, created when they have none yet.
+    fn visit_mut(&mut self, user_id: i32, story_id: i32) -> Result<&mut StoredVisit, LambdaError> {
+        self.user(user_id)?;
+        if self.story(story_id).is_none() {
+            return Err(LambdaError::NotFound);
+        }
+        let index = match self
+            .visits
+            .iter()
+            .position(|visit| visit.user_id == user_id && visit.story_id == story_id)
+        {
+            Some(index) => index,
+            None => {
+                self.visits.push(StoredVisit {
+                    user_id,
+                    story_id,
+                    seen_at: None,
+                    hidden: false,
+                });
+                self.visits.len() - 1
+            }
+        };
+        Ok(&mut self.visits[index])
+    }
+
+    fn poll(&self, story_id: i32, user_id: Option<i32>) -> Result<Poll, LambdaError> {
+        let poll = self
+            .polls
+            .iter()
+            .find(|poll| poll.story_id == story_id)
+            .ok_or(LambdaError::NotFound)?;
+        let ballots = || self.ballots.iter().filter(|ballot| ballot.story_id == story_id);
+        let mut voters: Vec<i32> = ballots().map(|ballot| ballot.user_id).collect();
+        voters.sort_unstable();
+        voters.dedup();
+        let mut ballot: Vec<i32> = ballots()
+            .filter(|ballot| Some(ballot.user_id) == user_id)
+            .map(|ballot| ballot.option_id)
+            .collect();
+        ballot.sort_unstable();
+
+        Ok(Poll {
+            story_id,
+            multiple: poll.multiple,
+            closes_at: poll.closes_at,
+            closed: poll.closes_at.is_some_and(|closes_at| closes_at <= now()),
+            options: poll
+                .options
+                .iter()
+                .map(|(id, text)| PollOption {
+                    id: *id,
+                    text: text.clone(),
+                    votes: ballots().filter(|ballot| ballot.option_id == *id).count() as i64,
+                })
+                .collect(),
+            voters: voters.len() as i64,
+            ballot,
+        })
+    }
+
+    fn tally(&self, story_id: Option<i32>, comment_id: Option<i32>) -> VoteTally {
+        let votes = self
+            .votes
+            .iter()
+            .filter(|vote| vote.story_id == story_id && vote.comment_id == comment_id);
+        VoteTally {
+            score: votes.clone().map(|vote| vote.value).sum(),
+            count: votes.count() as i32,
+        }
+    }
+}
+
+fn now() -> DateTime<FixedOffset> {
+    Local::now().fixed_offset()
+}
+
+/// Keeps everything in process, for tests. Behaves like [`super::PgRepo`]
+/// as far as the conformance suite can tell, minus webhooks, link previews
+/// and metrics.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRepo {
+    state: Arc<Mutex<State>>,
+}
+
+impl MemoryRepo {
+    pub fn new() -> Self {
+        MemoryRepo::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+#[axum::async_trait]
+impl StoryRepo for MemoryRepo {
+    async fn story_page(&self, page: i64, filter: &StoryFilter) -> Result<Vec<StoryListItem>, LambdaError> {
+        let state = self.state();
+        state
+            .newest()
+            .into_iter()
+            .filter(|story| filter.keeps(story.id, &story.tags))
+            .skip((page * PAGE_SIZE).max(0) as usize)
+            .take(PAGE_SIZE as usize)
+            .map(|story| state.list_item(story))
+            .collect()
+    }
+
+    async fn story_page_count(&self, filter: &StoryFilter) -> Result<i64, LambdaError> {
+        let state = self.state();
+        let count = state.newest().into_iter().filter(|story| filter.keeps(story.id, &story.tags)).count();
+        Ok(count as i64 / PAGE_SIZE + 1)
+    }
+
+    async fn story_kind_page(
+        &self,
+        kind: StoryKind,
+        page: i64,
+        filter: &StoryFilter,
+    ) -> Result<Vec<StoryListItem>, LambdaError> {
+        let state = self.state();
+        let job_cutoff = now() - Duration::days(JOB_LISTING_DAYS);
+        let mut stories: Vec<&Story> = state
+            .newest()
+            .into_iter()
+            .filter(|story| story.kind == kind)
+            .filter(|story| kind != StoryKind::Job || story.created_at > job_cutoff)
+            .filter(|story| filter.keeps(story.id, &story.tags))
+            .collect();
+        match kind {
+            StoryKind::Ask => stories.sort_by_key(|story| std::cmp::Reverse(state.activity(story))),
+            StoryKind::Show => stories.sort_by(|a, b| state.gravity(b).total_cmp(&state.gravity(a))),
+            StoryKind::Poll => stories.sort_by_key(|story| state.poll(story.id, None).is_ok_and(|poll| poll.closed)),
+            _ => {}
+        }
+        stories
+            .into_iter()
+            .skip((page * PAGE_SIZE).max(0) as usize)
+            .take(PAGE_SIZE as usize)
+            .map(|story| state.list_item(story))
+            .collect()
+    }
+
+    async fn story_by_id(&self, id: i32) -> Result<Story, LambdaError> {
+        self.state().story(id).cloned().ok_or(LambdaError::NotFound)
+    }
+
+    async fn story_duplicate(&self, canonical_url: &str) -> Result<Option<Story>, LambdaError> {
+        let cutoff = now() - Duration::days(DUPLICATE_WINDOW_DAYS);
+        Ok(self
+            .state()
+            .stories
+            .iter()
+            .filter(|stored| stored.canonical_url.as_deref() == Some(canonical_url))
+            .map(|stored| &stored.story)
+            .filter(|story| story.created_at > cutoff)
+            .max_by_key(|story| story.created_at)
+            .cloned())
+    }
+
+    async fn story_insert(&self, story: StoryCreateArgs, author_id: i32) -> Result<Story, LambdaError> {
+        story.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+
+        let canonical_url = story.url.as_deref().and_then(canonicalize_url);
+        if let Some(canonical_url) = canonical_url.as_deref() {
+            if let Some(existing) = self.story_duplicate(canonical_url).await? {
+                return Ok(existing);
+            }
+        }
+
+        let mut state = self.state();
+        state.user(author_id)?;
+        let tags = state.story_tags(&story.tags)?;
+        let kind = story.kind();
+        let poll = story.poll.clone();
+        let story = Story {
+            id: state.stories.len() as i32 + 1,
+            text_html: story.text.as_deref().map(|text| render_markdown(text, &state.mentions(text))),
+            title: story.title,
+            text: story.text,
+            url: story.url,
+            created_at: now(),
+            author_id,
+            kind,
+            tags,
+        };
+        if let Some(poll) = poll.filter(|_| kind == StoryKind::Poll) {
+            let first_option_id = state.polls.iter().map(|poll| poll.options.len()).sum::<usize>() as i32 + 1;
+            state.polls.push(StoredPoll {
+                story_id: story.id,
+                multiple: poll.multiple,
+                closes_at: poll.closes_at,
+                options: (first_option_id..).zip(poll_options(&poll.options)).collect(),
+            });
+        }
+        state.stories.push(StoredStory {
+            story: story.clone(),
+            canonical_url,
+        });
+
+        Ok(story)
+    }
+}
+
+#[axum::async_trait]
+impl CommentRepo for MemoryRepo {
+    async fn comment_insert(&self, comment: CommentCreateArgs, author_id: i32) -> Result<Comment, LambdaError> {
+        comment.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+        let mut state = self.state();
+        let author_name = state.user(author_id)?.username.clone();
+        if state.story(comment.story_id).is_none() {
+            return Err(LambdaError::NotFound);
+        }
+        if let Some(parent_id) = comment.parent_id {
+            let parent = state.comment(parent_id).ok_or(LambdaError::NotFound)?;
+            if parent.story_id != comment.story_id {
+                return Err(LambdaError::ValidationError("the parent comment is on another story".into()));
+            }
+        }
+
+        let comment = Comment {
+            id: state.comments.len() as i32 + 1,
+            mentions: state.mentions(&comment.text),
+            text: comment.text,
+            parent_id: comment.parent_id,
+            story_id: comment.story_id,
+            created_at: now(),
+            author_name,
+        };
+        state.comments.push(comment.clone());
+
+        Ok(comment)
+    }
+
+    async fn comments_for_story(&self, story_id: i32) -> Result<Vec<Comment>, LambdaError> {
+        let mut comments: Vec<Comment> = self
+            .state()
+            .comments
+            .iter()
+            .filter(|comment| comment.story_id == story_id)
+            .cloned()
+            .collect();
+        comments.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+
+        Ok(comments)
+    }
+
+    async fn comment_by_id(&self, id: i32) -> Result<Comment, LambdaError> {
+        self.state().comment(id).cloned().ok_or(LambdaError::NotFound)
+    }
+
+    async fn comment_with_parents(&self, id: i32) -> Result<Vec<Comment>, LambdaError> {
+        let state = self.state();
+        let mut chain = Vec::new();
+        let mut next = Some(id);
+        while let Some(comment) = next.and_then(|id| state.comment(id)) {
+            chain.push(comment.clone());
+            next = comment.parent_id;
+        }
+        chain.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+
+        Ok(chain)
+    }
+}
+
+#[axum::async_trait]
+impl UserRepo for MemoryRepo {
+    async fn user_by_name(&self, name: &str) -> Result<User, LambdaError> {
+        self.state()
+            .users
+            .iter()
+            .find(|user| user.username == name)
+            .cloned()
+            .ok_or(LambdaError::NotFound)
+    }
+
+    async fn user_insert(&self, name: &str) -> Result<User, LambdaError> {
+        if name.trim().is_empty() {
+            return Err(LambdaError::ValidationError("name is empty".into()));
+        }
+        let mut state = self.state();
+        if state.users.iter().any(|user| user.username == name) {
+            return Err(LambdaError::ValidationError(format!("user {name} already exists")));
+        }
+
+        let user = User {
+            id: state.users.len() as i32 + 1,
+            username: name.to_string(),
+            created_at: now(),
+        };
+        state.users.push(user.clone());
+
+        Ok(user)
+    }
+}
+
+#[axum::async_trait]
+impl VoteRepo for MemoryRepo {
+    async fn vote_cast(
+        &self,
+        story_id: Option<i32>,
+        comment_id: Option<i32>,
+        vote: VoteArgs,
+        user_id: i32,
+    ) -> Result<VoteTally, LambdaError> {
+        vote.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+
+        let mut state = self.state();
+        let exists = match (story_id, comment_id) {
+            (Some(id), None) => state.story(id).is_some(),
+            (None, Some(id)) => state.comment(id).is_some(),
+            _ => return Err(LambdaError::InvalidData("vote needs exactly one target".into())),
+        };
+
+        state
+            .votes
+            .retain(|stored| !(stored.user_id == user_id && stored.story_id == story_id && stored.comment_id == comment_id));
+        if vote.value != 0 {
+            if !exists {
+                return Err(LambdaError::NotFound);
+            }
+            state.user(user_id)?;
+            state.votes.push(StoredVote {
+                user_id,
+                story_id,
+                comment_id,
+                value: vote.value,
+            });
+        }
+
+        Ok(state.tally(story_id, comment_id))
+    }
+
+    async fn vote_tally(&self, story_id: Option<i32>, comment_id: Option<i32>) -> Result<VoteTally, LambdaError> {
+        Ok(self.state().tally(story_id, comment_id))
+    }
+}
+
+#[axum::async_trait]
+impl VisitRepo for MemoryRepo {
+    async fn story_visit(&self, user_id: i32, story_id: i32) -> Result<StoryVisit, LambdaError> {
+        let mut state = self.state();
+        let visit = state.visit_mut(user_id, story_id)?;
+        let previous = StoredVisit {
+            seen_at: visit.seen_at.replace(now()),
+            ..*visit
+        };
+        state.visit(&previous)
+    }
+
+    async fn story_visits(&self, user_id: i32, story_ids: &[i32]) -> Result<Vec<StoryVisit>, LambdaError> {
+        let state = self.state();
+        state
+            .visits
+            .iter()
+            .filter(|visit| visit.user_id == user_id && story_ids.contains(&visit.story_id))
+            .map(|visit| state.visit(visit))
+            .collect()
+    }
+
+    async fn story_hide(&self, user_id: i32, story_id: i32, hidden: bool) -> Result<(), LambdaError> {
+        self.state().visit_mut(user_id, story_id)?.hidden = hidden;
+        Ok(())
+    }
+
+    async fn hidden_story_ids(&self, user_id: i32) -> Result<Vec<i32>, LambdaError> {
+        Ok(self
+            .state()
+            .visits
+            .iter()
+            .filter(|visit| visit.user_id == user_id && visit.hidden)
+            .map(|visit| visit.story_id)
+            .collect())
+    }
+}
+
+#[axum::async_trait]
+impl TagRepo for MemoryRepo {
+    async fn tag_list(&self) -> Result<Vec<Tag>, LambdaError> {
+        let state = self.state();
+        let mut tags: Vec<Tag> = state
+            .tags
+            .iter()
+            .map(|tag| Tag {
+                story_count: state
+                    .stories
+                    .iter()
+                    .filter(|stored| stored.story.tags.contains(&tag.name))
+                    .count() as i64,
+                ..tag.clone()
+            })
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tags)
+    }
+
+    async fn tag_create(&self, name: &str, description: Option<&str>) -> Result<Tag, LambdaError> {
+        if !is_tag_name(name) {
+            return Err(LambdaError::ValidationError(format!(
+                "tag {name:?} is not lowercase letters, digits and dashes"
+            )));
+        }
+        let mut state = self.state();
+        if state.tag(name).is_ok() {
+            return Err(LambdaError::ValidationError(format!("tag {name} already exists")));
+        }
+
+        let tag = Tag {
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            story_count: 0,
+        };
+        state.tags.push(tag.clone());
+        Ok(tag)
+    }
+
+    async fn tag_page(&self, name: &str, page: i64) -> Result<Vec<StoryListItem>, LambdaError> {
+        let state = self.state();
+        state.tag(name)?;
+        state
+            .newest()
+            .into_iter()
+            .filter(|story| story.tags.iter().any(|tag| tag == name))
+            .skip((page * PAGE_SIZE).max(0) as usize)
+            .take(PAGE_SIZE as usize)
+            .map(|story| state.list_item(story))
+            .collect()
+    }
+
+    async fn story_tags_set(&self, story_id: i32, names: &[String]) -> Result<Vec<String>, LambdaError> {
+        let mut state = self.state();
+        state.story(story_id).ok_or(LambdaError::NotFound)?;
+        let tags = state.story_tags(names)?;
+        let stored = state
+            .stories
+            .iter_mut()
+            .find(|stored| stored.story.id == story_id)
+            .ok_or(LambdaError::NotFound)?;
+        stored.story.tags = tags.clone();
+        Ok(tags)
+    }
+
+    async fn tag_preferences(&self, user_id: i32) -> Result<TagPreferences, LambdaError> {
+        let state = self.state();
+        let mut preferences = TagPreferences::default();
+        for preference in state.preferences.iter().filter(|preference| preference.user_id == user_id) {
+            match preference.muted {
+                true => preferences.muted.push(preference.name.clone()),
+                false => preferences.followed.push(preference.name.clone()),
+            }
+        }
+        preferences.followed.sort();
+        preferences.muted.sort();
+        Ok(preferences)
+    }
+
+    async fn tag_prefer(&self, user_id: i32, name: &str, preference: Option<TagPreference>) -> Result<(), LambdaError> {
+        let mut state = self.state();
+        state.tag(name)?;
+        state.user(user_id)?;
+        state
+            .preferences
+            .retain(|stored| !(stored.user_id == user_id && stored.name == name));
+        if let Some(preference) = preference {
+            state.preferences.push(StoredPreference {
+                user_id,
+                name: name.to_string(),
+                muted: preference == TagPreference::Mute,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[axum::async_trait]
+impl PollRepo for MemoryRepo {
+    async fn poll(&self, story_id: i32, user_id: Option<i32>) -> Result<Poll, LambdaError> {
+        self.state().poll(story_id, user_id)
+    }
+
+    async fn poll_vote(&self, story_id: i32, user_id: i32, option_ids: &[i32]) -> Result<Poll, LambdaError> {
+        let mut state = self.state();
+        state.user(user_id)?;
+        state.poll(story_id, Some(user_id))?.check_ballot(option_ids)?;
+
+        state
+            .ballots
+            .retain(|ballot| ballot.story_id != story_id || ballot.user_id != user_id);
+        state.ballots.extend(option_ids.iter().map(|&option_id| StoredBallot {
+            story_id,
+            user_id,
+            option_id,
+        }));
+        state.poll(story_id, Some(user_id))
+    }
+}
+
+#[axum::async_trait]
+impl DraftRepo for MemoryRepo {
+    async fn draft_save(&self, user_id: i32, draft: DraftArgs) -> Result<(), LambdaError> {
+        draft.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+        if draft.is_blank() {
+            return self.draft_discard(user_id, draft.story_id, draft.parent_id).await;
+        }
+
+        let mut state = self.state();
+        state.user(user_id)?;
+        if draft.story_id.is_some_and(|story_id| state.story(story_id).is_none()) {
+            return Err(LambdaError::NotFound);
+        }
+        if let Some(parent_id) = draft.parent_id {
+            state
+                .comment(parent_id)
+                .filter(|parent| Some(parent.story_id) == draft.story_id)
+                .ok_or(LambdaError::NotFound)?;
+        }
+
+        state.drafts.retain(|stored| {
+            !(stored.user_id == user_id
+                && stored.draft.story_id == draft.story_id
+                && stored.draft.parent_id == draft.parent_id)
+        });
+        state.drafts.push(StoredDraft {
+            user_id,
+            draft,
+            updated_at: now(),
+        });
+        Ok(())
+    }
+
+    async fn draft_discard(&self, user_id: i32, story_id: Option<i32>, parent_id: Option<i32>) -> Result<(), LambdaError> {
+        self.state().drafts.retain(|stored| {
+            !(stored.user_id == user_id && stored.draft.story_id == story_id && stored.draft.parent_id == parent_id)
+        });
+        Ok(())
+    }
+
+    async fn drafts(&self, user_id: i32, editor: Option<(Option<i32>, Option<i32>)>) -> Result<Vec<Draft>, LambdaError> {
+        let state = self.state();
+        // Saved drafts are appended, so the latest come last.
+        Ok(state
+            .drafts
+            .iter()
+            .rev()
+            .filter(|stored| stored.user_id == user_id)
+            .filter(|stored| editor.is_none_or(|editor| editor == (stored.draft.story_id, stored.draft.parent_id)))
+            .map(|stored| Draft {
+                story_id: stored.draft.story_id,
+                story_title: stored
+                    .draft
+                    .story_id
+                    .and_then(|story_id| state.story(story_id))
+                    .map(|story| story.title.clone()),
+                parent_id: stored.draft.parent_id,
+                title: stored.draft.title.clone(),
+                url: stored.draft.url.clone(),
+                text: stored.draft.text.clone(),
+                updated_at: stored.updated_at,
+            })
+            .collect())
+    }
+}
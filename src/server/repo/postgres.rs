@@ -0,0 +1,174 @@
+use super::{CommentRepo, DraftRepo, PollRepo, StoryRepo, TagRepo, UserRepo, VisitRepo, VoteRepo};
+use crate::{
+    model::{
+        Comment, CommentCreateArgs, Draft, DraftArgs, LambdaError, Poll, Story, StoryCreateArgs, StoryFilter,
+        StoryKind, StoryListItem, StoryVisit, Tag, TagPreference, TagPreferences, User, VoteArgs, VoteTally,
+    },
+    server::{self, admin, drafts, polls, preview::PreviewFetcher, tags, visits},
+};
+use sqlx::PgPool;
+
+/// The production backend, over the SQL helpers in [`crate::server`].
+#[derive(Clone, Debug)]
+pub struct PgRepo {
+    pool: PgPool,
+    preview_fetcher: PreviewFetcher,
+}
+
+impl PgRepo {
+    pub fn new(pool: PgPool, preview_fetcher: PreviewFetcher) -> Self {
+        PgRepo { pool, preview_fetcher }
+    }
+}
+
+#[axum::async_trait]
+impl StoryRepo for PgRepo {
+    async fn story_page(&self, page: i64, filter: &StoryFilter) -> Result<Vec<StoryListItem>, LambdaError> {
+        server::story_page(&self.pool, page, filter).await
+    }
+
+    async fn story_page_count(&self, filter: &StoryFilter) -> Result<i64, LambdaError> {
+        server::story_page_count(&self.pool, filter).await
+    }
+
+    async fn story_kind_page(
+        &self,
+        kind: StoryKind,
+        page: i64,
+        filter: &StoryFilter,
+    ) -> Result<Vec<StoryListItem>, LambdaError> {
+        server::story_kind_page(&self.pool, kind, page, filter).await
+    }
+
+    async fn story_by_id(&self, id: i32) -> Result<Story, LambdaError> {
+        server::story_by_id(&self.pool, id).await
+    }
+
+    async fn story_duplicate(&self, canonical_url: &str) -> Result<Option<Story>, LambdaError> {
+        Ok(server::story_duplicate(&self.pool, canonical_url).await?)
+    }
+
+    async fn story_insert(&self, story: StoryCreateArgs, author_id: i32) -> Result<Story, LambdaError> {
+        server::story_insert(&self.pool, self.preview_fetcher.clone(), story, author_id).await
+    }
+}
+
+#[axum::async_trait]
+impl CommentRepo for PgRepo {
+    async fn comment_insert(&self, comment: CommentCreateArgs, author_id: i32) -> Result<Comment, LambdaError> {
+        server::comment_insert(&self.pool, comment, author_id).await
+    }
+
+    async fn comments_for_story(&self, story_id: i32) -> Result<Vec<Comment>, LambdaError> {
+        server::comments_for_story(&self.pool, story_id).await
+    }
+
+    async fn comment_by_id(&self, id: i32) -> Result<Comment, LambdaError> {
+        server::comment_by_id(&self.pool, id).await
+    }
+
+    async fn comment_with_parents(&self, id: i32) -> Result<Vec<Comment>, LambdaError> {
+        server::comment_with_parents(&self.pool, id).await
+    }
+}
+
+#[axum::async_trait]
+impl UserRepo for PgRepo {
+    async fn user_by_name(&self, name: &str) -> Result<User, LambdaError> {
+        server::user_by_name(&self.pool, name).await
+    }
+
+    async fn user_insert(&self, name: &str) -> Result<User, LambdaError> {
+        admin::user_create(&self.pool, name, None, false).await
+    }
+}
+
+#[axum::async_trait]
+impl VoteRepo for PgRepo {
+    async fn vote_cast(
+        &self,
+        story_id: Option<i32>,
+        comment_id: Option<i32>,
+        vote: VoteArgs,
+        user_id: i32,
+    ) -> Result<VoteTally, LambdaError> {
+        server::vote_cast(&self.pool, story_id, comment_id, vote, user_id).await
+    }
+
+    async fn vote_tally(&self, story_id: Option<i32>, comment_id: Option<i32>) -> Result<VoteTally, LambdaError> {
+        server::vote_tally(&self.pool, story_id, comment_id).await
+    }
+}
+
+#[axum::async_trait]
+impl VisitRepo for PgRepo {
+    async fn story_visit(&self, user_id: i32, story_id: i32) -> Result<StoryVisit, LambdaError> {
+        visits::story_visit(&self.pool, user_id, story_id).await
+    }
+
+    async fn story_visits(&self, user_id: i32, story_ids: &[i32]) -> Result<Vec<StoryVisit>, LambdaError> {
+        visits::story_visits(&self.pool, user_id, story_ids).await
+    }
+
+    async fn story_hide(&self, user_id: i32, story_id: i32, hidden: bool) -> Result<(), LambdaError> {
+        visits::story_hide(&self.pool, user_id, story_id, hidden).await
+    }
+
+    async fn hidden_story_ids(&self, user_id: i32) -> Result<Vec<i32>, LambdaError> {
+        visits::hidden_story_ids(&self.pool, user_id).await
+    }
+}
+
+#[axum::async_trait]
+impl TagRepo for PgRepo {
+    async fn tag_list(&self) -> Result<Vec<Tag>, LambdaError> {
+        tags::tag_list(&self.pool).await
+    }
+
+    async fn tag_create(&self, name: &str, description: Option<&str>) -> Result<Tag, LambdaError> {
+        tags::tag_create(&self.pool, name, description).await
+    }
+
+    async fn tag_page(&self, name: &str, page: i64) -> Result<Vec<StoryListItem>, LambdaError> {
+        tags::tag_page(&self.pool, name, page).await
+    }
+
+    async fn story_tags_set(&self, story_id: i32, names: &[String]) -> Result<Vec<String>, LambdaError> {
+        tags::story_tags_set(&self.pool, story_id, names).await
+    }
+
+    async fn tag_preferences(&self, user_id: i32) -> Result<TagPreferences, LambdaError> {
+        tags::tag_preferences(&self.pool, user_id).await
+    }
+
+    async fn tag_prefer(&self, user_id: i32, name: &str, preference: Option<TagPreference>) -> Result<(), LambdaError> {
+        tags::tag_prefer(&self.pool, user_id, name, preference).await
+    }
+}
+
+#[axum::async_trait]
+impl PollRepo for PgRepo {
+    async fn poll(&self, story_id: i32, user_id: Option<i32>) -> Result<Poll, LambdaError> {
+        let mut conn = self.pool.acquire().await?;
+        polls::poll(&mut conn, story_id, user_id).await
+    }
+
+    async fn poll_vote(&self, story_id: i32, user_id: i32, option_ids: &[i32]) -> Result<Poll, LambdaError> {
+        polls::poll_vote(&self.pool, story_id, user_id, option_ids).await
+    }
+}
+
+#[axum::async_trait]
+impl DraftRepo for PgRepo {
+    async fn draft_save(&self, user_id: i32, draft: DraftArgs) -> Result<(), LambdaError> {
+        drafts::draft_save(&self.pool, user_id, draft).await
+    }
+
+    async fn draft_discard(&self, user_id: i32, story_id: Option<i32>, parent_id: Option<i32>) -> Result<(), LambdaError> {
+        drafts::draft_discard(&self.pool, user_id, story_id, parent_id).await
+    }
+
+    async fn drafts(&self, user_id: i32, editor: Option<(Option<i32>, Option<i32>)>) -> Result<Vec<Draft>, LambdaError> {
+        drafts::draft_list(&self.pool, user_id, editor).await
+    }
+}
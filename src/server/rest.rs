@@ -0,0 +1,571 @@
+use crate::{
+    features::canonical::canonicalize_url,
+    model::{
+        ssr::{AppState, ErrorBody},
+        BallotArgs, Comment, CommentCreateArgs, LambdaError, Poll, Story, StoryCreateArgs,
+        StoryListItem, TokenScope, User, VoteArgs, VoteTally,
+    },
+    server::{
+        account::{account_delete, personal_data, AccountDeleteArgs, AccountDeleted, PersonalData},
+        auth::Bearer,
+        ratelimit::Throttle,
+        story_filter,
+    },
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    IntoParams, Modify, OpenApi, ToSchema,
+};
+
+pub const PREFIX: &str = "/v1";
+
+type ApiResult = Result<Response, LambdaError>;
+
+/// Stable, versioned JSON API mounted under [`PREFIX`]. Unlike the `#[server]`
+/// functions, its paths and payloads are part of the public contract.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/stories", get(story_list).post(story_create))
+        .route("/stories/:id", get(story_get))
+        .route("/stories/:id/comments", get(story_comments).post(story_comment_create))
+        .route("/stories/:id/votes", get(story_votes).post(story_vote))
+        .route("/stories/:id/poll", get(story_poll).post(story_poll_vote))
+        .route("/comments/:id", get(comment_get))
+        .route("/comments/:id/votes", get(comment_votes).post(comment_vote))
+        .route("/users/:name", get(user_get))
+        .route("/me", delete(me_delete))
+        .route("/me/export", get(me_export))
+        .route("/openapi.json", get(openapi))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Lambda Function",
+        description = "Public REST API for stories, comments, users, votes and polls.",
+        license(name = "MIT OR Apache-2.0")
+    ),
+    paths(
+        story_list,
+        story_create,
+        story_get,
+        story_comments,
+        story_comment_create,
+        story_votes,
+        story_vote,
+        story_poll,
+        story_poll_vote,
+        comment_get,
+        comment_votes,
+        comment_vote,
+        user_get,
+        me_export,
+        me_delete,
+    ),
+    components(schemas(ErrorBody, LambdaError, PageLinks, TokenScope)),
+    modifiers(&BearerScheme)
+)]
+pub struct ApiDoc;
+
+struct BearerScheme;
+
+const SCOPES: &str = "A personal API token. Each token carries scopes: `read` to read, `submit` to post stories, \
+`comment` to comment, `vote` to vote, `write` to change the holder's own bookmarks, story visits, hidden \
+stories, tag preferences, drafts and notifications, and `account` to export or delete the holder's account.";
+
+impl Modify for BearerScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .description(Some(SCOPES))
+                        .build(),
+                ),
+            );
+        }
+    }
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct PageQuery {
+    /// Zero-based page number.
+    pub page: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub links: PageLinks,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
+pub struct PageLinks {
+    pub first: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    pub last: String,
+}
+
+impl PageLinks {
+    fn new(path: &str, page: i64, pages: i64) -> Self {
+        let last = (pages - 1).max(0);
+        let link = |page: i64| format!("{PREFIX}{path}?page={page}");
+        PageLinks {
+            first: link(0),
+            prev: (page > 0).then(|| link((page - 1).min(last))),
+            next: (page < last).then(|| link(page + 1)),
+            last: link(last),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct CommentBody {
+    pub text: String,
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+}
+
+/// Serializes `value` with a content-derived `ETag`, answering with
+/// `304 Not Modified` when the client already holds the same representation.
+fn conditional_json<T: Serialize>(headers: &HeaderMap, value: &T) -> ApiResult {
+    let body = serde_json::to_vec(value).map_err(|_| LambdaError::InternalServerError)?;
+    let etag = format!("\"{:x}\"", Sha256::digest(&body));
+
+    let matches = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        });
+
+    let cache_headers = [(header::ETAG, etag), (header::CACHE_CONTROL, "no-cache".to_string())];
+    if matches {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    Ok((
+        cache_headers,
+        [(header::CONTENT_TYPE, "application/json")],
+        body,
+    )
+        .into_response())
+}
+
+fn created<T: Serialize>(location: String, value: T) -> ApiResult {
+    Ok((StatusCode::CREATED, [(header::LOCATION, location)], Json(value)).into_response())
+}
+
+/// Points at a resource that already existed instead of creating another.
+fn see_other<T: Serialize>(location: String, value: T) -> ApiResult {
+    Ok((StatusCode::SEE_OTHER, [(header::LOCATION, location)], Json(value)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stories",
+    params(PageQuery),
+    responses(
+        (status = 200, body = Page<StoryListItem>),
+        (status = 304, description = "Not modified"),
+        (status = 400, body = ErrorBody),
+    )
+)]
+async fn story_list(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Query(query): Query<PageQuery>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    let page = query.page.unwrap_or(0);
+    if page < 0 {
+        return Err(LambdaError::InvalidData("page must not be negative".into()));
+    }
+
+    let filter = story_filter(&state.repo, &bearer, None).await?;
+    let data: Vec<StoryListItem> = state.story_cache.story_page(&*state.repo, page, &filter).await?;
+    let pages = state.repo.story_page_count(&filter).await?;
+
+    conditional_json(
+        &headers,
+        &Page {
+            data,
+            links: PageLinks::new("/stories", page, pages),
+        },
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/stories",
+    request_body = StoryCreateArgs,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, body = Story),
+        (status = 303, body = Story, description = "A recent story with the same link, posted instead"),
+        (status = 422, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 429, body = ErrorBody),
+    )
+)]
+async fn story_create(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    _: Throttle,
+    Json(story): Json<StoryCreateArgs>,
+) -> ApiResult {
+    let author_id = bearer.require(TokenScope::Submit)?;
+    let duplicate = match story.url.as_deref().and_then(canonicalize_url) {
+        Some(canonical_url) => state.repo.story_duplicate(&canonical_url).await?,
+        None => None,
+    };
+    if let Some(existing) = duplicate {
+        return see_other(format!("{PREFIX}/stories/{}", existing.id), existing);
+    }
+    let story: Story = state.repo.story_insert(story, author_id).await?;
+    state.story_cache.invalidate().await;
+
+    created(format!("{PREFIX}/stories/{}", story.id), story)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stories/{id}",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Story),
+        (status = 304, description = "Not modified"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn story_get(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    let story: Story = state.repo.story_by_id(id).await?;
+
+    conditional_json(&headers, &story)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stories/{id}/comments",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Vec<Comment>),
+        (status = 304, description = "Not modified"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn story_comments(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    state.repo.story_by_id(id).await?;
+    let comments: Vec<Comment> = state.repo.comments_for_story(id).await?;
+
+    conditional_json(&headers, &comments)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/stories/{id}/comments",
+    params(("id" = i32, Path)),
+    request_body = CommentBody,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, body = Comment),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody, description = "Blank text, or a parent on another story"),
+        (status = 429, body = ErrorBody),
+    )
+)]
+async fn story_comment_create(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    _: Throttle,
+    Path(id): Path<i32>,
+    Json(body): Json<CommentBody>,
+) -> ApiResult {
+    let author_id = bearer.require(TokenScope::Comment)?;
+    let args = CommentCreateArgs {
+        text: body.text,
+        parent_id: body.parent_id,
+        story_id: id,
+    };
+    let comment = state.repo.comment_insert(args, author_id).await?;
+
+    created(format!("{PREFIX}/comments/{}", comment.id), comment)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stories/{id}/votes",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = VoteTally),
+        (status = 304, description = "Not modified"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn story_votes(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    state.repo.story_by_id(id).await?;
+    let tally: VoteTally = state.repo.vote_tally(Some(id), None).await?;
+
+    conditional_json(&headers, &tally)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/stories/{id}/votes",
+    params(("id" = i32, Path)),
+    request_body = VoteArgs,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = VoteTally),
+        (status = 400, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 429, body = ErrorBody),
+    )
+)]
+async fn story_vote(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    _: Throttle,
+    Path(id): Path<i32>,
+    Json(vote): Json<VoteArgs>,
+) -> ApiResult {
+    let user_id = bearer.require(TokenScope::Vote)?;
+    let tally = state.repo.vote_cast(Some(id), None, vote, user_id).await?;
+
+    Ok(Json(tally).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stories/{id}/poll",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Poll, description = "Results, with the token owner's ballot"),
+        (status = 304, description = "Not modified"),
+        (status = 404, body = ErrorBody, description = "The story is not a poll"),
+    )
+)]
+async fn story_poll(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    let voter = bearer.owner();
+    let poll: Poll = state.repo.poll(id, voter).await?;
+
+    conditional_json(&headers, &poll)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/stories/{id}/poll",
+    params(("id" = i32, Path)),
+    request_body = BallotArgs,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Poll),
+        (status = 400, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody, description = "The story is not a poll"),
+        (status = 429, body = ErrorBody),
+    )
+)]
+async fn story_poll_vote(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    _: Throttle,
+    Path(id): Path<i32>,
+    Json(ballot): Json<BallotArgs>,
+) -> ApiResult {
+    let user_id = bearer.require(TokenScope::Vote)?;
+    let poll = state.repo.poll_vote(id, user_id, &ballot.option_ids).await?;
+
+    Ok(Json(poll).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/comments/{id}",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Comment),
+        (status = 304, description = "Not modified"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn comment_get(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    let comment = state.repo.comment_by_id(id).await?;
+
+    conditional_json(&headers, &comment)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/comments/{id}/votes",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = VoteTally),
+        (status = 304, description = "Not modified"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn comment_votes(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    state.repo.comment_by_id(id).await?;
+    let tally = state.repo.vote_tally(None, Some(id)).await?;
+
+    conditional_json(&headers, &tally)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/comments/{id}/votes",
+    params(("id" = i32, Path)),
+    request_body = VoteArgs,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = VoteTally),
+        (status = 400, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 429, body = ErrorBody),
+    )
+)]
+async fn comment_vote(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    _: Throttle,
+    Path(id): Path<i32>,
+    Json(vote): Json<VoteArgs>,
+) -> ApiResult {
+    let user_id = bearer.require(TokenScope::Vote)?;
+    let tally = state.repo.vote_cast(None, Some(id), vote, user_id).await?;
+
+    Ok(Json(tally).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/users/{name}",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, body = User),
+        (status = 304, description = "Not modified"),
+        (status = 404, body = ErrorBody),
+    )
+)]
+async fn user_get(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult {
+    bearer.allow_read()?;
+    let user: User = state.repo.user_by_name(&name).await?;
+
+    conditional_json(&headers, &user)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/me/export",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = PersonalData, description = "Everything stored about the token's owner"),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+    )
+)]
+async fn me_export(State(state): State<AppState>, bearer: Bearer) -> ApiResult {
+    let user_id = bearer.require(TokenScope::Account)?;
+    let data = personal_data(&state.pool, user_id).await?;
+    let disposition = format!("attachment; filename=\"account-{user_id}.json\"");
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(data)).into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/me",
+    request_body = AccountDeleteArgs,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = AccountDeleted, description = "The account is gone; its content belongs to the tombstone user"),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+        (status = 429, body = ErrorBody),
+    )
+)]
+async fn me_delete(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    _: Throttle,
+    Json(args): Json<AccountDeleteArgs>,
+) -> ApiResult {
+    let user_id = bearer.require(TokenScope::Account)?;
+    let deleted = account_delete(&state.pool, user_id, args.content, Some(user_id)).await?;
+    state.story_cache.invalidate().await;
+
+    Ok(Json(deleted).into_response())
+}
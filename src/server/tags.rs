@@ -0,0 +1,309 @@
+use crate::{
+    constants::{PAGE_SIZE, PROFILE, STORY, STORY_MAX_TAGS, TAG},
+    features::{
+        poll::poll_results_text,
+        tag::{is_tag_name, normalize_tags},
+    },
+    model::{ssr::AppState, LambdaError, Poll, StoryKind, StoryListItem, Tag, TagPreference, TagPreferences},
+    server::{auth::Bearer, telemetry},
+};
+use atom_syndication::{Category, Content, Entry, Feed, Link, Person, Text};
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::{field::Empty, instrument};
+
+/// The vocabulary, alphabetically, with how many stories carry each tag.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn tag_list(pool: &PgPool) -> Result<Vec<Tag>, LambdaError> {
+    let tags = sqlx::query_as!(
+        Tag,
+        r#"
+            SELECT t.name, t.description, COUNT(st.story_id) as "story_count!"
+            FROM tags t
+            LEFT JOIN story_tags st ON st.tag_id = t.id
+            GROUP BY t.id
+            ORDER BY t.name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(tags.len());
+
+    Ok(tags)
+}
+
+pub async fn tag_create(pool: &PgPool, name: &str, description: Option<&str>) -> Result<Tag, LambdaError> {
+    if !is_tag_name(name) {
+        return Err(LambdaError::ValidationError(format!(
+            "tag {name:?} is not lowercase letters, digits and dashes"
+        )));
+    }
+
+    sqlx::query_as!(
+        Tag,
+        r#"
+            INSERT INTO tags (name, description) VALUES ($1, $2)
+            ON CONFLICT (name) DO NOTHING
+            RETURNING name, description, 0::bigint as "story_count!"
+        "#,
+        name,
+        description
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| LambdaError::ValidationError(format!("tag {name} already exists")))
+}
+
+/// Removes a tag from the vocabulary and from every story that carried it.
+pub async fn tag_delete(pool: &PgPool, name: &str) -> Result<(), LambdaError> {
+    let result = sqlx::query!("DELETE FROM tags WHERE name = $1", name)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(LambdaError::NotFound);
+    }
+    Ok(())
+}
+
+/// A page of the stories with a tag, newest first. Tags outside the
+/// vocabulary are not found.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn tag_page(pool: &PgPool, name: &str, page: i64) -> Result<Vec<StoryListItem>, LambdaError> {
+    let tag_id = sqlx::query_scalar!("SELECT id FROM tags WHERE name = $1", name)
+        .fetch_one(pool)
+        .await?;
+
+    let stories = sqlx::query_as!(
+        StoryListItem,
+        r#"
+            SELECT
+                s.id,
+                s.title,
+                s.text,
+                s.url,
+                s.created_at,
+                u.display_name as author_name,
+                s.score as "rating?",
+                s.comment_count as "comment_count?",
+                s.kind as "kind: StoryKind",
+                COALESCE(stn.tags, '{}') as "tags!"
+            FROM
+                stories s
+            JOIN
+                story_tags st ON st.story_id = s.id AND st.tag_id = $1
+            JOIN
+                users u ON s.author_id = u.id
+            LEFT JOIN
+                story_tag_names stn ON stn.story_id = s.id
+            ORDER BY
+                s.created_at DESC
+            LIMIT $2
+            OFFSET $3
+        "#,
+        tag_id,
+        PAGE_SIZE,
+        page * PAGE_SIZE,
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(stories.len());
+
+    Ok(stories)
+}
+
+/// Replaces a story's tags inside the caller's transaction, returning them
+/// sorted. Names outside the vocabulary are refused.
+pub async fn save(conn: &mut PgConnection, story_id: i32, names: &[String]) -> Result<Vec<String>, LambdaError> {
+    let names = normalize_tags(names);
+    if names.len() > STORY_MAX_TAGS {
+        return Err(LambdaError::ValidationError(format!(
+            "a story has at most {STORY_MAX_TAGS} tags"
+        )));
+    }
+
+    let tags = sqlx::query!("SELECT id, name FROM tags WHERE name = ANY($1)", &names)
+        .fetch_all(&mut *conn)
+        .await?;
+    if let Some(unknown) = names.iter().find(|name| !tags.iter().any(|tag| &tag.name == *name)) {
+        return Err(LambdaError::ValidationError(format!("tag {unknown} does not exist")));
+    }
+
+    let tag_ids: Vec<i32> = tags.iter().map(|tag| tag.id).collect();
+    sqlx::query!("DELETE FROM story_tags WHERE story_id = $1", story_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO story_tags (story_id, tag_id) SELECT $1, UNNEST($2::integer[])",
+        story_id,
+        &tag_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(names)
+}
+
+/// Retags a story, as moderators do.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_tags_set(pool: &PgPool, story_id: i32, names: &[String]) -> Result<Vec<String>, LambdaError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query_scalar!("SELECT id FROM stories WHERE id = $1 FOR UPDATE", story_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let tags = save(&mut tx, story_id, names).await?;
+    tx.commit().await?;
+    telemetry::record_rows(tags.len());
+
+    Ok(tags)
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn tag_preferences(pool: &PgPool, user_id: i32) -> Result<TagPreferences, LambdaError> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT t.name, p.muted
+            FROM tag_preferences p
+            JOIN tags t ON t.id = p.tag_id
+            WHERE p.user_id = $1
+            ORDER BY t.name
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    let (muted, followed): (Vec<_>, Vec<_>) = rows.into_iter().partition(|row| row.muted);
+    Ok(TagPreferences {
+        followed: followed.into_iter().map(|row| row.name).collect(),
+        muted: muted.into_iter().map(|row| row.name).collect(),
+    })
+}
+
+/// Follows or mutes a tag for the reader, or forgets either with `None`.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn tag_prefer(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+    preference: Option<TagPreference>,
+) -> Result<(), LambdaError> {
+    let tag_id = sqlx::query_scalar!("SELECT id FROM tags WHERE name = $1", name)
+        .fetch_one(pool)
+        .await?;
+
+    let result = match preference {
+        Some(preference) => {
+            sqlx::query!(
+                r#"
+                    INSERT INTO tag_preferences (user_id, tag_id, muted) VALUES ($1, $2, $3)
+                    ON CONFLICT (user_id, tag_id) DO UPDATE SET muted = EXCLUDED.muted
+                "#,
+                user_id,
+                tag_id,
+                preference == TagPreference::Mute
+            )
+            .execute(pool)
+            .await?
+        }
+        None => {
+            sqlx::query!("DELETE FROM tag_preferences WHERE user_id = $1 AND tag_id = $2", user_id, tag_id)
+                .execute(pool)
+                .await?
+        }
+    };
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(())
+}
+
+/// A feed of each tag's newest stories next to its page, e.g.
+/// `/tag/rust/feed.atom`.
+pub fn router() -> Router<AppState> {
+    Router::new().route(&format!("/{TAG}/:name/feed.atom"), get(export_atom))
+}
+
+async fn export_atom(
+    State(state): State<AppState>,
+    bearer: Bearer,
+    Path(name): Path<String>,
+) -> Result<Response, LambdaError> {
+    bearer.allow_read()?;
+    let stories = state.repo.tag_page(&name, 0).await?;
+    let mut polls = Vec::new();
+    for story in stories.iter().filter(|story| story.kind == StoryKind::Poll) {
+        match state.repo.poll(story.id, None).await {
+            Ok(poll) => polls.push(poll),
+            Err(LambdaError::NotFound) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    let feed = tag_feed(&name, &stories, &polls, &state.site.name, state.site.origin());
+
+    Ok(([(header::CONTENT_TYPE, "application/atom+xml")], feed.to_string()).into_response())
+}
+
+/// Entries for poll stories carry the results of those in `polls`.
+pub fn tag_feed(name: &str, stories: &[StoryListItem], polls: &[Poll], site_name: &str, origin: &str) -> Feed {
+    let page = format!("{origin}/{TAG}/{name}");
+    let link = |href: String, rel: &str| Link {
+        href,
+        rel: rel.to_string(),
+        ..Link::default()
+    };
+
+    let entries: Vec<Entry> = stories
+        .iter()
+        .map(|story| {
+            let story_page = format!("{origin}/{STORY}/{}", story.id);
+            let mut links = vec![link(story_page.clone(), "alternate")];
+            links.extend(story.url.clone().map(|url| link(url, "related")));
+
+            let mut entry = Entry::default();
+            entry.set_id(story_page);
+            entry.set_title(story.title.clone());
+            entry.set_updated(story.created_at);
+            entry.set_published(Some(story.created_at));
+            entry.set_links(links);
+            entry.set_authors(vec![Person {
+                name: story.author_name.clone(),
+                uri: Some(format!("{origin}/{PROFILE}/{}", story.author_name)),
+                ..Person::default()
+            }]);
+            entry.set_categories(
+                story
+                    .tags
+                    .iter()
+                    .map(|tag| Category {
+                        term: tag.clone(),
+                        ..Category::default()
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            entry.set_summary(story.text.clone().map(Text::plain));
+            entry.set_content(polls.iter().find(|poll| poll.story_id == story.id).map(|poll| Content {
+                value: Some(poll_results_text(poll)),
+                content_type: Some("text".into()),
+                ..Content::default()
+            }));
+            entry
+        })
+        .collect();
+
+    let mut feed = Feed::default();
+    feed.set_id(page.clone());
+    feed.set_title(format!("{name} on {site_name}"));
+    feed.set_links(vec![link(format!("{page}/feed.atom"), "self"), link(page, "alternate")]);
+    if let Some(latest) = stories.first() {
+        feed.set_updated(latest.created_at);
+    }
+    feed.set_entries(entries);
+    feed
+}
@@ -0,0 +1,99 @@
+use crate::server::metrics;
+use opentelemetry::{global, trace::TraceError, trace::TracerProvider as _, KeyValue};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace::TracerProvider, Resource};
+use std::{env, str::FromStr};
+use thiserror::Error;
+use tracing::Span;
+use tracing_subscriber::{
+    layer::SubscriberExt,
+    util::{SubscriberInitExt, TryInitError},
+    EnvFilter,
+};
+
+/// Selects the span exporter, using the variable name from the OpenTelemetry
+/// SDK specification. The OTLP exporter reads its endpoint and headers from the
+/// standard `OTEL_EXPORTER_OTLP_*` variables.
+pub const EXPORTER_VAR: &str = "OTEL_TRACES_EXPORTER";
+
+const SERVICE_NAME_VAR: &str = "OTEL_SERVICE_NAME";
+const DEFAULT_FILTER: &str = "info";
+
+#[derive(Error, Debug)]
+pub enum TelemetryError {
+    #[error("unknown {EXPORTER_VAR} value {0:?}, expected otlp, stdout or none")]
+    UnknownExporter(String),
+    #[error(transparent)]
+    Exporter(#[from] TraceError),
+    #[error(transparent)]
+    Subscriber(#[from] TryInitError),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Exporter {
+    Otlp,
+    #[default]
+    Stdout,
+    None,
+}
+
+impl FromStr for Exporter {
+    type Err = TelemetryError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "otlp" => Ok(Exporter::Otlp),
+            "stdout" | "console" => Ok(Exporter::Stdout),
+            "none" => Ok(Exporter::None),
+            other => Err(TelemetryError::UnknownExporter(other.to_string())),
+        }
+    }
+}
+
+impl Exporter {
+    pub fn from_env() -> Result<Self, TelemetryError> {
+        env::var(EXPORTER_VAR).map_or(Ok(Exporter::default()), |value| value.parse())
+    }
+}
+
+/// Installs the W3C trace context propagator, the tracer provider and a
+/// `tracing` subscriber forwarding spans to it. The returned provider should be
+/// shut down on exit so batched spans are flushed.
+pub fn init() -> Result<TracerProvider, TelemetryError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let service_name = env::var(SERVICE_NAME_VAR).unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string());
+    let builder = TracerProvider::builder().with_resource(Resource::new([KeyValue::new("service.name", service_name)]));
+    let provider = match Exporter::from_env()? {
+        Exporter::Otlp => builder.with_batch_exporter(
+            opentelemetry_otlp::SpanExporter::builder().with_http().build()?,
+            runtime::Tokio,
+        ),
+        Exporter::Stdout => builder.with_simple_exporter(opentelemetry_stdout::SpanExporter::default()),
+        Exporter::None => builder,
+    }
+    .build();
+    global::set_tracer_provider(provider.clone());
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME"))))
+        .try_init()?;
+
+    Ok(provider)
+}
+
+/// Span wrapping one server function call, named after the function rather
+/// than its hashed URL.
+pub fn server_fn_span(path: &str) -> Span {
+    let function = metrics::server_fn_name(path).unwrap_or(path);
+    tracing::info_span!("server_fn", otel.name = function, function)
+}
+
+/// Records how many rows the enclosing query span returned or affected.
+pub fn record_rows(rows: impl TryInto<i64>) {
+    if let Ok(rows) = rows.try_into() {
+        Span::current().record("rows", rows);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    model::{LambdaError, StoryVisit},
+    server::telemetry,
+};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use sqlx::PgPool;
+use tracing::{field::Empty, instrument};
+
+/// Records that the reader opened a story, returning their state from
+/// before, so the caller can tell which comments are new to them.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_visit(pool: &PgPool, user_id: i32, story_id: i32) -> Result<StoryVisit, LambdaError> {
+    let row = sqlx::query!(
+        r#"
+            WITH previous AS (
+                SELECT seen_at, hidden_at FROM story_visits WHERE user_id = $1 AND story_id = $2
+            ), visited AS (
+                INSERT INTO story_visits (user_id, story_id, seen_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, story_id) DO UPDATE SET seen_at = EXCLUDED.seen_at
+            )
+            SELECT
+                p.seen_at as "seen_at?",
+                p.hidden_at IS NOT NULL as "hidden!",
+                ARRAY(
+                    SELECT c.id FROM comments c
+                    WHERE c.story_id = $2 AND c.created_at > p.seen_at AND c.author_id <> $1
+                    ORDER BY c.id
+                ) as "new_comment_ids!"
+            FROM (SELECT) one
+            LEFT JOIN previous p ON TRUE
+        "#,
+        user_id,
+        story_id,
+        Local::now().into()
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(StoryVisit {
+        story_id,
+        seen_at: row.seen_at.map(Into::into),
+        new_comment_ids: row.new_comment_ids,
+        hidden: row.hidden,
+    })
+}
+
+/// The reader's state for those of `story_ids` they opened or hid.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_visits(pool: &PgPool, user_id: i32, story_ids: &[i32]) -> Result<Vec<StoryVisit>, LambdaError> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                v.story_id,
+                v.seen_at,
+                v.hidden_at IS NOT NULL as "hidden!",
+                ARRAY(
+                    SELECT c.id FROM comments c
+                    WHERE c.story_id = v.story_id AND c.created_at > v.seen_at AND c.author_id <> v.user_id
+                    ORDER BY c.id
+                ) as "new_comment_ids!"
+            FROM story_visits v
+            WHERE v.user_id = $1 AND v.story_id = ANY($2)
+        "#,
+        user_id,
+        story_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| StoryVisit {
+            story_id: row.story_id,
+            seen_at: row.seen_at.map(Into::into),
+            new_comment_ids: row.new_comment_ids,
+            hidden: row.hidden,
+        })
+        .collect())
+}
+
+/// Comments on each of `story_ids` since the matching `seen_at`, for readers
+/// who keep their visits themselves. Comments by `user_id`, when known, are
+/// left out.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn new_comments(
+    pool: &PgPool,
+    story_ids: &[i32],
+    seen_at: &[DateTime<FixedOffset>],
+    user_id: Option<i32>,
+) -> Result<Vec<StoryVisit>, LambdaError> {
+    if story_ids.len() != seen_at.len() {
+        return Err(LambdaError::ValidationError("each story needs one visit time".into()));
+    }
+    let seen_at: Vec<DateTime<Utc>> = seen_at.iter().map(|at| at.with_timezone(&Utc)).collect();
+
+    let rows = sqlx::query!(
+        r#"
+            SELECT
+                v.story_id as "story_id!",
+                v.seen_at as "seen_at!",
+                ARRAY(
+                    SELECT c.id FROM comments c
+                    WHERE c.story_id = v.story_id AND c.created_at > v.seen_at AND c.author_id IS DISTINCT FROM $3
+                    ORDER BY c.id
+                ) as "new_comment_ids!"
+            FROM UNNEST($1::integer[], $2::timestamptz[]) AS v(story_id, seen_at)
+        "#,
+        story_ids,
+        &seen_at,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| StoryVisit {
+            story_id: row.story_id,
+            seen_at: Some(row.seen_at.into()),
+            new_comment_ids: row.new_comment_ids,
+            hidden: false,
+        })
+        .collect())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn story_hide(pool: &PgPool, user_id: i32, story_id: i32, hidden: bool) -> Result<(), LambdaError> {
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO story_visits (user_id, story_id, hidden_at)
+            VALUES ($1, $2, CASE WHEN $3 THEN NOW() END)
+            ON CONFLICT (user_id, story_id) DO UPDATE SET
+                hidden_at = CASE WHEN $3 THEN COALESCE(story_visits.hidden_at, NOW()) END
+        "#,
+        user_id,
+        story_id,
+        hidden
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn hidden_story_ids(pool: &PgPool, user_id: i32) -> Result<Vec<i32>, LambdaError> {
+    let ids = sqlx::query_scalar!(
+        "SELECT story_id FROM story_visits WHERE user_id = $1 AND hidden_at IS NOT NULL",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(ids.len());
+
+    Ok(ids)
+}
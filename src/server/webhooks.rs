@@ -0,0 +1,377 @@
+use crate::{
+    constants::{
+        WEBHOOK_BACKOFF_SECS, WEBHOOK_BATCH_SIZE, WEBHOOK_MAX_ATTEMPTS, WEBHOOK_POLL_SECS,
+        WEBHOOK_TIMEOUT_SECS,
+    },
+    model::{LambdaError, Webhook, WebhookCreateArgs, WebhookCreated, WebhookDelivery, WebhookEvent},
+    server::{
+        auth::SITE_USER_ID,
+        health::Shutdown,
+        preview::{check_url, PublicResolver},
+        telemetry,
+    },
+};
+use chrono::{Duration as ChronoDuration, Local};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use reqwest::{header::CONTENT_TYPE, redirect, Client};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+use tracing::{field::Empty, instrument};
+use typed_builder::TypedBuilder;
+use url::Url;
+use validator::Validate;
+
+pub const SIGNATURE_HEADER: &str = "x-lambda-signature";
+pub const EVENT_HEADER: &str = "x-lambda-event";
+pub const DELIVERY_HEADER: &str = "x-lambda-delivery";
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `body` keyed with the
+/// subscription secret, as sent in [`SIGNATURE_HEADER`].
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    format!("sha256={}", digest.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+}
+
+/// Queues `data` for every subscription listening to `event`. Delivery happens
+/// later in [`WebhookDispatcher::run`], so this never blocks on receivers.
+/// Subscriptions of the shared site user, which any browser could once
+/// register, get nothing.
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn enqueue<T: Serialize>(pool: &PgPool, event: WebhookEvent, data: &T) -> Result<u64, LambdaError> {
+    let payload = serde_json::json!({
+        "event": event,
+        "created_at": Local::now(),
+        "data": data,
+    });
+
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload, next_attempt_at, created_at)
+            SELECT id, $1, $2, NOW(), NOW()
+            FROM webhooks
+            WHERE deleted_at IS NULL AND $1 = ANY(events) AND user_id <> $3
+        "#,
+        event.as_str(),
+        payload,
+        SITE_USER_ID
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    Ok(result.rows_affected())
+}
+
+/// Enqueues an event, logging rather than failing the request that caused it.
+pub async fn emit<T: Serialize>(pool: &PgPool, event: WebhookEvent, data: &T) {
+    if let Err(err) = enqueue(pool, event, data).await {
+        leptos::logging::error!("Failed to enqueue {} webhooks: {err}", event.as_str());
+    }
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn webhook_create(
+    pool: &PgPool,
+    user_id: i32,
+    args: WebhookCreateArgs,
+    allow_private: bool,
+) -> Result<WebhookCreated, LambdaError> {
+    args.validate().map_err(|err| LambdaError::ValidationError(err.to_string()))?;
+    if user_id == SITE_USER_ID {
+        return Err(LambdaError::ValidationError("the site account cannot subscribe to webhooks".into()));
+    }
+    let events = args.events();
+    if events.is_empty() {
+        return Err(LambdaError::ValidationError("at least one event is required".into()));
+    }
+    let url = Url::parse(&args.url).map_err(|err| LambdaError::InvalidData(err.to_string()))?;
+    check_url(&url, allow_private).map_err(|err| LambdaError::InvalidData(err.to_string()))?;
+
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let secret: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    let event_names: Vec<String> = events.iter().map(|event| event.as_str().to_string()).collect();
+
+    let row = sqlx::query!(
+        r#"
+            INSERT INTO webhooks (user_id, url, secret, events, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, created_at
+        "#,
+        user_id,
+        args.url,
+        secret,
+        &event_names,
+        Local::now().into()
+    )
+    .fetch_one(pool)
+    .await?;
+    telemetry::record_rows(1);
+
+    Ok(WebhookCreated {
+        webhook: Webhook {
+            id: row.id,
+            url: args.url,
+            events,
+            created_at: row.created_at.into(),
+        },
+        secret,
+    })
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn webhook_list(pool: &PgPool, user_id: i32) -> Result<Vec<Webhook>, LambdaError> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT id, url, events, created_at
+            FROM webhooks
+            WHERE user_id = $1 AND deleted_at IS NULL
+            ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Webhook {
+            id: row.id,
+            url: row.url,
+            events: row.events.iter().filter_map(|event| event.parse().ok()).collect(),
+            created_at: row.created_at.into(),
+        })
+        .collect())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn webhook_delete(pool: &PgPool, user_id: i32, id: i32) -> Result<(), LambdaError> {
+    let result = sqlx::query!(
+        r#"UPDATE webhooks SET deleted_at = NOW() WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL"#,
+        id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+    telemetry::record_rows(result.rows_affected());
+
+    if result.rows_affected() == 0 {
+        return Err(LambdaError::NotFound);
+    }
+    Ok(())
+}
+
+#[instrument(skip_all, fields(rows = Empty))]
+pub async fn delivery_list(pool: &PgPool, user_id: i32, webhook_id: i32) -> Result<Vec<WebhookDelivery>, LambdaError> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT d.id, d.webhook_id, d.event, d.status, d.attempts, d.last_status_code,
+                d.last_error, d.created_at, d.delivered_at
+            FROM webhook_deliveries d
+            JOIN webhooks w ON d.webhook_id = w.id
+            WHERE w.id = $1 AND w.user_id = $2
+            ORDER BY d.created_at DESC
+            LIMIT 50
+        "#,
+        webhook_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    telemetry::record_rows(rows.len());
+
+    Ok(rows
+        .into_iter()
+        .map(|row| WebhookDelivery {
+            id: row.id,
+            webhook_id: row.webhook_id,
+            event: row.event,
+            status: row.status,
+            attempts: row.attempts,
+            last_status_code: row.last_status_code,
+            last_error: row.last_error,
+            created_at: row.created_at.into(),
+            delivered_at: row.delivered_at.map(Into::into),
+        })
+        .collect())
+}
+
+/// Outcome of a single delivery attempt.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Attempt {
+    Delivered(u16),
+    Failed(Option<u16>, String),
+}
+
+/// Background worker draining `webhook_deliveries`. Failed attempts are retried
+/// with exponential backoff until [`WEBHOOK_MAX_ATTEMPTS`] is reached.
+#[derive(Clone, Debug, TypedBuilder)]
+pub struct WebhookDispatcher {
+    #[builder(default = Duration::from_secs(WEBHOOK_TIMEOUT_SECS))]
+    pub timeout: Duration,
+    #[builder(default = Duration::from_secs(WEBHOOK_POLL_SECS))]
+    pub poll_interval: Duration,
+    #[builder(default = WEBHOOK_MAX_ATTEMPTS)]
+    pub max_attempts: i32,
+    #[builder(default = false)]
+    pub allow_private: bool,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl WebhookDispatcher {
+    fn client(&self) -> Result<Client, reqwest::Error> {
+        Client::builder()
+            .timeout(self.timeout)
+            .redirect(redirect::Policy::none())
+            // A proxy would resolve hosts itself, past the resolver's checks.
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver {
+                allow_private: self.allow_private,
+            }))
+            .user_agent(concat!("news/", env!("CARGO_PKG_VERSION")))
+            .build()
+    }
+
+    /// POSTs one signed payload to `url`.
+    pub async fn deliver(&self, url: &str, secret: &str, delivery_id: i64, event: &str, body: Vec<u8>) -> Attempt {
+        let client = match self.client() {
+            Ok(client) => client,
+            Err(err) => return Attempt::Failed(None, err.to_string()),
+        };
+        let parsed = match Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(err) => return Attempt::Failed(None, err.to_string()),
+        };
+        if let Err(err) = check_url(&parsed, self.allow_private) {
+            return Attempt::Failed(None, err.to_string());
+        }
+
+        let response = client
+            .post(parsed)
+            .header(CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, sign(secret, &body))
+            .header(EVENT_HEADER, event)
+            .header(DELIVERY_HEADER, delivery_id.to_string())
+            .body(body)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => Attempt::Delivered(response.status().as_u16()),
+            Ok(response) => Attempt::Failed(
+                Some(response.status().as_u16()),
+                format!("receiver answered {}", response.status()),
+            ),
+            Err(err) => Attempt::Failed(None, err.to_string()),
+        }
+    }
+
+    /// Claims and attempts one batch of due deliveries, returning how many were
+    /// attempted. Claiming pushes a delivery's next attempt past the time the
+    /// whole batch may take, so no transaction stays open while receivers
+    /// answer and a worker that dies mid-batch only delays its deliveries.
+    #[instrument(level = "debug", skip_all, fields(rows = Empty))]
+    pub async fn tick(&self, pool: &PgPool) -> Result<usize, LambdaError> {
+        let lease = ChronoDuration::from_std(self.timeout * (WEBHOOK_BATCH_SIZE as u32 + 1))
+            .map_err(|_| LambdaError::InternalServerError)?;
+        let due = sqlx::query!(
+            r#"
+                UPDATE webhook_deliveries d
+                SET next_attempt_at = $2
+                FROM webhooks w
+                WHERE d.webhook_id = w.id
+                    AND d.id IN (
+                        SELECT d.id
+                        FROM webhook_deliveries d
+                        JOIN webhooks w ON d.webhook_id = w.id
+                        WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND w.deleted_at IS NULL
+                        ORDER BY d.next_attempt_at
+                        LIMIT $1
+                        FOR UPDATE OF d SKIP LOCKED
+                    )
+                RETURNING d.id, d.event, d.payload, d.attempts, w.url, w.secret
+            "#,
+            WEBHOOK_BATCH_SIZE,
+            (Local::now() + lease).into()
+        )
+        .fetch_all(pool)
+        .await?;
+        telemetry::record_rows(due.len());
+
+        for delivery in &due {
+            let body = serde_json::to_vec(&delivery.payload).map_err(|_| LambdaError::InternalServerError)?;
+            let attempts = delivery.attempts + 1;
+
+            match self.deliver(&delivery.url, &delivery.secret, delivery.id, &delivery.event, body).await {
+                Attempt::Delivered(code) => {
+                    sqlx::query!(
+                        r#"
+                            UPDATE webhook_deliveries
+                            SET status = 'delivered', attempts = $2, last_status_code = $3,
+                                last_error = NULL, delivered_at = NOW()
+                            WHERE id = $1
+                        "#,
+                        delivery.id,
+                        attempts,
+                        code as i32
+                    )
+                    .execute(pool)
+                    .await?;
+                }
+                Attempt::Failed(code, error) => {
+                    let status = if attempts >= self.max_attempts { "failed" } else { "pending" };
+                    let backoff = ChronoDuration::seconds(WEBHOOK_BACKOFF_SECS << (attempts - 1).min(16));
+                    sqlx::query!(
+                        r#"
+                            UPDATE webhook_deliveries
+                            SET status = $2, attempts = $3, last_status_code = $4, last_error = $5,
+                                next_attempt_at = $6
+                            WHERE id = $1
+                        "#,
+                        delivery.id,
+                        status,
+                        attempts,
+                        code.map(i32::from),
+                        error,
+                        (Local::now() + backoff).into()
+                    )
+                    .execute(pool)
+                    .await?;
+                }
+            }
+        }
+
+        Ok(due.len())
+    }
+
+    /// Drains due deliveries until `shutdown` is triggered, finishing the batch
+    /// in progress before returning.
+    pub async fn run(self, pool: PgPool, shutdown: Shutdown) {
+        while !shutdown.is_triggered() {
+            let idle = match self.tick(&pool).await {
+                Ok(attempted) => attempted == 0,
+                Err(err) => {
+                    leptos::logging::error!("Webhook dispatch failed: {err}");
+                    true
+                }
+            };
+            if idle {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.poll_interval) => {},
+                    _ = shutdown.triggered() => {},
+                }
+            }
+        }
+    }
+}
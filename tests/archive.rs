@@ -0,0 +1,76 @@
+#![cfg(feature = "ssr")]
+
+use news::{
+    model::{PollArgs, StoryCreateArgs, StoryKind},
+    server::{
+        admin, archive, audit,
+        preview::PreviewFetcher,
+        repo::{PgRepo, PollRepo, StoryRepo, UserRepo},
+    },
+};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+async fn scratch(admin_conn: &mut PgConnection, url: &str, name: &str) -> PgPool {
+    admin_conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str()).await.unwrap();
+    let (base, _) = url.rsplit_once('/').unwrap();
+    let pool = PgPool::connect(&format!("{base}/{name}")).await.unwrap();
+    admin::MIGRATOR.run(&pool).await.unwrap();
+    pool
+}
+
+async fn count(pool: &PgPool, table: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn archives_keep_tags_polls_and_the_audit_trail() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return;
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let (source_name, target_name) = (format!("news_archive_{nanos}"), format!("news_restore_{nanos}"));
+    let mut admin_conn = PgConnection::connect(&url).await.unwrap();
+    let source = scratch(&mut admin_conn, &url, &source_name).await;
+    let target = scratch(&mut admin_conn, &url, &target_name).await;
+
+    let repo = PgRepo::new(source.clone(), PreviewFetcher::builder().enabled(false).build());
+    let user = repo.user_insert("archivist").await.unwrap();
+    let poll = PollArgs::builder().options(vec!["Coq".into(), "Lean".into()]).build();
+    let story = StoryCreateArgs::builder()
+        .title("Proof assistant?".into())
+        .kind(StoryKind::Poll)
+        .poll(poll)
+        .tags(vec!["type-theory".into()])
+        .build();
+    let story = repo.story_insert(story, user.id).await.unwrap();
+    let lean = repo.poll(story.id, None).await.unwrap().options[1].id;
+    repo.poll_vote(story.id, user.id, &[lean]).await.unwrap();
+    let details = serde_json::json!({ "tags": ["type-theory"] });
+    audit::record(&source, Some(user.id), audit::STORY_TAGGED, Some(story.id), details).await.unwrap();
+
+    let mut bytes = Vec::new();
+    let manifest = archive::export(&source, &mut bytes, |_, _| {}).await.unwrap();
+    assert_eq!(manifest.version, archive::VERSION);
+    assert_eq!(archive::import(&target, bytes.as_slice(), |_| {}).await.unwrap(), manifest);
+
+    for table in archive::TABLES {
+        assert_eq!(count(&target, table).await, count(&source, table).await, "{table}");
+    }
+    let restored = PgRepo::new(target.clone(), PreviewFetcher::builder().enabled(false).build());
+    let results = restored.poll(story.id, Some(user.id)).await.unwrap();
+    assert_eq!((results.voters, results.options[1].votes), (1, 1));
+
+    source.close().await;
+    target.close().await;
+    for name in [source_name, target_name] {
+        admin_conn
+            .execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+            .await
+            .unwrap();
+    }
+}
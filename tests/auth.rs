@@ -0,0 +1,195 @@
+#![cfg(feature = "ssr")]
+
+use axum::http::{
+    header::{AUTHORIZATION, COOKIE},
+    HeaderMap,
+};
+use news::{
+    model::{ApiTokenCreateArgs, LambdaError, TokenScope},
+    server::{
+        admin,
+        auth::{self, Bearer, Principal},
+    },
+};
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+async fn scratch(admin_conn: &mut PgConnection, url: &str, name: &str) -> PgPool {
+    admin_conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str()).await.unwrap();
+    let (base, _) = url.rsplit_once('/').unwrap();
+    let pool = PgPool::connect(&format!("{base}/{name}")).await.unwrap();
+    admin::MIGRATOR.run(&pool).await.unwrap();
+    pool
+}
+
+fn bearer(secret: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, format!("Bearer {secret}").parse().unwrap());
+    headers
+}
+
+fn hex_digest(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[test]
+fn principals_only_hold_their_scopes() {
+    let reader = Principal {
+        user_id: 7,
+        scopes: vec![TokenScope::Read],
+    };
+    assert_eq!(reader.require(TokenScope::Read), Ok(7));
+    assert_eq!(reader.require(TokenScope::Vote), Err(LambdaError::Forbidden("vote".into())));
+    assert!(TokenScope::ALL.iter().all(|&scope| Principal::site().require(scope).is_ok()));
+}
+
+#[test]
+fn only_named_users_are_members() {
+    let token = |scopes: Vec<TokenScope>| Bearer {
+        token: Some(Principal { user_id: 7, scopes }),
+        session: None,
+    };
+    assert_eq!(token(vec![TokenScope::Read]).member(TokenScope::Read), Ok(7));
+    assert_eq!(
+        token(vec![TokenScope::Read]).member(TokenScope::Write),
+        Err(LambdaError::Forbidden("write".into()))
+    );
+    let signed_in = Bearer {
+        token: None,
+        session: Some(8),
+    };
+    assert_eq!(signed_in.member(TokenScope::Write), Ok(8));
+    assert_eq!(Bearer::default().member(TokenScope::Read), Err(LambdaError::AuthError));
+}
+
+#[test]
+fn passwords_are_salted_and_verified() {
+    let hash = auth::password_hash("correct horse").unwrap();
+    assert_ne!(hash, auth::password_hash("correct horse").unwrap());
+    assert!(auth::password_verify("correct horse", &hash));
+    assert!(!auth::password_verify("battery staple", &hash));
+    assert!(!auth::password_verify("correct horse", "not a hash"));
+}
+
+#[tokio::test]
+async fn tokens_are_stored_hashed_and_checked_on_use() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return;
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let name = format!("news_auth_{nanos}");
+    let mut admin_conn = PgConnection::connect(&url).await.unwrap();
+    let pool = scratch(&mut admin_conn, &url, &name).await;
+
+    let user = admin::user_create(&pool, "holder", None, false).await.unwrap();
+    let args = ApiTokenCreateArgs::builder().name("reader".into()).read(true).build();
+    let created = auth::token_create(&pool, user.id, args).await.unwrap();
+    assert_eq!(created.token.scopes, [TokenScope::Read]);
+
+    // Only the digest of the secret is kept.
+    let stored: String = sqlx::query_scalar("SELECT token_hash FROM api_tokens WHERE id = $1")
+        .bind(created.token.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, hex_digest(&created.secret));
+    assert!(!stored.contains(&created.secret));
+
+    let unused = auth::token_list(&pool, user.id).await.unwrap();
+    assert_eq!(unused[0].last_used_at, None);
+    let principal = auth::authenticate(&pool, &bearer(&created.secret)).await.unwrap().unwrap();
+    assert_eq!(principal.require(TokenScope::Read), Ok(user.id));
+    assert!(principal.require(TokenScope::Submit).is_err());
+    assert!(principal.require(TokenScope::Write).is_err());
+    let used = auth::token_list(&pool, user.id).await.unwrap();
+    assert!(used[0].last_used_at.is_some());
+
+    assert_eq!(auth::authenticate(&pool, &HeaderMap::new()).await, Ok(None));
+    assert_eq!(auth::authenticate(&pool, &bearer("lf_forged")).await, Err(LambdaError::AuthError));
+    let empty = ApiTokenCreateArgs::builder().name("nothing".into()).build();
+    assert!(matches!(
+        auth::token_create(&pool, user.id, empty).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+
+    // The site user is every anonymous browser, so it gets no tokens, and
+    // ones minted before that was refused no longer work.
+    let site = ApiTokenCreateArgs::builder().name("site".into()).account(true).build();
+    assert!(matches!(
+        auth::token_create(&pool, auth::SITE_USER_ID, site).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    let minted = "INSERT INTO api_tokens (user_id, name, token_hash, scopes, created_at) VALUES ($1, 'old', $2, $3, NOW())";
+    sqlx::query(minted)
+        .bind(auth::SITE_USER_ID)
+        .bind(hex_digest("lf_minted_by_anyone"))
+        .bind(vec!["account".to_string()])
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(auth::token_principal(&pool, "lf_minted_by_anyone").await, Err(LambdaError::AuthError));
+
+    admin::user_set_banned(&pool, "holder", true).await.unwrap();
+    assert_eq!(auth::token_principal(&pool, &created.secret).await, Err(LambdaError::AuthError));
+    admin::user_set_banned(&pool, "holder", false).await.unwrap();
+    auth::token_revoke(&pool, user.id, created.token.id).await.unwrap();
+    assert_eq!(auth::token_principal(&pool, &created.secret).await, Err(LambdaError::AuthError));
+
+    pool.close().await;
+    admin_conn
+        .execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn passwords_sign_users_in_to_sessions() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return;
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let name = format!("news_session_{nanos}");
+    let mut admin_conn = PgConnection::connect(&url).await.unwrap();
+    let pool = scratch(&mut admin_conn, &url, &name).await;
+
+    let user = admin::user_create(&pool, "ada", Some("correct horse"), false).await.unwrap();
+    admin::user_create(&pool, "bob", None, false).await.unwrap();
+    assert_eq!(auth::password_login(&pool, "ada", "battery staple").await, Err(LambdaError::AuthError));
+    assert_eq!(auth::password_login(&pool, "bob", "").await, Err(LambdaError::AuthError));
+    assert_eq!(auth::password_login(&pool, "nobody", "correct horse").await, Err(LambdaError::AuthError));
+    // The shared site user can't be signed in to, whatever its password.
+    admin::user_set_password(&pool, "LambdaFunction", "correct horse").await.unwrap();
+    assert_eq!(
+        auth::password_login(&pool, "LambdaFunction", "correct horse").await,
+        Err(LambdaError::AuthError)
+    );
+
+    let user_id = auth::password_login(&pool, "ada", "correct horse").await.unwrap();
+    assert_eq!(user_id, user.id);
+    let secret = auth::session_create(&pool, user_id).await.unwrap();
+    let mut headers = HeaderMap::new();
+    let cookie = format!("theme=dark; {}", auth::session_cookie(Some(&secret), false));
+    headers.insert(COOKIE, cookie.split_once("; Path").unwrap().0.parse().unwrap());
+    assert_eq!(auth::session_secret(&headers), Some(secret.as_str()));
+    assert_eq!(auth::session_user(&pool, &secret).await.unwrap().id, user.id);
+    assert!(auth::session_cookie(None, true).contains("Max-Age=0"));
+
+    admin::user_set_banned(&pool, "ada", true).await.unwrap();
+    assert!(matches!(auth::session_user(&pool, &secret).await, Err(LambdaError::AuthError)));
+    assert_eq!(auth::password_login(&pool, "ada", "correct horse").await, Err(LambdaError::AuthError));
+    admin::user_set_banned(&pool, "ada", false).await.unwrap();
+    auth::session_delete(&pool, &secret).await.unwrap();
+    assert!(matches!(auth::session_user(&pool, &secret).await, Err(LambdaError::AuthError)));
+    let secret = auth::session_create(&pool, user_id).await.unwrap();
+    admin::user_set_password(&pool, "ada", "battery staple").await.unwrap();
+    assert!(matches!(auth::session_user(&pool, &secret).await, Err(LambdaError::AuthError)));
+
+    pool.close().await;
+    admin_conn
+        .execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+        .await
+        .unwrap();
+}
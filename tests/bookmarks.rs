@@ -0,0 +1,53 @@
+#![cfg(feature = "ssr")]
+
+use chrono::DateTime;
+use news::{
+    model::{Bookmark, Favorites},
+    server::bookmarks::favorites_feed,
+};
+
+#[test]
+fn feeds_list_stories_and_comments_newest_first() {
+    let saved = |at| DateTime::parse_from_rfc3339(at).unwrap();
+    let favorites = Favorites {
+        user_name: "ada".into(),
+        public: true,
+        owned: true,
+        bookmarks: vec![
+            Bookmark::builder()
+                .id(2)
+                .story_id(7)
+                .story_title("Engines".into())
+                .comment_id(9)
+                .excerpt("Numbers & more".into())
+                .note("reread".into())
+                .created_at(saved("2025-04-19T12:00:00+00:00"))
+                .build(),
+            Bookmark::builder()
+                .id(1)
+                .story_id(3)
+                .story_title("Notes".into())
+                .story_url("https://example.org/notes".into())
+                .created_at(saved("2025-04-18T12:00:00+00:00"))
+                .build(),
+        ],
+    };
+
+    let feed = favorites_feed(&favorites, "Lambda Function", "https://news.example");
+    assert_eq!(feed.id(), "https://news.example/closure/ada/favorites");
+    assert_eq!(feed.updated().to_rfc3339(), "2025-04-19T12:00:00+00:00");
+
+    let [comment, story] = feed.entries() else {
+        panic!("expected two entries");
+    };
+    assert_eq!(comment.title().as_str(), "Comment on Engines");
+    assert_eq!(comment.links()[0].href(), "https://news.example/bind/7");
+    assert_eq!(comment.summary().unwrap().as_str(), "Numbers & more");
+    assert_eq!(comment.content().unwrap().value(), Some("reread"));
+    assert_eq!(story.title().as_str(), "Notes");
+    assert_eq!(story.links()[1].href(), "https://example.org/notes");
+
+    let xml = feed.to_string();
+    assert!(xml.contains("<title>ada&apos;s favorites on Lambda Function</title>"));
+    assert!(xml.contains("Numbers &amp; more"));
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "ssr")]
+
+use news::{
+    features::canonical::canonicalize_url,
+    model::StoryCreateArgs,
+    server::{
+        admin,
+        preview::PreviewFetcher,
+        repo::{PgRepo, StoryRepo, UserRepo},
+    },
+};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[test]
+fn spellings_of_one_link_agree() {
+    let canonical = Some("https://example.org/papers/lambda".to_string());
+    for url in [
+        "https://example.org/papers/lambda",
+        "http://example.org/papers/lambda",
+        "https://www.example.org/papers/lambda/",
+        "  https://EXAMPLE.org/papers/lambda#abstract ",
+        "https://example.org/papers/lambda?utm_source=feed&UTM_Medium=rss",
+    ] {
+        assert_eq!(canonicalize_url(url), canonical, "{url}");
+    }
+}
+
+#[test]
+fn meaningful_parts_are_kept() {
+    assert_eq!(
+        canonicalize_url("https://example.org/item?id=7&utm_campaign=x").as_deref(),
+        Some("https://example.org/item?id=7")
+    );
+    assert_eq!(canonicalize_url("https://example.org/").as_deref(), Some("https://example.org"));
+    assert_ne!(canonicalize_url("https://example.org/a"), canonicalize_url("https://example.org/b"));
+    assert_ne!(canonicalize_url("https://example.org/Paper"), canonicalize_url("https://example.org/paper"));
+}
+
+#[test]
+fn only_web_links_have_a_canonical_form() {
+    assert_eq!(canonicalize_url("ftp://example.org/file"), None);
+    assert_eq!(canonicalize_url("mailto:ada@example.org"), None);
+    assert_eq!(canonicalize_url("not a url"), None);
+}
+
+#[tokio::test]
+async fn rebuilding_backfills_missing_canonical_links() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return;
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let name = format!("news_canonical_{nanos}");
+    let mut admin_conn = PgConnection::connect(&url).await.unwrap();
+    admin_conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str()).await.unwrap();
+    let (base, _) = url.rsplit_once('/').unwrap();
+    let pool = PgPool::connect(&format!("{base}/{name}")).await.unwrap();
+    admin::MIGRATOR.run(&pool).await.unwrap();
+
+    let repo = PgRepo::new(pool.clone(), PreviewFetcher::builder().enabled(false).build());
+    let user = repo.user_insert("backfiller").await.unwrap();
+    let story = StoryCreateArgs::builder()
+        .title("Lambda papers".into())
+        .url("https://www.example.org/papers/lambda/?utm_source=feed".into())
+        .build();
+    let story = repo.story_insert(story, user.id).await.unwrap();
+    let text = StoryCreateArgs::builder().title("Ask: lambda?".into()).text("Why?".into()).build();
+    repo.story_insert(text, user.id).await.unwrap();
+    // As posted before duplicate detection existed.
+    sqlx::query("UPDATE stories SET canonical_url = NULL").execute(&pool).await.unwrap();
+
+    assert_eq!(admin::canonical_urls_rebuild(&pool, |_| {}).await.unwrap(), 1);
+    assert_eq!(admin::canonical_urls_rebuild(&pool, |_| {}).await.unwrap(), 0);
+    let canonical_url: Option<String> = sqlx::query_scalar("SELECT canonical_url FROM stories WHERE id = $1")
+        .bind(story.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(canonical_url.as_deref(), Some("https://example.org/papers/lambda"));
+    let duplicate = repo.story_duplicate("https://example.org/papers/lambda").await.unwrap();
+    assert_eq!(duplicate.map(|duplicate| duplicate.id), Some(story.id));
+
+    pool.close().await;
+    admin_conn
+        .execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,26 @@
+#![cfg(feature = "ssr")]
+
+use news::model::DraftArgs;
+use validator::Validate;
+
+#[test]
+fn whitespace_is_not_worth_keeping() {
+    assert!(DraftArgs::default().is_blank());
+    assert!(DraftArgs::builder().title(" ".into()).text("\n\t".into()).build().is_blank());
+    assert!(!DraftArgs::builder().url("https://example.org".into()).build().is_blank());
+    assert!(!DraftArgs::builder().story_id(7).text("Half a thought".into()).build().is_blank());
+}
+
+#[test]
+fn drafts_saved_without_text_read_back_empty() {
+    let draft: DraftArgs = serde_json::from_str(r#"{"title":"Lambda calculus"}"#).unwrap();
+    assert_eq!(draft.text, "");
+    assert_eq!(draft.title.as_deref(), Some("Lambda calculus"));
+}
+
+#[test]
+fn oversized_drafts_are_refused() {
+    let draft = DraftArgs::builder().text("λ".repeat(100_001)).build();
+    assert!(draft.validate().is_err());
+    assert!(DraftArgs::builder().text("λ".repeat(100_000)).build().validate().is_ok());
+}
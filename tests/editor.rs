@@ -0,0 +1,48 @@
+#![cfg(feature = "ssr")]
+
+use news::features::ui::editor::{apply_format, Format};
+
+/// Applies a format to the part of `text` between `[` and `]`, returning the
+/// result with the new selection marked the same way.
+fn format(text: &str, format: Format) -> String {
+    let start = text.find('[').unwrap();
+    let end = text.find(']').unwrap() - 1;
+    let text = text.replacen('[', "", 1).replacen(']', "", 1);
+    let offset = |byte: usize| text[..byte].encode_utf16().count() as u32;
+
+    let (text, start, end) = apply_format(&text, offset(start), offset(end), format);
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let piece = |range: std::ops::Range<u32>| {
+        String::from_utf16(&units[range.start as usize..range.end as usize]).unwrap()
+    };
+    format!("{}[{}]{}", piece(0..start), piece(start..end), piece(end..units.len() as u32))
+}
+
+#[test]
+fn inline_formats_wrap_and_keep_the_selection() {
+    assert_eq!(format("a [word] here", Format::Bold), "a **[word]** here");
+    assert_eq!(format("a [] here", Format::Italic), "a _[]_ here");
+    assert_eq!(format("call [f()]", Format::Code), "call `[f()]`");
+}
+
+#[test]
+fn links_select_their_placeholder_url() {
+    assert_eq!(format("see [the docs]", Format::Link), "see [the docs]([https://])");
+}
+
+#[test]
+fn line_formats_prefix_every_touched_line() {
+    assert_eq!(format("intro\nfi[rst\nsec]ond\nend", Format::List), "intro\n[- first\n- second]\nend");
+    assert_eq!(format("[]quoted", Format::Quote), "[> quoted]");
+}
+
+#[test]
+fn code_blocks_start_on_their_own_line() {
+    assert_eq!(format("run [ls]", Format::CodeBlock), "run \n```\n[ls]\n```");
+    assert_eq!(format("[ls]", Format::CodeBlock), "```\n[ls]\n```");
+}
+
+#[test]
+fn offsets_count_utf16_units() {
+    assert_eq!(format("λ 🦀 [crab]", Format::Bold), "λ 🦀 **[crab]**");
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "ssr")]
+
+use news::{
+    model::StoryKind,
+    server::hn::{hn_story_kind, hn_text, HnItem, HnKind, HnRecord},
+};
+
+#[test]
+fn converts_hn_html_to_markdown() {
+    let html = "I <i>like</i> it &amp; it&#x27;s fast.<p>See <a href=\"https:&#x2F;&#x2F;example.org&#x2F;a-long-path\" rel=\"nofollow\">https:&#x2F;&#x2F;example.org&#x2F;...</a><p><pre><code>  fn main() {\n      println!(&quot;hi&quot;);\n  }\n</code></pre>";
+
+    assert_eq!(
+        hn_text(html),
+        "I *like* it & it's fast.\n\nSee https://example.org/a-long-path\n\n      fn main() {\n          println!(\"hi\");\n      }"
+    );
+}
+
+#[test]
+fn leaves_unknown_entities_alone() {
+    assert_eq!(hn_text("AT&T &bogus; 1 &lt; 2"), "AT&T &bogus; 1 < 2");
+}
+
+#[test]
+fn parses_items_users_and_nested_kids() {
+    let dump = r#"[
+        {"id": "pg", "created": 1160418092, "karma": 157000},
+        {"id": 1, "type": "story", "by": "pg", "time": 1160418111, "title": "Y", "kids": [2, {"id": 3, "type": "comment", "by": "x", "text": "hi"}]},
+        {"id": 4, "type": "pollopt", "poll": 1}
+    ]"#;
+
+    let HnRecord::Many(records) = serde_json::from_str(dump).unwrap() else {
+        panic!("expected an array");
+    };
+    assert!(matches!(&records[0], HnRecord::User(user) if user.id == "pg"));
+    assert!(matches!(&records[1], HnRecord::Item(item) if item.kind == HnKind::Story && item.kids.len() == 2));
+    assert!(matches!(&records[2], HnRecord::Item(item) if item.kind == HnKind::Other));
+}
+
+#[test]
+fn maps_items_to_story_kinds() {
+    let kind = |json: &str| hn_story_kind(&serde_json::from_str::<HnItem>(json).unwrap());
+
+    assert_eq!(kind(r#"{"id": 1, "type": "story", "title": "Y", "url": "https://y.org"}"#), StoryKind::Link);
+    assert_eq!(kind(r#"{"id": 2, "type": "story", "title": "Ask HN: Why?", "text": "?"}"#), StoryKind::Ask);
+    assert_eq!(kind(r#"{"id": 3, "type": "story", "title": "Show HN: X", "url": "https://x.org"}"#), StoryKind::Show);
+    assert_eq!(kind(r#"{"id": 4, "type": "story", "title": "Musings", "text": "hm"}"#), StoryKind::Ask);
+    assert_eq!(kind(r#"{"id": 5, "type": "job", "title": "Hiring", "url": "https://y.org/jobs"}"#), StoryKind::Job);
+    assert_eq!(kind(r#"{"id": 6, "type": "job", "title": "Hiring", "text": "Email us"}"#), StoryKind::Ask);
+    assert_eq!(kind(r#"{"id": 7, "type": "poll", "title": "Tabs or spaces?"}"#), StoryKind::Ask);
+}
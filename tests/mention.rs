@@ -0,0 +1,23 @@
+#![cfg(feature = "ssr")]
+
+use news::features::mention::{mention_spans, mentioned_names};
+
+#[test]
+fn finds_distinct_mentions_in_order() {
+    assert_eq!(
+        mentioned_names("@alice, thanks! cc @bob_2 and @alice again (@carol-x)."),
+        ["alice", "bob_2", "carol-x"]
+    );
+}
+
+#[test]
+fn ignores_addresses_and_bare_at_signs() {
+    assert!(mentioned_names("mail me@example.org, or @ me, or @@twice").is_empty());
+}
+
+#[test]
+fn spans_cover_the_at_sign_and_name() {
+    let text = "hi @ada and @grace.";
+    let spans: Vec<&str> = mention_spans(text).into_iter().map(|span| &text[span]).collect();
+    assert_eq!(spans, ["@ada", "@grace"]);
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "ssr")]
+
+use news::server::admin;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Reverting stops short of migrations whose down script only explains why
+/// nothing can be undone, and leaves the database as it was.
+#[tokio::test]
+async fn reverting_refuses_migrations_that_cannot_be_undone() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return;
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let name = format!("news_migrations_{nanos}");
+    let mut admin_conn = PgConnection::connect(&url).await.unwrap();
+    admin_conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str()).await.unwrap();
+    let (base, _) = url.rsplit_once('/').unwrap();
+    let pool = PgPool::connect(&format!("{base}/{name}")).await.unwrap();
+    admin::MIGRATOR.run(&pool).await.unwrap();
+    let applied = || sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM _sqlx_migrations").fetch_one(&pool);
+    let before = applied().await.unwrap();
+
+    let kind_fixes = 20250508120000;
+    let err = admin::migrations_revert(&pool, kind_fixes - 1).await.unwrap_err();
+    assert!(err.to_string().contains("story kind fixes"), "{err}");
+    let err = admin::migrations_revert(&pool, 0).await.unwrap_err();
+    assert!(err.to_string().contains("can't be undone"), "{err}");
+    assert_eq!(applied().await.unwrap(), before);
+
+    let reverted = admin::migrations_revert(&pool, kind_fixes).await.unwrap();
+    assert!(!reverted.is_empty());
+    assert_eq!(applied().await.unwrap(), before - reverted.len() as i64);
+    admin::MIGRATOR.run(&pool).await.unwrap();
+
+    pool.close().await;
+    admin_conn
+        .execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+        .await
+        .unwrap();
+}
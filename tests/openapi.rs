@@ -0,0 +1,22 @@
+#![cfg(feature = "ssr")]
+
+use news::server::rest::ApiDoc;
+use utoipa::OpenApi;
+
+const SPEC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi/v1.json");
+
+#[test]
+fn checked_in_spec_matches_code() {
+    let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(SPEC_PATH, &generated).unwrap();
+        return;
+    }
+
+    let checked_in = std::fs::read_to_string(SPEC_PATH).unwrap_or_default();
+    assert!(
+        generated == checked_in,
+        "openapi/v1.json has drifted from the API types; regenerate it with `UPDATE_OPENAPI=1 cargo test --features ssr --test openapi`"
+    );
+}
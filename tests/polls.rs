@@ -0,0 +1,73 @@
+#![cfg(feature = "ssr")]
+
+use chrono::DateTime;
+use news::{
+    features::poll::{percent, poll_options, poll_results_text},
+    model::{Poll, PollArgs, PollOption, StoryKind, StoryListItem},
+    server::tags::tag_feed,
+};
+
+fn results() -> Poll {
+    let option = |id, text: &str, votes| PollOption::builder().id(id).text(text.into()).votes(votes).build();
+    Poll::builder()
+        .story_id(9)
+        .multiple(true)
+        .options(vec![option(1, "Coq", 2), option(2, "Lean", 1), option(3, "Agda", 0)])
+        .voters(3)
+        .build()
+}
+
+#[test]
+fn blank_options_are_left_out() {
+    let options = ["  Coq ", "", "Lean", " "].map(String::from);
+    assert_eq!(poll_options(&options), ["Coq", "Lean"]);
+}
+
+#[test]
+fn shares_are_rounded_percentages_of_voters() {
+    assert_eq!(percent(2, 3), 67);
+    assert_eq!(percent(1, 3), 33);
+    assert_eq!(percent(0, 0), 0);
+}
+
+#[test]
+fn close_times_accept_form_and_api_values() {
+    let closes_at = |value: &str| {
+        serde_json::from_value::<PollArgs>(serde_json::json!({ "options": [], "closes_at": value }))
+            .map(|poll| poll.closes_at.map(|closes_at| closes_at.to_rfc3339()))
+    };
+
+    assert_eq!(
+        closes_at("2025-05-01T12:30").unwrap().as_deref(),
+        Some("2025-05-01T12:30:00+00:00")
+    );
+    assert_eq!(
+        closes_at("2025-05-01T12:30:00+02:00").unwrap().as_deref(),
+        Some("2025-05-01T12:30:00+02:00")
+    );
+    assert_eq!(closes_at("").unwrap(), None);
+    assert!(closes_at("next week").is_err());
+}
+
+#[test]
+fn feeds_carry_poll_results() {
+    assert_eq!(
+        poll_results_text(&results()),
+        "Coq: 2 votes (67%)\nLean: 1 vote (33%)\nAgda: 0 votes (0%)\n3 voters"
+    );
+
+    let story = StoryListItem::builder()
+        .id(9)
+        .title("Proof assistant?".into())
+        .created_at(DateTime::parse_from_rfc3339("2025-05-01T12:00:00+00:00").unwrap())
+        .author_name("ada".into())
+        .rating(Some(0))
+        .comment_count(Some(0))
+        .kind(StoryKind::Poll)
+        .tags(vec!["type-theory".into()])
+        .build();
+    let feed = tag_feed("type-theory", &[story], &[results()], "Lambda Function", "https://news.example");
+
+    let content = feed.entries()[0].content().unwrap();
+    assert_eq!(content.value(), Some(poll_results_text(&results()).as_str()));
+}
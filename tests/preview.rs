@@ -0,0 +1,133 @@
+#![cfg(feature = "ssr")]
+
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use news::server::preview::{is_public, PreviewError, PreviewFetcher};
+use std::net::{IpAddr, SocketAddr};
+
+const ARTICLE: &str = r#"
+    <html>
+        <head>
+            <title>  Plain   title </title>
+            <meta property="og:title" content="Functors for the Working Programmer" />
+            <meta property="og:description" content="A gentle introduction." />
+            <meta property="og:image" content="/cover.png" />
+            <link rel="alternate" type="application/json+oembed" href="/oembed.json" />
+        </head>
+        <body></body>
+    </html>
+"#;
+
+async fn serve() -> SocketAddr {
+    let app = Router::new()
+        .route("/article", get(|| async { Html(ARTICLE) }))
+        .route("/untitled", get(|| async { Html("<title>Only a title</title>") }))
+        .route(
+            "/oembed.json",
+            get(|| async {
+                (
+                    [(header::CONTENT_TYPE, "application/json")],
+                    r#"{"title":"ignored","provider_name":"Stub Press"}"#,
+                )
+                    .into_response()
+            }),
+        )
+        .route("/data.json", get(|| async { ([(header::CONTENT_TYPE, "application/json")], "{}") }))
+        .route("/huge", get(|| async { Html(format!("{}<title>too late</title>", " ".repeat(64 * 1024))) }))
+        .route("/redirect", get(|| async { axum::response::Redirect::temporary("/article") }));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+fn fetcher() -> PreviewFetcher {
+    PreviewFetcher::builder().allow_private(true).build()
+}
+
+#[tokio::test]
+async fn extracts_opengraph_and_oembed_metadata() {
+    let addr = serve().await;
+    let preview = fetcher().fetch(&format!("http://{addr}/article")).await.unwrap();
+
+    assert_eq!(preview.title.as_deref(), Some("Functors for the Working Programmer"));
+    assert_eq!(preview.description.as_deref(), Some("A gentle introduction."));
+    assert_eq!(preview.image_url, Some(format!("http://{addr}/cover.png")));
+    assert_eq!(preview.site_name.as_deref(), Some("Stub Press"));
+}
+
+#[tokio::test]
+async fn falls_back_to_title_element() {
+    let addr = serve().await;
+    let preview = fetcher().fetch(&format!("http://{addr}/untitled")).await.unwrap();
+
+    assert_eq!(preview.title.as_deref(), Some("Only a title"));
+}
+
+#[tokio::test]
+async fn follows_redirects() {
+    let addr = serve().await;
+    let preview = fetcher().fetch(&format!("http://{addr}/redirect")).await.unwrap();
+
+    assert_eq!(preview.description.as_deref(), Some("A gentle introduction."));
+}
+
+#[tokio::test]
+async fn stops_reading_at_size_cap() {
+    let addr = serve().await;
+    let fetcher = PreviewFetcher::builder().allow_private(true).max_bytes(1024).build();
+    let preview = fetcher.fetch(&format!("http://{addr}/huge")).await.unwrap();
+
+    assert_eq!(preview.title, None);
+}
+
+#[tokio::test]
+async fn rejects_non_html_responses() {
+    let addr = serve().await;
+    let result = fetcher().fetch(&format!("http://{addr}/data.json")).await;
+
+    assert!(matches!(result, Err(PreviewError::NotHtml)));
+}
+
+#[tokio::test]
+async fn refuses_private_addresses() {
+    let addr = serve().await;
+    let fetcher = PreviewFetcher::default();
+
+    for url in [
+        format!("http://{addr}/article"),
+        format!("http://localhost:{}/article", addr.port()),
+        "http://10.0.0.1/".to_string(),
+        "http://[::1]/".to_string(),
+        "http://169.254.169.254/latest/meta-data".to_string(),
+    ] {
+        let result = fetcher.fetch(&url).await;
+        assert!(result.is_err(), "{url} should be refused");
+    }
+}
+
+#[test]
+fn ipv6_addresses_embedding_ipv4_follow_its_rules() {
+    let public = |ip: &str| is_public(ip.parse::<IpAddr>().unwrap());
+
+    for ip in ["::ffff:10.0.0.1", "::127.0.0.1", "::", "::1", "64:ff9b::a9fe:a9fe", "64:ff9b::192.168.1.1"] {
+        assert!(!public(ip), "{ip} should be private");
+    }
+    assert!(!public("2001:db8::1"));
+    assert!(!public("fd00::1"));
+    for ip in ["::ffff:93.184.216.34", "64:ff9b::93.184.216.34", "2606:4700::1111"] {
+        assert!(public(ip), "{ip} should be public");
+    }
+}
+
+#[tokio::test]
+async fn rejects_unsupported_schemes() {
+    let result = fetcher().fetch("file:///etc/passwd").await;
+
+    assert!(matches!(result, Err(PreviewError::UnsupportedUrl(_))));
+}
@@ -0,0 +1,37 @@
+#![cfg(feature = "ssr")]
+
+use axum::http::HeaderMap;
+use news::server::ratelimit::RateLimiter;
+use std::net::IpAddr;
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+fn forwarded(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forwarded-for", value.parse().unwrap());
+    headers
+}
+
+#[test]
+fn only_trusted_proxies_may_name_the_client() {
+    let limiter = RateLimiter::new(1, vec![ip("10.0.0.1"), ip("10.0.0.2")]);
+    let headers = forwarded("6.6.6.6, 203.0.113.7, 10.0.0.2");
+
+    assert_eq!(limiter.client(ip("10.0.0.1"), &headers), ip("203.0.113.7"));
+    assert_eq!(limiter.client(ip("198.51.100.9"), &headers), ip("198.51.100.9"));
+    assert_eq!(limiter.client(ip("10.0.0.1"), &HeaderMap::new()), ip("10.0.0.1"));
+    assert_eq!(limiter.client(ip("10.0.0.1"), &forwarded("6.6.6.6, garbage")), ip("10.0.0.1"));
+}
+
+#[test]
+fn forwarded_clients_are_limited_separately() {
+    let limiter = RateLimiter::new(1, vec![ip("10.0.0.1")]);
+    let ada = limiter.client(ip("10.0.0.1"), &forwarded("203.0.113.7"));
+    let bob = limiter.client(ip("10.0.0.1"), &forwarded("203.0.113.8"));
+
+    assert!(limiter.check(ada));
+    assert!(limiter.check(bob));
+    assert!(!limiter.check(ada));
+}
@@ -0,0 +1,782 @@
+#![cfg(feature = "ssr")]
+
+//! One conformance suite for every storage backend. The in-memory backend
+//! always runs; Postgres runs in a throwaway database when `DATABASE_URL` is
+//! set.
+
+use leptos::prelude::{provide_context, Owner};
+use news::{
+    api::{
+        comment_create, comment_list, draft_get, draft_save, get_story_page_count, story_create, story_hide,
+        story_kind_list, story_list, story_mark_seen, tag_prefer,
+    },
+    constants::PAGE_SIZE,
+    model::{
+        BookmarkArgs, CommentCreateArgs, DraftArgs, LambdaError, Mention, PollArgs, StoryCreateArgs, StoryFilter, StoryKind,
+        TagPreference, TagPreferences, VoteArgs,
+    },
+    server::{
+        admin,
+        audit,
+        auth::SITE_USER_ID,
+        bookmarks,
+        preview::PreviewFetcher,
+        repo::{DraftRepo, MemoryRepo, PgRepo, Repo, Repository, TagRepo, UserRepo},
+        visits,
+    },
+};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn link(title: &str, url: &str) -> StoryCreateArgs {
+    StoryCreateArgs::builder().title(title.into()).url(url.into()).build()
+}
+
+fn reply(story_id: i32, parent_id: Option<i32>, text: &str) -> CommentCreateArgs {
+    CommentCreateArgs {
+        text: text.into(),
+        parent_id,
+        story_id,
+    }
+}
+
+async fn users(repo: &dyn Repository) {
+    let user = repo.user_insert("conformance-users").await.unwrap();
+    assert_eq!(user.username, "conformance-users");
+    assert_eq!(repo.user_by_name("conformance-users").await.unwrap().id, user.id);
+
+    assert!(matches!(
+        repo.user_insert("conformance-users").await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    assert!(matches!(repo.user_insert(" ").await, Err(LambdaError::ValidationError(_))));
+    assert!(matches!(
+        repo.user_by_name("conformance-nobody").await,
+        Err(LambdaError::NotFound)
+    ));
+}
+
+async fn stories(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-stories").await.unwrap();
+    let pages = repo.story_page_count(&StoryFilter::default()).await.unwrap();
+
+    let first = repo
+        .story_insert(link("First", "https://example.org/first"), author.id)
+        .await
+        .unwrap();
+    let text = StoryCreateArgs::builder()
+        .title("Second".into())
+        .text("Some *emphasis*".into())
+        .build();
+    let second = repo.story_insert(text, author.id).await.unwrap();
+    assert_eq!(second.author_id, author.id);
+    assert!(second.text_html.as_deref().unwrap().contains("<em>emphasis</em>"));
+    assert_eq!(repo.story_by_id(first.id).await.unwrap().title, "First");
+
+    let page = repo.story_page(0, &StoryFilter::default()).await.unwrap();
+    assert_eq!(page[0].id, second.id);
+    assert_eq!(page[1].id, first.id);
+    assert_eq!(page[1].author_name, "conformance-stories");
+    assert_eq!(page[1].rating, Some(0));
+    assert_eq!(page[1].comment_count, Some(0));
+    assert!(pages <= repo.story_page_count(&StoryFilter::default()).await.unwrap());
+
+    assert!(matches!(repo.story_by_id(-1).await, Err(LambdaError::NotFound)));
+    assert!(matches!(
+        repo.story_insert(link("", "https://example.org/untitled"), author.id).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    assert!(matches!(
+        repo.story_insert(link("Orphan", "https://example.org/orphan"), -1).await,
+        Err(LambdaError::NotFound)
+    ));
+}
+
+async fn duplicates(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-duplicates").await.unwrap();
+    let original = repo
+        .story_insert(link("Original", "https://example.org/dup?utm_source=a"), author.id)
+        .await
+        .unwrap();
+
+    let found = repo.story_duplicate("https://example.org/dup").await.unwrap();
+    assert_eq!(found.map(|story| story.id), Some(original.id));
+    assert!(repo.story_duplicate("https://example.org/other").await.unwrap().is_none());
+
+    let again = repo
+        .story_insert(link("Again", "https://www.example.org/dup"), author.id)
+        .await
+        .unwrap();
+    assert_eq!(again.id, original.id);
+}
+
+async fn comments(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-comments").await.unwrap();
+    let story = repo
+        .story_insert(link("Discussed", "https://example.org/discussed"), author.id)
+        .await
+        .unwrap();
+
+    let root = repo.comment_insert(reply(story.id, None, "root"), author.id).await.unwrap();
+    let child = repo
+        .comment_insert(reply(story.id, Some(root.id), "child"), author.id)
+        .await
+        .unwrap();
+    let grandchild = repo
+        .comment_insert(reply(story.id, Some(child.id), "grandchild"), author.id)
+        .await
+        .unwrap();
+    assert_eq!(grandchild.author_name, "conformance-comments");
+    assert_eq!(repo.comment_by_id(child.id).await.unwrap().text, "child");
+
+    let listed: Vec<i32> = repo
+        .comments_for_story(story.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|comment| comment.id)
+        .collect();
+    assert_eq!(listed, [grandchild.id, child.id, root.id]);
+
+    let chain: Vec<i32> = repo
+        .comment_with_parents(grandchild.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|comment| comment.id)
+        .collect();
+    assert_eq!(chain, [root.id, child.id, grandchild.id]);
+    assert!(repo.comment_with_parents(-1).await.unwrap().is_empty());
+
+    let page = repo.story_page(0, &StoryFilter::default()).await.unwrap();
+    let item = page.iter().find(|item| item.id == story.id).unwrap();
+    assert_eq!(item.comment_count, Some(3));
+
+    assert!(matches!(repo.comment_by_id(-1).await, Err(LambdaError::NotFound)));
+    assert!(matches!(
+        repo.comment_insert(reply(-1, None, "lost"), author.id).await,
+        Err(LambdaError::NotFound)
+    ));
+    assert!(matches!(
+        repo.comment_insert(reply(story.id, Some(-1), "lost"), author.id).await,
+        Err(LambdaError::NotFound)
+    ));
+    assert!(matches!(
+        repo.comment_insert(reply(story.id, None, " \n\t"), author.id).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    // Replies stay on the story of the comment they answer.
+    let elsewhere = repo
+        .story_insert(link("Elsewhere", "https://example.org/elsewhere"), author.id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        repo.comment_insert(reply(elsewhere.id, Some(root.id), "astray"), author.id).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    assert!(repo.comments_for_story(elsewhere.id).await.unwrap().is_empty());
+    let page = repo.story_page(0, &StoryFilter::default()).await.unwrap();
+    let item = page.iter().find(|item| item.id == elsewhere.id).unwrap();
+    assert_eq!(item.comment_count, Some(0));
+}
+
+async fn mentions(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-mentions").await.unwrap();
+    let mentioned = repo.user_insert("conformance-mentioned").await.unwrap();
+    let story = StoryCreateArgs::builder()
+        .title("Mentioning".into())
+        .text("Thanks @conformance-mentioned, not @conformance-nobody or `@conformance-mentioned`".into())
+        .build();
+    let story = repo.story_insert(story, author.id).await.unwrap();
+    let html = story.text_html.unwrap();
+    assert!(html.contains(r#"<a href="/closure/conformance-mentioned">@conformance-mentioned</a>"#));
+    assert!(html.contains("not @conformance-nobody"));
+    assert!(html.contains("<code>@conformance-mentioned</code>"));
+
+    let comment = repo
+        .comment_insert(reply(story.id, None, "@conformance-mentioned and @conformance-nobody"), author.id)
+        .await
+        .unwrap();
+    let expected = [Mention {
+        name: mentioned.username.clone(),
+        user_name: mentioned.username.clone(),
+    }];
+    assert_eq!(comment.mentions, expected);
+    assert_eq!(repo.comment_by_id(comment.id).await.unwrap().mentions, expected);
+    assert_eq!(repo.comments_for_story(story.id).await.unwrap()[0].mentions, expected);
+    assert_eq!(repo.comment_with_parents(comment.id).await.unwrap()[0].mentions, expected);
+}
+
+async fn votes(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-votes").await.unwrap();
+    let voter = repo.user_insert("conformance-voter").await.unwrap();
+    let story = repo
+        .story_insert(link("Voted", "https://example.org/voted"), author.id)
+        .await
+        .unwrap();
+    let comment = repo.comment_insert(reply(story.id, None, "voted"), author.id).await.unwrap();
+    let vote = |value| VoteArgs { value };
+
+    let tally = repo.vote_cast(Some(story.id), None, vote(1), author.id).await.unwrap();
+    assert_eq!((tally.score, tally.count), (1, 1));
+    let tally = repo.vote_cast(Some(story.id), None, vote(-1), voter.id).await.unwrap();
+    assert_eq!((tally.score, tally.count), (0, 2));
+    let tally = repo.vote_cast(Some(story.id), None, vote(1), voter.id).await.unwrap();
+    assert_eq!((tally.score, tally.count), (2, 2));
+
+    let page = repo.story_page(0, &StoryFilter::default()).await.unwrap();
+    let item = page.iter().find(|item| item.id == story.id).unwrap();
+    assert_eq!(item.rating, Some(2));
+
+    let tally = repo.vote_cast(Some(story.id), None, vote(0), author.id).await.unwrap();
+    assert_eq!((tally.score, tally.count), (1, 1));
+    assert_eq!(repo.vote_tally(Some(story.id), None).await.unwrap(), tally);
+
+    let tally = repo.vote_cast(None, Some(comment.id), vote(-1), voter.id).await.unwrap();
+    assert_eq!((tally.score, tally.count), (-1, 1));
+    assert_eq!(repo.vote_tally(Some(story.id), None).await.unwrap().count, 1);
+
+    assert!(matches!(
+        repo.vote_cast(Some(story.id), None, vote(2), voter.id).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    assert!(matches!(
+        repo.vote_cast(Some(story.id), Some(comment.id), vote(1), voter.id).await,
+        Err(LambdaError::InvalidData(_))
+    ));
+    assert!(matches!(
+        repo.vote_cast(Some(-1), None, vote(1), voter.id).await,
+        Err(LambdaError::NotFound)
+    ));
+}
+
+async fn visits(repo: &dyn Repository) {
+    let reader = repo.user_insert("conformance-reader").await.unwrap();
+    let author = repo.user_insert("conformance-visited").await.unwrap();
+    let story = repo
+        .story_insert(link("Visited", "https://example.org/visited"), author.id)
+        .await
+        .unwrap();
+    let old = repo.comment_insert(reply(story.id, None, "before"), author.id).await.unwrap();
+
+    let first = repo.story_visit(reader.id, story.id).await.unwrap();
+    assert_eq!(first.seen_at, None);
+    assert!(first.new_comment_ids.is_empty());
+
+    let new = repo.comment_insert(reply(story.id, Some(old.id), "after"), author.id).await.unwrap();
+    repo.comment_insert(reply(story.id, None, "mine"), reader.id).await.unwrap();
+    let visits = repo.story_visits(reader.id, &[story.id, -1]).await.unwrap();
+    assert_eq!(visits.len(), 1);
+    assert_eq!(visits[0].new_comment_ids, vec![new.id]);
+
+    let second = repo.story_visit(reader.id, story.id).await.unwrap();
+    assert!(second.seen_at.is_some());
+    assert_eq!(second.new_comment_ids, vec![new.id]);
+    let third = repo.story_visit(reader.id, story.id).await.unwrap();
+    assert!(third.new_comment_ids.is_empty());
+
+    repo.story_hide(reader.id, story.id, true).await.unwrap();
+    assert_eq!(repo.hidden_story_ids(reader.id).await.unwrap(), vec![story.id]);
+    assert!(repo.hidden_story_ids(author.id).await.unwrap().is_empty());
+    assert!(repo.story_visit(reader.id, story.id).await.unwrap().hidden);
+    repo.story_hide(reader.id, story.id, false).await.unwrap();
+    assert!(repo.hidden_story_ids(reader.id).await.unwrap().is_empty());
+
+    assert!(matches!(
+        repo.story_hide(reader.id, -1, true).await,
+        Err(LambdaError::NotFound)
+    ));
+    assert!(matches!(
+        repo.story_visit(reader.id, -1).await,
+        Err(LambdaError::NotFound)
+    ));
+}
+
+async fn tags(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-tags").await.unwrap();
+    let reader = repo.user_insert("conformance-tag-reader").await.unwrap();
+    repo.tag_create("conformance-a", Some("First")).await.unwrap();
+    repo.tag_create("conformance-b", None).await.unwrap();
+    assert!(matches!(
+        repo.tag_create("conformance-a", None).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    assert!(matches!(repo.tag_create("Not a tag", None).await, Err(LambdaError::ValidationError(_))));
+
+    let tagged = StoryCreateArgs::builder()
+        .title("Tagged".into())
+        .url("https://example.org/tagged".into())
+        .tags(vec!["conformance-b".into(), "conformance-a".into(), "conformance-a".into()])
+        .build();
+    let story = repo.story_insert(tagged, author.id).await.unwrap();
+    assert_eq!(story.tags, ["conformance-a", "conformance-b"]);
+    assert_eq!(repo.story_by_id(story.id).await.unwrap().tags, story.tags);
+    let other = repo
+        .story_insert(link("Untagged", "https://example.org/untagged"), author.id)
+        .await
+        .unwrap();
+    assert!(other.tags.is_empty());
+
+    let page = repo.tag_page("conformance-a", 0).await.unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].id, story.id);
+    assert_eq!(page[0].tags, story.tags);
+    let front = repo.story_page(0, &StoryFilter::default()).await.unwrap();
+    assert_eq!(front.iter().find(|item| item.id == story.id).unwrap().tags, story.tags);
+    let muted = StoryFilter::builder().muted(vec!["conformance-b".into()]).build();
+    let front = repo.story_page(0, &muted).await.unwrap();
+    assert!(front.iter().all(|item| item.id != story.id));
+    assert!(front.iter().any(|item| item.id == other.id));
+    let listed = repo.tag_list().await.unwrap();
+    let first = listed.iter().find(|tag| tag.name == "conformance-a").unwrap();
+    assert_eq!((first.description.as_deref(), first.story_count), (Some("First"), 1));
+
+    assert_eq!(
+        repo.story_tags_set(other.id, &["conformance-b".into()]).await.unwrap(),
+        ["conformance-b"]
+    );
+    assert_eq!(repo.tag_page("conformance-b", 0).await.unwrap().len(), 2);
+    assert!(repo.story_tags_set(other.id, &[]).await.unwrap().is_empty());
+    assert_eq!(repo.tag_page("conformance-b", 0).await.unwrap().len(), 1);
+
+    let unknown = StoryCreateArgs::builder()
+        .title("Unknown".into())
+        .text("tagged".into())
+        .tags(vec!["conformance-nothing".into()])
+        .build();
+    assert!(matches!(
+        repo.story_insert(unknown, author.id).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    let crowded: Vec<String> = (0..4).map(|n| format!("conformance-{n}")).collect();
+    assert!(matches!(
+        repo.story_tags_set(other.id, &crowded).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    assert!(matches!(
+        repo.story_tags_set(-1, &[]).await,
+        Err(LambdaError::NotFound)
+    ));
+    assert!(matches!(
+        repo.tag_page("conformance-nothing", 0).await,
+        Err(LambdaError::NotFound)
+    ));
+
+    repo.tag_prefer(reader.id, "conformance-a", Some(TagPreference::Follow)).await.unwrap();
+    repo.tag_prefer(reader.id, "conformance-b", Some(TagPreference::Mute)).await.unwrap();
+    let preferences = repo.tag_preferences(reader.id).await.unwrap();
+    assert_eq!(preferences.followed, ["conformance-a"]);
+    assert_eq!(preferences.muted, ["conformance-b"]);
+    repo.tag_prefer(reader.id, "conformance-b", Some(TagPreference::Follow)).await.unwrap();
+    repo.tag_prefer(reader.id, "conformance-a", None).await.unwrap();
+    let preferences = repo.tag_preferences(reader.id).await.unwrap();
+    assert_eq!(preferences.followed, ["conformance-b"]);
+    assert!(preferences.muted.is_empty());
+    assert_eq!(repo.tag_preferences(author.id).await.unwrap(), TagPreferences::default());
+    assert!(matches!(
+        repo.tag_prefer(reader.id, "conformance-nothing", Some(TagPreference::Mute)).await,
+        Err(LambdaError::NotFound)
+    ));
+}
+
+async fn kinds(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-kinds").await.unwrap();
+    let voter = repo.user_insert("conformance-kinds-voter").await.unwrap();
+    let ask = |title: &str| StoryCreateArgs::builder().title(title.into()).text("?".into()).build();
+    let of_kind = |kind, story: StoryCreateArgs| StoryCreateArgs { kind: Some(kind), ..story };
+
+    let inferred = repo.story_insert(ask("Inferred ask"), author.id).await.unwrap();
+    assert_eq!(inferred.kind, StoryKind::Ask);
+    let linked = repo
+        .story_insert(link("Inferred link", "https://example.org/inferred"), author.id)
+        .await
+        .unwrap();
+    assert_eq!(linked.kind, StoryKind::Link);
+
+    for refused in [
+        of_kind(StoryKind::Link, ask("Link without URL")),
+        of_kind(StoryKind::Show, ask("Show without URL")),
+        of_kind(StoryKind::Ask, link("Ask with URL", "https://example.org/ask")),
+        of_kind(StoryKind::Poll, link("Poll with URL", "https://example.org/poll")),
+    ] {
+        assert!(matches!(
+            repo.story_insert(refused, author.id).await,
+            Err(LambdaError::ValidationError(_))
+        ));
+    }
+
+    let answered = repo.story_insert(ask("Answered ask"), author.id).await.unwrap();
+    let unanswered = repo.story_insert(ask("Unanswered ask"), author.id).await.unwrap();
+    repo.comment_insert(reply(answered.id, None, "answer"), voter.id).await.unwrap();
+    let asks: Vec<i32> = repo
+        .story_kind_page(StoryKind::Ask, 0, &StoryFilter::default())
+        .await
+        .unwrap()
+        .iter()
+        .map(|story| story.id)
+        .collect();
+    assert_eq!(asks[..3], [answered.id, unanswered.id, inferred.id]);
+
+    let show = |title: &str, url: &str| of_kind(StoryKind::Show, link(title, url));
+    let liked = repo.story_insert(show("Liked show", "https://example.org/liked"), author.id).await.unwrap();
+    let ignored = repo
+        .story_insert(show("Ignored show", "https://example.org/ignored"), author.id)
+        .await
+        .unwrap();
+    repo.vote_cast(Some(liked.id), None, VoteArgs { value: 1 }, voter.id).await.unwrap();
+    let shows = repo.story_kind_page(StoryKind::Show, 0, &StoryFilter::default()).await.unwrap();
+    assert_eq!(shows.iter().map(|story| story.id).collect::<Vec<_>>(), [liked.id, ignored.id]);
+    assert!(shows.iter().all(|story| story.kind == StoryKind::Show));
+
+    let job = of_kind(StoryKind::Job, link("Hiring", "https://example.org/jobs"));
+    let job = repo.story_insert(job, author.id).await.unwrap();
+    let jobs = repo.story_kind_page(StoryKind::Job, 0, &StoryFilter::default()).await.unwrap();
+    assert_eq!(jobs.iter().map(|story| story.id).collect::<Vec<_>>(), [job.id]);
+    assert_eq!(repo.story_by_id(job.id).await.unwrap().kind, StoryKind::Job);
+}
+
+async fn polls(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-pollster").await.unwrap();
+    let voter = repo.user_insert("conformance-poll-voter").await.unwrap();
+    let poll = |title: &str, options: &[&str], multiple: bool| {
+        let poll = PollArgs::builder()
+            .options(options.iter().map(|option| option.to_string()).collect())
+            .multiple(multiple)
+            .build();
+        StoryCreateArgs::builder()
+            .title(title.into())
+            .kind(StoryKind::Poll)
+            .poll(poll)
+            .build()
+    };
+
+    for refused in [
+        poll("Too few", &["Coq", " "], false),
+        poll("Repeated", &["Coq", "Coq"], false),
+        StoryCreateArgs { kind: None, ..poll("Not a poll", &["Coq", "Lean"], false) },
+    ] {
+        assert!(matches!(
+            repo.story_insert(refused, author.id).await,
+            Err(LambdaError::ValidationError(_))
+        ));
+    }
+
+    let single = repo
+        .story_insert(poll("Proof assistant?", &["Coq", "", "Lean", "Agda"], false), author.id)
+        .await
+        .unwrap();
+    assert_eq!(single.kind, StoryKind::Poll);
+    let results = repo.poll(single.id, None).await.unwrap();
+    let texts: Vec<&str> = results.options.iter().map(|option| option.text.as_str()).collect();
+    assert_eq!(texts, ["Coq", "Lean", "Agda"]);
+    assert_eq!((results.voters, results.closed, results.multiple), (0, false, false));
+    let [coq, lean, agda] = [0, 1, 2].map(|index| results.options[index].id);
+
+    assert!(matches!(
+        repo.poll_vote(single.id, voter.id, &[coq, lean]).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    let results = repo.poll_vote(single.id, voter.id, &[coq]).await.unwrap();
+    assert_eq!((results.voters, results.options[0].votes, results.ballot.clone()), (1, 1, vec![coq]));
+    let results = repo.poll_vote(single.id, voter.id, &[lean]).await.unwrap();
+    assert_eq!((results.options[0].votes, results.options[1].votes), (0, 1));
+    repo.poll_vote(single.id, author.id, &[lean]).await.unwrap();
+    let results = repo.poll(single.id, Some(author.id)).await.unwrap();
+    assert_eq!((results.voters, results.options[1].votes, results.ballot), (2, 2, vec![lean]));
+    assert!(repo.poll(single.id, None).await.unwrap().ballot.is_empty());
+
+    let multiple = repo
+        .story_insert(poll("Editors?", &["Emacs", "Vim"], true), author.id)
+        .await
+        .unwrap();
+    let options: Vec<i32> = repo.poll(multiple.id, None).await.unwrap().options.iter().map(|o| o.id).collect();
+    assert!(matches!(
+        repo.poll_vote(multiple.id, voter.id, &[agda]).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    assert!(matches!(
+        repo.poll_vote(multiple.id, voter.id, &[options[0], options[0]]).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    let results = repo.poll_vote(multiple.id, voter.id, &options).await.unwrap();
+    assert_eq!((results.voters, results.options[0].votes, results.options[1].votes), (1, 1, 1));
+    let results = repo.poll_vote(multiple.id, voter.id, &[]).await.unwrap();
+    assert_eq!((results.voters, results.ballot.len()), (0, 0));
+
+    let story = repo
+        .story_insert(link("Not a poll", "https://example.org/not-a-poll"), author.id)
+        .await
+        .unwrap();
+    assert!(matches!(repo.poll(story.id, None).await, Err(LambdaError::NotFound)));
+    assert!(matches!(
+        repo.poll_vote(story.id, voter.id, &[]).await,
+        Err(LambdaError::NotFound)
+    ));
+}
+
+async fn drafts(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-drafter").await.unwrap();
+    let other = repo.user_insert("conformance-other-drafter").await.unwrap();
+    let story = repo.story_insert(link("Drafted on", "https://example.org/drafted"), author.id).await.unwrap();
+    let comment = repo.comment_insert(reply(story.id, None, "drafted under"), other.id).await.unwrap();
+    let elsewhere = repo.story_insert(link("Elsewhere", "https://example.org/elsewhere"), author.id).await.unwrap();
+
+    let new_story = DraftArgs::builder().title("Half a title".into()).build();
+    repo.draft_save(author.id, new_story).await.unwrap();
+    let on_story = DraftArgs::builder().story_id(story.id).text("First go".into()).build();
+    repo.draft_save(author.id, on_story.clone()).await.unwrap();
+    let revised = DraftArgs { text: "Second go".into(), ..on_story };
+    repo.draft_save(author.id, revised).await.unwrap();
+    let answer = DraftArgs::builder().story_id(story.id).parent_id(comment.id).text("Indeed".into()).build();
+    repo.draft_save(author.id, answer).await.unwrap();
+
+    let drafts = repo.drafts(author.id, None).await.unwrap();
+    let texts: Vec<&str> = drafts.iter().map(|draft| draft.text.as_str()).collect();
+    assert_eq!(texts, ["Indeed", "Second go", ""]);
+    assert_eq!(drafts[1].story_title.as_deref(), Some("Drafted on"));
+    assert_eq!(drafts[2].title.as_deref(), Some("Half a title"));
+    assert!(repo.drafts(other.id, None).await.unwrap().is_empty());
+
+    let editor = repo.drafts(author.id, Some((Some(story.id), None))).await.unwrap();
+    assert_eq!(editor.len(), 1);
+    assert_eq!(editor[0].text, "Second go");
+
+    for refused in [
+        DraftArgs::builder().story_id(i32::MAX).text("Lost".into()).build(),
+        DraftArgs::builder().story_id(elsewhere.id).parent_id(comment.id).text("Misplaced".into()).build(),
+    ] {
+        assert!(matches!(repo.draft_save(author.id, refused).await, Err(LambdaError::NotFound)));
+    }
+
+    let blank = DraftArgs::builder().story_id(story.id).text("  ".into()).build();
+    repo.draft_save(author.id, blank).await.unwrap();
+    repo.draft_discard(author.id, None, None).await.unwrap();
+    repo.draft_discard(author.id, None, None).await.unwrap();
+    let drafts = repo.drafts(author.id, None).await.unwrap();
+    assert_eq!(drafts.len(), 1);
+    assert_eq!(drafts[0].parent_id, Some(comment.id));
+}
+
+async fn paging(repo: &dyn Repository) {
+    let author = repo.user_insert("conformance-paging").await.unwrap();
+    let before = repo.story_page_count(&StoryFilter::default()).await.unwrap();
+    for n in 0..PAGE_SIZE {
+        let story = StoryCreateArgs::builder()
+            .title(format!("Page filler {n}"))
+            .text("filler".into())
+            .build();
+        repo.story_insert(story, author.id).await.unwrap();
+    }
+
+    assert_eq!(repo.story_page_count(&StoryFilter::default()).await.unwrap(), before + 1);
+    let page = repo.story_page(0, &StoryFilter::default()).await.unwrap();
+    assert_eq!(page.len() as i64, PAGE_SIZE);
+    assert_eq!(page[0].title, format!("Page filler {}", PAGE_SIZE - 1));
+    assert!(!repo.story_page(1, &StoryFilter::default()).await.unwrap().is_empty());
+
+    // Hidden stories are left out before paging, so pages stay full.
+    let hidden = StoryFilter::builder().hidden(vec![page[0].id, page[1].id]).build();
+    let filtered = repo.story_page(0, &hidden).await.unwrap();
+    assert_eq!(filtered.len() as i64, PAGE_SIZE);
+    assert_eq!(filtered[0].id, page[2].id);
+    let filler = |n| format!("Page filler {n}");
+    let kind = repo.story_kind_page(StoryKind::Ask, 0, &hidden).await.unwrap();
+    assert!(kind.iter().all(|story| story.title != filler(PAGE_SIZE - 1)));
+    assert_eq!(kind.len() as i64, PAGE_SIZE);
+    assert!(repo.story_page_count(&hidden).await.unwrap() <= before + 1);
+}
+
+async fn conformance(repo: &dyn Repository) {
+    users(repo).await;
+    stories(repo).await;
+    duplicates(repo).await;
+    comments(repo).await;
+    mentions(repo).await;
+    votes(repo).await;
+    visits(repo).await;
+    tags(repo).await;
+    kinds(repo).await;
+    polls(repo).await;
+    drafts(repo).await;
+    paging(repo).await;
+}
+
+#[tokio::test]
+async fn memory_conforms() {
+    conformance(&MemoryRepo::new()).await;
+}
+
+#[tokio::test]
+async fn postgres_conforms() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return;
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let name = format!("news_repo_{nanos}");
+
+    let mut admin_conn = PgConnection::connect(&url).await.unwrap();
+    admin_conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str()).await.unwrap();
+
+    let (base, _) = url.rsplit_once('/').unwrap();
+    let pool = PgPool::connect(&format!("{base}/{name}")).await.unwrap();
+    admin::MIGRATOR.run(&pool).await.unwrap();
+    let fetcher = PreviewFetcher::builder().enabled(false).build();
+
+    let repo = PgRepo::new(pool.clone(), fetcher);
+    conformance(&repo).await;
+    renamed_mentions_follow_the_user(&repo, &pool).await;
+    favorites_are_private_to_their_owner(&repo, &pool).await;
+    browsers_ask_what_is_new_since_their_visits(&repo, &pool).await;
+    only_named_references_are_not_found(&repo, &pool).await;
+
+    pool.close().await;
+    admin_conn
+        .execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+        .await
+        .unwrap();
+}
+
+/// Mentions resolve to a user, not a name: after a rename they link to the
+/// new name, and a newcomer taking the old name doesn't inherit them.
+async fn renamed_mentions_follow_the_user(repo: &dyn Repository, pool: &PgPool) {
+    let author = repo.user_insert("rename-author").await.unwrap();
+    repo.user_insert("rename-before").await.unwrap();
+    let story = StoryCreateArgs::builder()
+        .title("Renamed".into())
+        .text("Hi @rename-before".into())
+        .build();
+    let story = repo.story_insert(story, author.id).await.unwrap();
+    let comment = repo
+        .comment_insert(reply(story.id, None, "@rename-before"), author.id)
+        .await
+        .unwrap();
+
+    assert_eq!(admin::user_rename(pool, "rename-before", "rename-after").await.unwrap(), 1);
+    repo.user_insert("rename-before").await.unwrap();
+
+    let html = repo.story_by_id(story.id).await.unwrap().text_html.unwrap();
+    assert!(html.contains(r#"<a href="/closure/rename-after">@rename-after</a>"#));
+    let comment = repo.comment_by_id(comment.id).await.unwrap();
+    assert_eq!(
+        comment.mentions,
+        [Mention {
+            name: "rename-before".into(),
+            user_name: "rename-after".into(),
+        }]
+    );
+
+    admin::markdown_rerender(pool, |_| {}).await.unwrap();
+    let html = repo.story_by_id(story.id).await.unwrap().text_html.unwrap();
+    assert!(html.contains("/closure/rename-after"));
+}
+
+/// Collections are hidden until made public, and even then anonymous viewers
+/// don't count as the owner and see no notes.
+async fn favorites_are_private_to_their_owner(repo: &dyn Repository, pool: &PgPool) {
+    let owner = repo.user_insert("collector").await.unwrap();
+    let story = repo.story_insert(link("Kept", "https://example.org/kept"), owner.id).await.unwrap();
+    let args = BookmarkArgs::builder().story_id(story.id).note("mine".into()).build();
+    bookmarks::bookmark_set(pool, owner.id, args).await.unwrap();
+
+    let anonymous = bookmarks::favorites(pool, None, Some("collector")).await;
+    assert_eq!(anonymous, Err(LambdaError::NotFound));
+    assert_eq!(bookmarks::favorites(pool, None, None).await, Err(LambdaError::NotFound));
+
+    bookmarks::favorites_set_public(pool, owner.id, true).await.unwrap();
+    let public = bookmarks::favorites(pool, None, Some("collector")).await.unwrap();
+    assert!(!public.owned);
+    assert_eq!(public.bookmarks[0].note, None);
+    let own = bookmarks::favorites(pool, Some(owner.id), None).await.unwrap();
+    assert!(own.owned);
+    assert_eq!(own.bookmarks[0].note.as_deref(), Some("mine"));
+}
+
+/// References a write names, like a story, read as not found; others point
+/// at a bug.
+async fn only_named_references_are_not_found(repo: &dyn Repository, pool: &PgPool) {
+    let author = repo.user_insert("reference-author").await.unwrap();
+    let story = repo.story_insert(link("Referenced", "https://example.org/referenced"), author.id).await.unwrap();
+
+    assert_eq!(
+        repo.comment_insert(reply(story.id, None, "ghost"), -1).await,
+        Err(LambdaError::NotFound)
+    );
+    assert_eq!(
+        audit::record(pool, Some(-1), audit::STORY_TAGGED, Some(story.id), serde_json::json!({})).await,
+        Err(LambdaError::InternalServerError)
+    );
+}
+
+/// Browsers keep when they last opened each story and send that along; the
+/// caller's own comments, when signed in, aren't new to them.
+async fn browsers_ask_what_is_new_since_their_visits(repo: &dyn Repository, pool: &PgPool) {
+    let reader = repo.user_insert("browser-reader").await.unwrap();
+    let author = repo.user_insert("browser-visited").await.unwrap();
+    let story = repo.story_insert(link("Browsed", "https://example.org/browsed"), author.id).await.unwrap();
+    let old = repo.comment_insert(reply(story.id, None, "before"), author.id).await.unwrap();
+    let seen_at = chrono::Local::now().fixed_offset();
+    let new = repo.comment_insert(reply(story.id, Some(old.id), "after"), author.id).await.unwrap();
+    let mine = repo.comment_insert(reply(story.id, None, "mine"), reader.id).await.unwrap();
+
+    let anonymous = visits::new_comments(pool, &[story.id], &[seen_at], None).await.unwrap();
+    assert_eq!(anonymous[0].new_comment_ids, [new.id, mine.id]);
+    let signed_in = visits::new_comments(pool, &[story.id], &[seen_at], Some(reader.id)).await.unwrap();
+    assert_eq!((signed_in[0].story_id, signed_in[0].new_comment_ids.as_slice()), (story.id, [new.id].as_slice()));
+    assert!(visits::new_comments(pool, &[], &[], None).await.unwrap().is_empty());
+    assert!(matches!(
+        visits::new_comments(pool, &[story.id], &[], None).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+}
+
+/// Server functions run against whatever [`Repo`] is in context, so they
+/// can be exercised without a database.
+#[tokio::test]
+async fn server_functions_use_the_repository() {
+    let repo = MemoryRepo::new();
+    // Browser requests act as the first user.
+    repo.user_insert("site").await.unwrap();
+
+    let owner = Owner::new();
+    owner.set();
+    provide_context(Repo::new(repo.clone()));
+    provide_context(axum::http::Request::new(()).into_parts().0);
+
+    let story = story_create(link("Served", "https://example.org/served")).await.unwrap();
+    // Anonymous browsers share the site user, so they keep drafts locally.
+    let draft = DraftArgs::builder().story_id(story.id).text("serv".into()).build();
+    assert!(draft_save(draft).await.is_err());
+    assert!(draft_get(Some(story.id), None).await.is_err());
+    comment_create(reply(story.id, None, "served")).await.unwrap();
+    assert!(repo.drafts(SITE_USER_ID, None).await.unwrap().is_empty());
+
+    let stories = story_list(None, None).await.unwrap();
+    assert_eq!(stories[0].id, story.id);
+    assert_eq!(stories[0].author_name, "site");
+    assert_eq!(stories[0].comment_count, Some(1));
+    assert_eq!(comment_list(story.id).await.unwrap()[0].text, "served");
+    assert_eq!(get_story_page_count(None).await.unwrap(), 1);
+
+    // Browsers track visits, hide stories and mute tags locally and send
+    // that along.
+    assert!(story_mark_seen(story.id).await.is_err());
+    assert!(story_hide(story.id, true).await.is_err());
+    assert_eq!(story_list(None, None).await.unwrap().len(), 1);
+    let hidden = StoryFilter::builder().hidden(vec![story.id]).build();
+    assert!(story_list(None, Some(hidden)).await.unwrap().is_empty());
+
+    repo.tag_create("muted", None).await.unwrap();
+    let tagged = StoryCreateArgs::builder()
+        .title("Muted".into())
+        .url("https://example.org/muted".into())
+        .tags(vec!["muted".into()])
+        .build();
+    let muted = story_create(tagged).await.unwrap();
+    assert_eq!(story_list(None, None).await.unwrap()[0].id, muted.id);
+    assert!(tag_prefer("muted".into(), Some(TagPreference::Mute)).await.is_err());
+    let filter = StoryFilter::builder().muted(vec!["muted".into()]).build();
+    let stories = story_list(None, Some(filter.clone())).await.unwrap();
+    assert_eq!(stories.iter().map(|story| story.id).collect::<Vec<_>>(), [story.id]);
+    assert_eq!(story_kind_list(StoryKind::Link, None, Some(filter)).await.unwrap()[0].id, story.id);
+}
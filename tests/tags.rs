@@ -0,0 +1,54 @@
+#![cfg(feature = "ssr")]
+
+use chrono::DateTime;
+use news::{
+    features::tag::{is_tag_name, normalize_tags},
+    model::StoryListItem,
+    server::tags::tag_feed,
+};
+
+#[test]
+fn tag_names_are_lowercase_slugs() {
+    assert!(is_tag_name("type-theory"));
+    assert!(is_tag_name("c99"));
+    assert!(!is_tag_name(""));
+    assert!(!is_tag_name("Rust"));
+    assert!(!is_tag_name("type theory"));
+    assert!(!is_tag_name("-rust"));
+    assert!(!is_tag_name(&"a".repeat(33)));
+}
+
+#[test]
+fn story_tags_are_distinct_and_sorted() {
+    let names = ["rust", " haskell", "rust", ""].map(String::from);
+    assert_eq!(normalize_tags(&names), ["haskell", "rust"]);
+}
+
+#[test]
+fn feeds_list_tagged_stories() {
+    let story = StoryListItem::builder()
+        .id(7)
+        .title("Lenses".into())
+        .url("https://example.org/lenses".into())
+        .created_at(DateTime::parse_from_rfc3339("2025-04-25T12:00:00+00:00").unwrap())
+        .author_name("ada".into())
+        .rating(Some(3))
+        .comment_count(Some(1))
+        .tags(vec!["category-theory".into(), "haskell".into()])
+        .build();
+
+    let feed = tag_feed("haskell", &[story], &[], "Lambda Function", "https://news.example");
+    assert_eq!(feed.id(), "https://news.example/tag/haskell");
+    assert_eq!(feed.title().as_str(), "haskell on Lambda Function");
+    assert_eq!(feed.links()[0].href(), "https://news.example/tag/haskell/feed.atom");
+    assert_eq!(feed.updated().to_rfc3339(), "2025-04-25T12:00:00+00:00");
+
+    let [entry] = feed.entries() else {
+        panic!("expected one entry");
+    };
+    assert_eq!(entry.id(), "https://news.example/bind/7");
+    assert_eq!(entry.links()[1].href(), "https://example.org/lenses");
+    assert_eq!(entry.authors()[0].name(), "ada");
+    let terms: Vec<&str> = entry.categories().iter().map(|category| category.term()).collect();
+    assert_eq!(terms, ["category-theory", "haskell"]);
+}
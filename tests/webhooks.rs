@@ -0,0 +1,196 @@
+#![cfg(feature = "ssr")]
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use news::{
+    model::{LambdaError, WebhookCreateArgs, WebhookEvent},
+    server::{
+        admin,
+        auth::SITE_USER_ID,
+        webhooks::{
+            delivery_list, enqueue, sign, webhook_create, webhook_delete, Attempt, WebhookDispatcher,
+            DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER,
+        },
+    },
+};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECRET: &str = "correct horse battery staple";
+
+type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+async fn receive(State(received): State<Received>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    let valid = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|signature| signature == sign(SECRET, &body));
+    received.lock().unwrap().push((headers, body));
+    if valid {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::UNAUTHORIZED
+    }
+}
+
+async fn serve() -> (SocketAddr, Received) {
+    let received = Received::default();
+    let app = Router::new()
+        .route("/hook", post(receive))
+        .route("/broken", post(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+        .route("/accept", post(|| async { StatusCode::NO_CONTENT }))
+        .with_state(received.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (addr, received)
+}
+
+fn dispatcher() -> WebhookDispatcher {
+    WebhookDispatcher::builder().allow_private(true).build()
+}
+
+#[tokio::test]
+async fn delivers_signed_payloads() {
+    let (addr, received) = serve().await;
+    let body = br#"{"event":"story.created","data":{"id":1}}"#.to_vec();
+
+    let attempt = dispatcher()
+        .deliver(&format!("http://{addr}/hook"), SECRET, 7, "story.created", body.clone())
+        .await;
+
+    assert_eq!(attempt, Attempt::Delivered(204));
+    let received = received.lock().unwrap();
+    let (headers, received_body) = &received[0];
+    assert_eq!(received_body.as_ref(), body.as_slice());
+    assert_eq!(headers[EVENT_HEADER], "story.created");
+    assert_eq!(headers[DELIVERY_HEADER], "7");
+}
+
+#[tokio::test]
+async fn reports_rejected_signatures() {
+    let (addr, _) = serve().await;
+
+    let attempt = dispatcher()
+        .deliver(&format!("http://{addr}/hook"), "wrong secret", 1, "comment.created", b"{}".to_vec())
+        .await;
+
+    assert!(matches!(attempt, Attempt::Failed(Some(401), _)));
+}
+
+#[tokio::test]
+async fn reports_receiver_errors() {
+    let (addr, _) = serve().await;
+
+    let attempt = dispatcher()
+        .deliver(&format!("http://{addr}/broken"), SECRET, 1, "story.created", b"{}".to_vec())
+        .await;
+
+    assert!(matches!(attempt, Attempt::Failed(Some(500), _)));
+}
+
+#[tokio::test]
+async fn refuses_private_receivers_by_default() {
+    let (addr, received) = serve().await;
+
+    let attempt = WebhookDispatcher::default()
+        .deliver(&format!("http://{addr}/hook"), SECRET, 1, "story.created", b"{}".to_vec())
+        .await;
+
+    assert!(matches!(attempt, Attempt::Failed(None, _)));
+    assert!(received.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn deliveries_bypass_configured_proxies() {
+    let (addr, received) = serve().await;
+    // Nothing listens there; a proxy would also skip the resolver's checks.
+    std::env::set_var("HTTP_PROXY", "http://127.0.0.1:9");
+
+    let attempt = dispatcher()
+        .deliver(&format!("http://{addr}/hook"), SECRET, 1, "story.created", b"{}".to_vec())
+        .await;
+
+    assert_eq!(attempt, Attempt::Delivered(204));
+    assert_eq!(received.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn signature_is_hex_hmac_sha256() {
+    assert_eq!(
+        sign("key", b"The quick brown fox jumps over the lazy dog"),
+        "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+}
+
+#[tokio::test]
+async fn tick_skips_deleted_webhooks() {
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return;
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let name = format!("news_webhooks_{nanos}");
+
+    let mut admin_conn = PgConnection::connect(&url).await.unwrap();
+    admin_conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str()).await.unwrap();
+
+    let (base, _) = url.rsplit_once('/').unwrap();
+    let pool = PgPool::connect(&format!("{base}/{name}")).await.unwrap();
+    admin::MIGRATOR.run(&pool).await.unwrap();
+
+    let user = admin::user_create(&pool, "subscriber", None, false).await.unwrap();
+    let (addr, _) = serve().await;
+    let subscribe = |path: &str| {
+        WebhookCreateArgs::builder()
+            .url(format!("http://{addr}/{path}"))
+            .moderation_action(true)
+            .build()
+    };
+    let live = webhook_create(&pool, user.id, subscribe("accept"), true).await.unwrap();
+    let gone = webhook_create(&pool, user.id, subscribe("broken"), true).await.unwrap();
+    assert!(matches!(
+        webhook_create(&pool, SITE_USER_ID, subscribe("accept"), true).await,
+        Err(LambdaError::ValidationError(_))
+    ));
+    // Left from when any browser could subscribe as the site user.
+    sqlx::query("UPDATE webhooks SET user_id = $1 WHERE id = $2")
+        .bind(SITE_USER_ID)
+        .bind(gone.webhook.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let event = WebhookEvent::ModerationAction;
+    assert_eq!(enqueue(&pool, event, &serde_json::json!({})).await.unwrap(), 1);
+    sqlx::query("UPDATE webhooks SET user_id = $1 WHERE id = $2")
+        .bind(user.id)
+        .bind(gone.webhook.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(enqueue(&pool, event, &serde_json::json!({})).await.unwrap(), 2);
+    webhook_delete(&pool, user.id, gone.webhook.id).await.unwrap();
+
+    assert_eq!(dispatcher().tick(&pool).await.unwrap(), 2);
+    assert_eq!(dispatcher().tick(&pool).await.unwrap(), 0);
+    let delivered = delivery_list(&pool, user.id, live.webhook.id).await.unwrap();
+    assert!(delivered.iter().all(|delivery| (delivery.status.as_str(), delivery.attempts) == ("delivered", 1)));
+    let skipped = delivery_list(&pool, user.id, gone.webhook.id).await.unwrap();
+    assert_eq!((skipped[0].status.as_str(), skipped[0].attempts), ("pending", 0));
+
+    pool.close().await;
+    admin_conn
+        .execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+        .await
+        .unwrap();
+}